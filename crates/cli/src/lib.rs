@@ -33,13 +33,13 @@ pub enum Command {
     },
     /// Update the configuration by introspecting the database, using the configuration options.
     Update,
-    // /// Upgrade the configuration to the latest version. This does not involve the database.
-    // Upgrade {
-    //     #[arg(long)]
-    //     dir_from: PathBuf,
-    //     #[arg(long)]
-    //     dir_to: PathBuf,
-    // },
+    /// Upgrade the configuration to the latest version. This does not involve the database.
+    Upgrade {
+        #[arg(long)]
+        dir_from: PathBuf,
+        #[arg(long)]
+        dir_to: PathBuf,
+    },
 }
 
 /// The set of errors that can go wrong _in addition to_ generic I/O or parsing errors.
@@ -54,7 +54,7 @@ pub async fn run(command: Command, context: Context<impl Environment>) -> anyhow
     match command {
         Command::Initialize { with_metadata } => initialize(with_metadata, context).await?,
         Command::Update => update(context).await?,
-        // Command::Upgrade { dir_from, dir_to } => upgrade(dir_from, dir_to).await?,
+        Command::Upgrade { dir_from, dir_to } => upgrade(dir_from, dir_to).await?,
     };
     Ok(())
 }
@@ -164,6 +164,7 @@ async fn update(context: Context<impl Environment>) -> anyhow::Result<()> {
         if input_again_before_write == existing_configuration {
             // In order to be sure to capture default values absent in the initial input we have to
             // always write out the updated configuration.
+            warn_stale_metadata(&output);
             configuration::write_parsed_configuration(output, &context.context_path).await?;
             return Ok(());
         }
@@ -177,15 +178,32 @@ async fn update(context: Context<impl Environment>) -> anyhow::Result<()> {
     ))
 }
 
-// /// Upgrade the configuration in a directory by trying to read it and then write it back
-// /// out to a different directory.
-// ///
-// async fn upgrade(dir_from: PathBuf, dir_to: PathBuf) -> anyhow::Result<()> {
-//     let old_configuration = configuration::parse_configuration(dir_from).await?;
-//     let upgraded_configuration = configuration::upgrade_to_latest_version(old_configuration);
-//     configuration::write_parsed_configuration(upgraded_configuration, dir_to).await?;
+/// Tell the user about the tables and columns introspection did not find anymore. They are kept
+/// in the configuration, flagged as stale, for the user to remove or fix.
+fn warn_stale_metadata(configuration: &configuration::ParsedConfiguration) {
+    for (table_name, table_info) in &configuration.metadata().tables.0 {
+        if table_info.stale {
+            tracing::warn!("table '{table_name}' was not found, it is flagged as stale.");
+            continue;
+        }
+        for (column_name, column_info) in &table_info.columns {
+            if column_info.stale {
+                tracing::warn!(
+                    "column '{column_name}' of table '{table_name}' was not found in the sampled items, it is flagged as stale."
+                );
+            }
+        }
+    }
+}
+
+/// Upgrade the configuration in a directory by trying to read it and then write it back
+/// out to a different directory.
+async fn upgrade(dir_from: PathBuf, dir_to: PathBuf) -> anyhow::Result<()> {
+    let old_configuration = configuration::parse_configuration(dir_from).await?;
+    let upgraded_configuration = configuration::upgrade_to_latest_version(old_configuration);
+    configuration::write_parsed_configuration(upgraded_configuration, dir_to).await?;
 
-//     eprintln!("Upgrade completed successfully. You may need to also run 'update'.");
+    eprintln!("Upgrade completed successfully. You may need to also run 'update'.");
 
-//     Ok(())
-// }
+    Ok(())
+}
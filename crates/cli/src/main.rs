@@ -34,6 +34,16 @@ pub struct Args {
 
 #[tokio::main]
 pub async fn main() -> ExitCode {
+    // warnings, such as those about stale metadata, go to stderr, leaving stdout to the output
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .with_target(false)
+        .without_time()
+        .init();
     if let Err(err) = try_main().await {
         // The default formatting for anyhow in our case includes a 'Caused by' section
         // that duplicates what's already in the error message, so we don't display it.
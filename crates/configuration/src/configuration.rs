@@ -1,10 +1,151 @@
+use std::num::NonZeroU32;
+use std::path::Path;
+
 use query_engine_metadata::metadata;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::environment::Environment;
+use crate::error::{ParseConfigurationError, WriteParsedConfigurationError};
+use crate::{version1, version2};
 
 pub const CONFIGURATION_FILENAME: &str = "configuration.json";
 pub const CONFIGURATION_JSONSCHEMA_FILENAME: &str = "schema.json";
 
 pub const DEFAULT_CONNECTION_URI_VARIABLE: &str = "CONNECTION_URI";
 
+/// The configuration format, in any of its versions, told apart by the `version` field.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ParsedConfiguration {
+    Version1(version1::ParsedConfiguration),
+    Version2(version2::ParsedConfiguration),
+}
+
+impl ParsedConfiguration {
+    /// An initial configuration in the latest version.
+    pub fn initial() -> Self {
+        ParsedConfiguration::Version2(version2::ParsedConfiguration::empty())
+    }
+
+    pub fn metadata(&self) -> &metadata::Metadata {
+        match self {
+            ParsedConfiguration::Version1(configuration) => &configuration.metadata,
+            ParsedConfiguration::Version2(configuration) => &configuration.metadata,
+        }
+    }
+}
+
+/// Introspect the database, keeping the version of the configuration.
+pub async fn introspect(
+    input: &ParsedConfiguration,
+    environment: impl Environment,
+) -> anyhow::Result<ParsedConfiguration> {
+    match input {
+        ParsedConfiguration::Version1(configuration) => Ok(ParsedConfiguration::Version1(
+            version1::introspect(configuration, environment).await?,
+        )),
+        ParsedConfiguration::Version2(configuration) => Ok(ParsedConfiguration::Version2(
+            version2::introspect(configuration, environment).await?,
+        )),
+    }
+}
+
+/// Parse the configuration format from a directory.
+pub async fn parse_configuration(
+    configuration_dir: impl AsRef<Path> + Send,
+) -> Result<ParsedConfiguration, ParseConfigurationError> {
+    let configuration_file = configuration_dir.as_ref().join(CONFIGURATION_FILENAME);
+
+    let configuration_file_contents =
+        fs::read_to_string(&configuration_file)
+            .await
+            .map_err(|err| {
+                ParseConfigurationError::IoErrorButStringified(format!(
+                    "{}: {}",
+                    &configuration_file.display(),
+                    err
+                ))
+            })?;
+
+    parse_configuration_contents(&configuration_file, &configuration_file_contents)
+}
+
+/// Parse the contents of a configuration file with the format of the version it declares.
+fn parse_configuration_contents(
+    configuration_file: &Path,
+    configuration_file_contents: &str,
+) -> Result<ParsedConfiguration, ParseConfigurationError> {
+    let parse_error = |error: serde_json::Error| ParseConfigurationError::ParseError {
+        file_path: configuration_file.to_path_buf(),
+        line: error.line(),
+        column: error.column(),
+        message: error.to_string(),
+    };
+
+    // Read the version first, to parse the rest with the format of that version.
+    let configuration: serde_json::Value =
+        serde_json::from_str(configuration_file_contents).map_err(parse_error)?;
+    let version = configuration.get("version");
+    match version.and_then(serde_json::Value::as_u64) {
+        Some(1) => serde_json::from_str(configuration_file_contents)
+            .map(ParsedConfiguration::Version1)
+            .map_err(parse_error),
+        Some(2) => serde_json::from_str(configuration_file_contents)
+            .map(ParsedConfiguration::Version2)
+            .map_err(parse_error),
+        _ => Err(ParseConfigurationError::DidNotFindExpectedVersionTag(
+            version.map(ToString::to_string).unwrap_or_default(),
+        )),
+    }
+}
+
+/// Write the parsed configuration into a directory on disk.
+pub async fn write_parsed_configuration(
+    parsed_config: ParsedConfiguration,
+    out_dir: impl AsRef<Path>,
+) -> Result<(), WriteParsedConfigurationError> {
+    let configuration_file = out_dir.as_ref().to_owned().join(CONFIGURATION_FILENAME);
+    fs::create_dir_all(out_dir.as_ref()).await?;
+
+    // create the configuration file
+    fs::write(
+        configuration_file,
+        serde_json::to_string_pretty(&parsed_config)
+            .map_err(|e| WriteParsedConfigurationError::IoError(e.into()))?
+            + "\n",
+    )
+    .await?;
+
+    // // create the jsonschema file
+    // let configuration_jsonschema_file_path = out_dir
+    //     .as_ref()
+    //     .to_owned()
+    //     .join(CONFIGURATION_JSONSCHEMA_FILENAME);
+
+    // let output = schemars::schema_for!(ParsedConfiguration);
+    // fs::write(
+    //     &configuration_jsonschema_file_path,
+    //     serde_json::to_string_pretty(&output)
+    //         .map_err(|e| WriteParsedConfigurationError::IoError(e.into()))?
+    //         + "\n",
+    // )
+    // .await?;
+
+    Ok(())
+}
+
+/// Upgrade a configuration to the latest version. This does not involve the database.
+pub fn upgrade_to_latest_version(parsed_config: ParsedConfiguration) -> ParsedConfiguration {
+    match parsed_config {
+        ParsedConfiguration::Version1(configuration) => {
+            ParsedConfiguration::Version2(version2::upgrade_from_v1(configuration))
+        }
+        ParsedConfiguration::Version2(_) => parsed_config,
+    }
+}
+
 /// The 'Configuration' type collects all the information necessary to serve queries at runtime.
 ///
 /// 'ParsedConfiguration' deals with a multitude of different concrete version formats, and each
@@ -23,5 +164,177 @@ pub struct Configuration {
     pub secret_access_key: String,
     // pub provider_name: String,
     pub region: String,
+    /// The endpoint to send requests to instead of the endpoint of the region.
+    pub endpoint_url: Option<String>,
     // pub mutations_version: Option<metadata::mutations::MutationsVersion>,
+    pub explain_options: ExplainOptions,
+    pub query_options: QueryOptions,
+}
+
+/// Options for the `/query/explain` and `/mutation/explain` endpoints.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainOptions {
+    /// Show the values of parameters and of the keys of affected items instead of only their
+    /// types.
+    #[serde(default)]
+    pub show_parameter_values: bool,
+}
+
+impl ExplainOptions {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// The default bound on the join keys of an `exists` filter across a relationship: as many
+/// values as a single DynamoDB `IN` condition takes.
+const DEFAULT_MAX_EXISTS_JOIN_KEYS: usize = 50;
+
+/// The default bound on the items of a query ordered in memory.
+const DEFAULT_MAX_ITEMS_SORTED_IN_MEMORY: usize = 10_000;
+
+/// Options for running queries.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryOptions {
+    /// The most distinct join keys an `exists` filter across a relationship reads from the
+    /// matching items of the target collection. Queries matching more fail rather than filter
+    /// on a statement DynamoDB would reject. The keys are all in the condition of a single
+    /// statement, with at most 50 values per `IN` condition, so raising it makes longer
+    /// statements, up to the size DynamoDB accepts.
+    #[serde(default = "default_max_exists_join_keys")]
+    pub max_exists_join_keys: usize,
+    /// The most items a query whose ordering DynamoDB cannot do itself reads and sorts in memory.
+    /// Queries matching more fail rather than hold every item.
+    #[serde(default = "default_max_items_sorted_in_memory")]
+    pub max_items_sorted_in_memory: usize,
+}
+
+fn default_max_exists_join_keys() -> usize {
+    DEFAULT_MAX_EXISTS_JOIN_KEYS
+}
+
+fn default_max_items_sorted_in_memory() -> usize {
+    DEFAULT_MAX_ITEMS_SORTED_IN_MEMORY
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        QueryOptions {
+            max_exists_join_keys: DEFAULT_MAX_EXISTS_JOIN_KEYS,
+            max_items_sorted_in_memory: DEFAULT_MAX_ITEMS_SORTED_IN_MEMORY,
+        }
+    }
+}
+
+impl QueryOptions {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// The default number of items sampled from each table by introspection.
+const DEFAULT_SAMPLE_SIZE: NonZeroU32 = match NonZeroU32::new(20) {
+    Some(n) => n,
+    None => unreachable!(),
+};
+
+/// Options for introspecting the database with `update`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IntrospectionOptions {
+    /// The number of items sampled from each table to infer the attributes beyond its keys.
+    #[serde(default = "default_sample_size")]
+    pub sample_size: NonZeroU32,
+}
+
+fn default_sample_size() -> NonZeroU32 {
+    DEFAULT_SAMPLE_SIZE
+}
+
+impl Default for IntrospectionOptions {
+    fn default() -> Self {
+        IntrospectionOptions {
+            sample_size: default_sample_size(),
+        }
+    }
+}
+
+impl IntrospectionOptions {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_configuration_contents, ParsedConfiguration};
+    use crate::error::ParseConfigurationError;
+    use std::path::Path;
+
+    fn parse(
+        configuration: &serde_json::Value,
+    ) -> Result<ParsedConfiguration, ParseConfigurationError> {
+        parse_configuration_contents(Path::new("configuration.json"), &configuration.to_string())
+    }
+
+    fn configuration(version: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "version": version,
+            "connectionSettings": {
+                "accessKeyId": { "variable": "ACCESS_KEY_ID" },
+                "secretAccessKey": { "variable": "SECRET_ACCESS_KEY" },
+                "region": "us-east-1",
+            },
+        })
+    }
+
+    fn version_tag_error(configuration: &serde_json::Value) -> String {
+        match parse(configuration) {
+            Err(ParseConfigurationError::DidNotFindExpectedVersionTag(version)) => version,
+            result => panic!("expected a version tag error, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_configuration_of_each_version() {
+        let version1 = parse(&configuration(serde_json::json!(1))).unwrap();
+        assert!(matches!(version1, ParsedConfiguration::Version1(_)));
+
+        let mut v2 = configuration(serde_json::json!(2));
+        v2["connectionSettings"]["endpointUrl"] = serde_json::json!("http://localhost:8000");
+        v2["introspectionOptions"] = serde_json::json!({ "sampleSize": 50 });
+        match parse(&v2).unwrap() {
+            ParsedConfiguration::Version2(configuration) => {
+                assert!(configuration.connection_settings.endpoint_url.is_some());
+                assert_eq!(configuration.introspection_options.sample_size.get(), 50);
+            }
+            configuration => panic!("expected version 2, got {configuration:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_configuration_of_unknown_or_missing_versions() {
+        assert_eq!(version_tag_error(&configuration(serde_json::json!(3))), "3");
+        assert_eq!(
+            version_tag_error(&configuration(serde_json::json!("2"))),
+            "\"2\""
+        );
+
+        let mut missing = configuration(serde_json::Value::Null);
+        missing.as_object_mut().unwrap().remove("version");
+        assert_eq!(version_tag_error(&missing), "");
+    }
+
+    #[test]
+    fn test_parse_configuration_of_the_wrong_shape() {
+        let mut configuration = configuration(serde_json::json!(2));
+        configuration["introspectionOptions"] = serde_json::json!({ "sampleSize": 0 });
+        let result = parse(&configuration);
+        assert!(
+            matches!(result, Err(ParseConfigurationError::ParseError { .. })),
+            "{result:?}"
+        );
+    }
 }
@@ -73,4 +73,36 @@ pub enum MakeRuntimeConfigurationError {
         file_path: std::path::PathBuf,
         message: String,
     },
+
+    #[error("invalid version attribute \"{version_attribute}\" on table \"{table}\": {message}")]
+    InvalidVersionAttribute {
+        table: String,
+        version_attribute: String,
+        message: String,
+    },
+
+    #[error("invalid native query \"{name}\": {message}")]
+    InvalidNativeQuery { name: String, message: String },
+
+    #[error("invalid native mutation \"{name}\": {message}")]
+    InvalidNativeMutation { name: String, message: String },
+
+    #[error("invalid entity \"{name}\": {message}")]
+    InvalidEntity { name: String, message: String },
+
+    #[error("invalid key template of attribute \"{attribute}\" on \"{collection}\": {message}")]
+    InvalidKeyTemplate {
+        /// The section of the metadata holding the collection, `tables` or `entities`.
+        section: String,
+        collection: String,
+        attribute: String,
+        message: String,
+    },
+
+    #[error("invalid foreign key \"{foreign_key}\" on table \"{table}\": {message}")]
+    InvalidForeignKey {
+        table: String,
+        foreign_key: String,
+        message: String,
+    },
 }
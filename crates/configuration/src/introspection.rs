@@ -0,0 +1,883 @@
+//! Introspection of the database, shared by the versions of the configuration format: the
+//! tables, their attributes and the scalar types of the metadata.
+
+use crate::configuration::IntrospectionOptions;
+use crate::error::ParseConfigurationError;
+use aws_sdk_dynamodb::types::{AttributeValue, KeyType};
+use aws_sdk_dynamodb::Config;
+use ndc_models::{
+    AggregateFunctionName, CollectionName, ComparisonOperatorName, FieldName, ObjectTypeName,
+    ScalarTypeName,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+use query_engine_metadata::metadata::{
+    self, database, ColumnInfo, CompositeTypes, Nullable, ProjectionTypeInfo, ScalarTypes,
+    TablesInfo,
+};
+
+/// Make a DynamoDB client with explicit credentials, using the endpoint of the region unless an
+/// endpoint such as `http://localhost:8000` for DynamoDB Local is given.
+pub(crate) fn make_client(
+    access_key_id: &str,
+    secret_access_key: &str,
+    region: &str,
+    endpoint_url: Option<&str>,
+) -> aws_sdk_dynamodb::Client {
+    let credentials = aws_sdk_dynamodb::config::Credentials::new(
+        access_key_id.to_string(),
+        secret_access_key.to_string(),
+        None,          // Optional session token
+        None,          // Expiration (None for non-expiring)
+        "my-provider", // Provider name
+    );
+
+    // Configure AWS SDK with explicit credentials
+    let config = Config::builder()
+        .region(aws_config::Region::new(region.to_string()))
+        .credentials_provider(credentials)
+        .set_endpoint_url(endpoint_url.map(ToString::to_string))
+        .behavior_version_latest()
+        .build();
+
+    aws_sdk_dynamodb::Client::from_conf(config)
+}
+
+/// Introspect the tables of the database, sampling items of each table to infer the attributes
+/// beyond the keys, and merge them with the previous metadata.
+pub(crate) async fn introspect_metadata(
+    client: &aws_sdk_dynamodb::Client,
+    previous_metadata: &metadata::Metadata,
+    introspection_options: &IntrospectionOptions,
+) -> anyhow::Result<metadata::Metadata> {
+    let sample_size = i32::try_from(introspection_options.sample_size.get()).unwrap_or(i32::MAX);
+    let tables_result = client.list_tables().send().await;
+    let tables = tables_result
+        .map_err(|_op| {
+            ParseConfigurationError::IoErrorButStringified("Failed to list tables".to_string())
+        })
+        .unwrap(); //TODO: handle error
+    let table_names = tables.table_names.unwrap_or_default();
+    let mut scalars_list: BTreeSet<ScalarTypeName> = BTreeSet::new();
+    let mut tables_info: BTreeMap<CollectionName, metadata::TableInfo> = BTreeMap::new();
+    let mut composite_types: BTreeMap<ObjectTypeName, metadata::CompositeType> = BTreeMap::new();
+    for table_name in table_names {
+        let table_result = client.describe_table().table_name(table_name).send().await;
+        let table = table_result.unwrap(); //TODO: handle error
+        let table = table.table.unwrap();
+        let table_name = table.table_name.unwrap();
+        let attribute_definitions = table.attribute_definitions.unwrap();
+        let mut columns_info: BTreeMap<FieldName, ColumnInfo> = BTreeMap::new();
+        for columns in attribute_definitions {
+            let scalar_name = columns.attribute_name;
+            let scalar_field_name = FieldName::new(scalar_name.clone().into());
+            let scalar_type_attribute = columns.attribute_type;
+            let scalar_type_name = match scalar_type_attribute.as_str() {
+                "S" => ScalarTypeName::new("String".into()),
+                "N" => ScalarTypeName::new("Number".into()),
+                "B" => ScalarTypeName::new("Binary".into()),
+                _ => ScalarTypeName::new("Any".into()),
+            };
+            scalars_list.insert(scalar_type_name.clone());
+            let scalar_type = metadata::Type::ScalarType(scalar_type_name);
+            let column_info = ColumnInfo {
+                name: scalar_name,
+                r#type: scalar_type,
+                nullable: Nullable::Nullable,
+                description: None,
+                stale: false,
+            };
+            columns_info.insert(scalar_field_name, column_info);
+        }
+
+        //get non key attributes
+        let result = client
+            .execute_statement()
+            .statement(format!(r#"select * from {table_name}"#))
+            .set_parameters(None)
+            .set_limit(Some(sample_size))
+            .send()
+            .await
+            .unwrap();
+
+        for item in &result.items.unwrap() {
+            for (key, attribute_value) in item {
+                let column_name = FieldName::new(key.clone().into());
+                let column_type = infer_attribute_type(
+                    &format!("{table_name}_{key}"),
+                    attribute_value,
+                    &mut scalars_list,
+                    &mut composite_types,
+                );
+                let column_info = ColumnInfo {
+                    name: key.clone(),
+                    r#type: column_type,
+                    nullable: Nullable::Nullable,
+                    description: None,
+                    stale: false,
+                };
+                columns_info.insert(column_name, column_info);
+            }
+        }
+
+        let previous_table_info = previous_metadata
+            .tables
+            .0
+            .get(&CollectionName::new(table_name.clone().into()));
+        if let Some(previous_table_info) = previous_table_info {
+            columns_info = merge_columns(previous_table_info, columns_info, &mut scalars_list);
+        }
+
+        //
+        let mut key_info: BTreeMap<KeyType, String> = BTreeMap::new();
+        let key_schema = table.key_schema.unwrap();
+        for key in key_schema {
+            let name = key.attribute_name;
+            let key_type = key.key_type;
+
+            if key_type == KeyType::Hash || key_type == KeyType::Range {
+                key_info.insert(key_type, name);
+            }
+        }
+        let partition_key = key_info.get(&KeyType::Hash).unwrap();
+        let sort_key = key_info.get(&KeyType::Range).unwrap();
+
+        let mut gsi_indexes: BTreeMap<String, metadata::GlobalSecondaryIndexInfo> = BTreeMap::new();
+        let gsis = table.global_secondary_indexes.unwrap();
+        for gsi in gsis {
+            let index_name = gsi.index_name.unwrap();
+            let mut index_keys_info: BTreeMap<KeyType, String> = BTreeMap::new();
+            let index_keys = gsi.key_schema.unwrap();
+            for key in index_keys {
+                let name = key.attribute_name;
+                let key_type = key.key_type;
+
+                if key_type == KeyType::Hash || key_type == KeyType::Range {
+                    index_keys_info.insert(key_type, name);
+                }
+            }
+            let partition_key = index_keys_info.get(&KeyType::Hash).unwrap();
+            let sort_key: Option<String> = index_keys_info.get(&KeyType::Range).cloned();
+
+            let projection_type = gsi
+                .projection
+                .clone()
+                .unwrap()
+                .projection_type
+                .unwrap()
+                .as_str()
+                .to_string();
+            let non_key_attributes = gsi
+                .projection
+                .unwrap()
+                .non_key_attributes
+                .unwrap_or_default();
+            gsi_indexes.insert(
+                index_name,
+                metadata::GlobalSecondaryIndexInfo {
+                    partition_key: partition_key.to_owned(),
+                    sort_key,
+                    projection_type: ProjectionTypeInfo {
+                        projection_type,
+                        non_key_attributes,
+                    },
+                },
+            );
+        }
+        let table_info = metadata::TableInfo {
+            table_name: table_name.clone(),
+            columns: columns_info,
+            partition_key: partition_key.to_owned(),
+            sort_key: sort_key.to_owned(),
+            gsi: metadata::GlobalSecondaryIndexes(gsi_indexes),
+            // keep the description, version attribute, scan policy, foreign keys, key templates
+            // and excluded columns configured by the user
+            description: previous_table_info.and_then(|table_info| table_info.description.clone()),
+            version_attribute: previous_table_info
+                .and_then(|table_info| table_info.version_attribute.clone()),
+            scan_policy: previous_table_info
+                .map(|table_info| table_info.scan_policy.clone())
+                .unwrap_or_default(),
+            foreign_relations: previous_table_info
+                .map(|table_info| table_info.foreign_relations.clone())
+                .unwrap_or_default(),
+            key_templates: previous_table_info
+                .map(|table_info| table_info.key_templates.clone())
+                .unwrap_or_default(),
+            excluded_columns: previous_table_info
+                .map(|table_info| table_info.excluded_columns.clone())
+                .unwrap_or_default(),
+            stale: false,
+            entity: None,
+        };
+        tables_info.insert(CollectionName::new(table_name.into()), table_info);
+    }
+    // Tables that were not found may have been deleted, or be out of reach of the credentials.
+    // They are kept, flagged for the user to check.
+    for (collection_name, previous_table_info) in &previous_metadata.tables.0 {
+        if !tables_info.contains_key(collection_name) {
+            for column_info in previous_table_info.columns.values() {
+                insert_scalar_types(&column_info.r#type, &mut scalars_list);
+            }
+            tables_info.insert(
+                collection_name.clone(),
+                metadata::TableInfo {
+                    stale: true,
+                    ..previous_table_info.clone()
+                },
+            );
+        }
+    }
+    let composite_types = merge_composite_types(
+        &previous_metadata.composite_types,
+        composite_types,
+        &mut scalars_list,
+    );
+    // Scalars
+    // the arguments of `_attribute_type`, `_is_missing`, `_exists` and the `_size_*` operators
+    scalars_list.insert(ScalarTypeName::new("String".into()));
+    scalars_list.insert(ScalarTypeName::new("Boolean".into()));
+    scalars_list.insert(ScalarTypeName::new("Number".into()));
+    let scalars: BTreeMap<ScalarTypeName, metadata::ScalarType> = scalars_list
+        .into_iter()
+        .map(|scalar| {
+            let mut scalar_type = make_scalar_type(&scalar);
+            // keep the descriptions written by the user
+            scalar_type.description = previous_metadata
+                .scalar_types
+                .0
+                .get(&scalar)
+                .and_then(|previous_scalar_type| previous_scalar_type.description.clone());
+            (scalar, scalar_type)
+        })
+        .collect();
+    Ok(metadata::Metadata {
+        tables: TablesInfo(tables_info),
+        scalar_types: ScalarTypes(scalars),
+        composite_types: CompositeTypes(composite_types),
+        // native queries, native mutations and entities are written by the user
+        native_queries: previous_metadata.native_queries.clone(),
+        native_mutations: previous_metadata.native_mutations.clone(),
+        entities: previous_metadata.entities.clone(),
+    })
+}
+
+/// The information of a scalar type: its comparison operators, aggregate functions and type
+/// representation.
+pub fn make_scalar_type(scalar: &ScalarTypeName) -> metadata::ScalarType {
+    let type_rep = match scalar.as_str() {
+        "String" => Some(metadata::TypeRepresentation::String),
+        "Number" => Some(metadata::TypeRepresentation::Int64),
+        "Boolean" => Some(metadata::TypeRepresentation::Boolean),
+        "Any" => Some(metadata::TypeRepresentation::Json),
+        name => metadata::CustomScalarType::from_name(name)
+            .map(metadata::CustomScalarType::type_representation),
+    };
+    metadata::ScalarType {
+        type_name: scalar.clone(),
+        description: None,
+        comparison_operators: get_comparison_operators_for_type(scalar),
+        aggregate_functions: get_aggregate_functions_for_type(scalar),
+        type_representation: type_rep,
+    }
+}
+
+/// Merge the columns found by introspection with the columns of the previous configuration of
+/// the table.
+///
+/// The descriptions and nullability set by the user are kept, as are the types set by the user,
+/// such as `Timestamp`, while the sampled values can be stored as them. Columns missing from the
+/// sampled items are kept and flagged as stale rather than deleted. Columns excluded by the user
+/// are left out, unless they are key attributes.
+fn merge_columns(
+    previous_table_info: &metadata::TableInfo,
+    mut columns_info: BTreeMap<FieldName, ColumnInfo>,
+    scalars_list: &mut BTreeSet<ScalarTypeName>,
+) -> BTreeMap<FieldName, ColumnInfo> {
+    let previous_columns = &previous_table_info.columns;
+    let key_attributes = [
+        &previous_table_info.partition_key,
+        &previous_table_info.sort_key,
+    ]
+    .into_iter()
+    .chain(
+        previous_table_info
+            .gsi
+            .0
+            .values()
+            .flat_map(|gsi| std::iter::once(&gsi.partition_key).chain(gsi.sort_key.as_ref())),
+    )
+    .map(String::as_str)
+    .collect::<BTreeSet<_>>();
+    columns_info.retain(|column_name, column_info| {
+        !previous_table_info.excluded_columns.contains(column_name)
+            || key_attributes.contains(column_info.name.as_str())
+    });
+    for (column_name, column_info) in &mut columns_info {
+        let Some(previous_column) = previous_columns.get(column_name) else {
+            continue;
+        };
+        column_info
+            .description
+            .clone_from(&previous_column.description);
+        column_info.nullable = previous_column.nullable.clone();
+        if can_store_as(&column_info.r#type, &previous_column.r#type) {
+            column_info.r#type = previous_column.r#type.clone();
+            insert_scalar_types(&column_info.r#type, scalars_list);
+        }
+    }
+    for (column_name, previous_column) in previous_columns {
+        if !columns_info.contains_key(column_name)
+            && !previous_table_info.excluded_columns.contains(column_name)
+        {
+            insert_scalar_types(&previous_column.r#type, scalars_list);
+            columns_info.insert(
+                column_name.clone(),
+                ColumnInfo {
+                    stale: true,
+                    ..previous_column.clone()
+                },
+            );
+        }
+    }
+    columns_info
+}
+
+/// Merge the composite types inferred by introspection with the composite types of the previous
+/// configuration, keeping the descriptions written by the user, and the field types set by the
+/// user while the sampled values can be stored as them. Composite types that were not inferred
+/// again are kept, as stale columns may refer to them.
+fn merge_composite_types(
+    previous_composite_types: &CompositeTypes,
+    mut composite_types: BTreeMap<ObjectTypeName, metadata::CompositeType>,
+    scalars_list: &mut BTreeSet<ScalarTypeName>,
+) -> BTreeMap<ObjectTypeName, metadata::CompositeType> {
+    for (type_name, previous_composite_type) in &previous_composite_types.0 {
+        match composite_types.get_mut(type_name) {
+            Some(composite_type) => {
+                composite_type
+                    .description
+                    .clone_from(&previous_composite_type.description);
+                for (field_name, field_info) in &mut composite_type.fields {
+                    let Some(previous_field_info) = previous_composite_type.fields.get(field_name)
+                    else {
+                        continue;
+                    };
+                    field_info
+                        .description
+                        .clone_from(&previous_field_info.description);
+                    if can_store_as(&field_info.r#type, &previous_field_info.r#type) {
+                        field_info.r#type = previous_field_info.r#type.clone();
+                        insert_scalar_types(&field_info.r#type, scalars_list);
+                    }
+                }
+            }
+            None => {
+                for field_info in previous_composite_type.fields.values() {
+                    insert_scalar_types(&field_info.r#type, scalars_list);
+                }
+                composite_types.insert(type_name.clone(), previous_composite_type.clone());
+            }
+        }
+    }
+    composite_types
+}
+
+/// Whether values of an inferred type can be stored as a type set by the user: a scalar type
+/// stored as the inferred one, such as a `Timestamp` for a `String`, or `Any`. Lists are compared
+/// by their elements, and a map may be given any composite type.
+fn can_store_as(inferred_type: &metadata::Type, previous_type: &metadata::Type) -> bool {
+    match (inferred_type, previous_type) {
+        (_, metadata::Type::ScalarType(previous_scalar)) if previous_scalar.as_str() == "Any" => {
+            true
+        }
+        (
+            metadata::Type::ScalarType(inferred_scalar),
+            metadata::Type::ScalarType(previous_scalar),
+        ) => {
+            let storage_type = metadata::CustomScalarType::from_name(previous_scalar.as_str())
+                .map_or(
+                    previous_scalar.as_str(),
+                    metadata::CustomScalarType::storage_type,
+                );
+            inferred_scalar.as_str() == storage_type
+        }
+        (
+            metadata::Type::ArrayType(inferred_element),
+            metadata::Type::ArrayType(previous_element),
+        ) => can_store_as(inferred_element, previous_element),
+        (metadata::Type::CompositeType(_), metadata::Type::CompositeType(_)) => true,
+        _ => false,
+    }
+}
+
+/// Add the scalar types of a type, or of its elements, to the scalar types to publish.
+fn insert_scalar_types(r#type: &metadata::Type, scalars_list: &mut BTreeSet<ScalarTypeName>) {
+    match r#type {
+        metadata::Type::ScalarType(scalar_type) => {
+            scalars_list.insert(scalar_type.clone());
+        }
+        metadata::Type::ArrayType(element_type) => insert_scalar_types(element_type, scalars_list),
+        metadata::Type::CompositeType(_) => {}
+    }
+}
+
+/// Infer the type of a sampled attribute value.
+///
+/// Maps get a composite type named after the path to the attribute, whose fields are merged
+/// across the sampled items. Lists get the type of their elements when every element has the
+/// same type, and `Any` elements otherwise.
+fn infer_attribute_type(
+    type_name: &str,
+    attribute_value: &AttributeValue,
+    scalars_list: &mut BTreeSet<ScalarTypeName>,
+    composite_types: &mut BTreeMap<ObjectTypeName, metadata::CompositeType>,
+) -> metadata::Type {
+    let scalar_type_name = match attribute_value {
+        AttributeValue::S(_) => "String",
+        AttributeValue::N(_) => "Number",
+        AttributeValue::Bool(_) => "Boolean",
+        AttributeValue::B(_) => "Binary",
+        AttributeValue::L(elements) => {
+            let element_types = elements
+                .iter()
+                .map(|element| {
+                    infer_attribute_type(type_name, element, scalars_list, composite_types)
+                })
+                .collect::<Vec<_>>();
+            return match element_types.split_first() {
+                Some((first, rest)) if rest.iter().all(|element_type| element_type == first) => {
+                    metadata::Type::ArrayType(Box::new(first.clone()))
+                }
+                _ => {
+                    scalars_list.insert(ScalarTypeName::new("Any".into()));
+                    metadata::Type::ArrayType(Box::new(metadata::Type::ScalarType(
+                        ScalarTypeName::new("Any".into()),
+                    )))
+                }
+            };
+        }
+        AttributeValue::M(fields) => {
+            let composite_type_name = ObjectTypeName::new(format!("{type_name}_map").into());
+            composite_types
+                .entry(composite_type_name.clone())
+                .or_insert_with(|| metadata::CompositeType {
+                    type_name: composite_type_name.to_string(),
+                    fields: BTreeMap::new(),
+                    description: None,
+                });
+            for (field_name, field_value) in fields {
+                let field_type = infer_attribute_type(
+                    &format!("{type_name}_{field_name}"),
+                    field_value,
+                    scalars_list,
+                    composite_types,
+                );
+                if let Some(composite_type) = composite_types.get_mut(&composite_type_name) {
+                    composite_type.fields.insert(
+                        FieldName::new(field_name.clone().into()),
+                        metadata::FieldInfo {
+                            field_name: field_name.clone(),
+                            r#type: field_type,
+                            description: None,
+                        },
+                    );
+                }
+            }
+            return metadata::Type::CompositeType(composite_type_name);
+        }
+        _ => "Any",
+    };
+    let scalar_type_name = ScalarTypeName::new(scalar_type_name.into());
+    scalars_list.insert(scalar_type_name.clone());
+    metadata::Type::ScalarType(scalar_type_name)
+}
+
+/// The comparison operators DynamoDB PartiQL offers for a scalar type.
+/// Every type can be compared for (in)equality and checked for its attribute type and whether
+/// the attribute is missing from the item, strings,
+/// numbers and binaries are ordered, and strings, binaries and documents (`Any`) also have the
+/// `begins_with`, `contains` and `size` functions where DynamoDB supports them.
+pub fn get_comparison_operators_for_type(
+    type_name: &ndc_models::ScalarTypeName,
+) -> BTreeMap<ComparisonOperatorName, database::ComparisonOperator> {
+    let string_type = ScalarTypeName::new("String".into());
+    let boolean_type = ScalarTypeName::new("Boolean".into());
+    let number_type = ScalarTypeName::new("Number".into());
+    let is_ordered = matches!(type_name.as_str(), "String" | "Number" | "Binary")
+        || metadata::CustomScalarType::from_name(type_name.as_str())
+            .is_some_and(metadata::CustomScalarType::is_ordered);
+    let is_sequence = matches!(type_name.as_str(), "String" | "Binary");
+    let has_size = matches!(type_name.as_str(), "String" | "Binary" | "Any");
+
+    let mut comparison_operators = BTreeMap::new();
+    let mut insert = |name: &str,
+                      operator_name: &str,
+                      operator_kind: database::OperatorKind,
+                      argument_type: &ScalarTypeName,
+                      is_infix: bool| {
+        comparison_operators.insert(
+            ComparisonOperatorName::new(name.into()),
+            database::ComparisonOperator {
+                operator_name: operator_name.to_string(),
+                operator_kind,
+                argument_type: argument_type.clone(),
+                is_infix,
+            },
+        );
+    };
+
+    // in ndc-spec, all things can be `==`
+    insert("_eq", "=", database::OperatorKind::Equal, type_name, true);
+    insert("_in", "IN", database::OperatorKind::In, type_name, true);
+    insert(
+        "_neq",
+        "<>",
+        database::OperatorKind::Custom,
+        type_name,
+        true,
+    );
+    insert(
+        "_attribute_type",
+        "attribute_type",
+        database::OperatorKind::AttributeType,
+        &string_type,
+        false,
+    );
+    // DynamoDB tells an absent attribute apart from one holding NULL
+    insert(
+        "_is_missing",
+        "IS MISSING",
+        database::OperatorKind::IsMissing,
+        &boolean_type,
+        false,
+    );
+    insert(
+        "_exists",
+        "IS NOT MISSING",
+        database::OperatorKind::Exists,
+        &boolean_type,
+        false,
+    );
+
+    if is_ordered {
+        insert("_lt", "<", database::OperatorKind::Custom, type_name, true);
+        insert(
+            "_lte",
+            "<=",
+            database::OperatorKind::Custom,
+            type_name,
+            true,
+        );
+        insert("_gt", ">", database::OperatorKind::Custom, type_name, true);
+        insert(
+            "_gte",
+            ">=",
+            database::OperatorKind::Custom,
+            type_name,
+            true,
+        );
+        // the bounds are a two element array of literals, as PartiQL has no way to take them
+        // from a bound list. Variable and column bounds are not implemented yet.
+        insert(
+            "_between",
+            "BETWEEN",
+            database::OperatorKind::Between,
+            type_name,
+            true,
+        );
+    }
+
+    if is_sequence {
+        insert(
+            "_begins_with",
+            "begins_with",
+            database::OperatorKind::BeginsWith,
+            type_name,
+            false,
+        );
+    }
+
+    // `contains` looks for a substring in strings and binaries, and for an element in lists
+    // and sets
+    if is_sequence || type_name.as_str() == "Any" {
+        insert(
+            "_contains",
+            "contains",
+            database::OperatorKind::Contains,
+            type_name,
+            false,
+        );
+    }
+
+    if has_size {
+        for (name, operator_name) in [
+            ("_size_eq", "="),
+            ("_size_neq", "<>"),
+            ("_size_lt", "<"),
+            ("_size_lte", "<="),
+            ("_size_gt", ">"),
+            ("_size_gte", ">="),
+        ] {
+            insert(
+                name,
+                operator_name,
+                database::OperatorKind::Size,
+                &number_type,
+                true,
+            );
+        }
+    }
+
+    comparison_operators
+}
+
+/// The aggregate functions the connector computes for a type, besides the counts which apply to
+/// every column.
+/// Numbers can be summed and averaged, and numbers and strings can be ordered.
+fn get_aggregate_functions_for_type(
+    type_name: &ndc_models::ScalarTypeName,
+) -> BTreeMap<AggregateFunctionName, database::AggregateFunction> {
+    let function_names: &[&str] = match type_name.as_str() {
+        "Number" => &["min", "max", "sum", "avg"],
+        "String" => &["min", "max"],
+        name if metadata::CustomScalarType::from_name(name)
+            .is_some_and(metadata::CustomScalarType::is_ordered) =>
+        {
+            &["min", "max"]
+        }
+        _ => &[],
+    };
+    function_names
+        .iter()
+        .map(|function_name| {
+            (
+                AggregateFunctionName::new((*function_name).into()),
+                database::AggregateFunction {
+                    return_type: type_name.clone(),
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_columns, merge_composite_types};
+    use ndc_models::{FieldName, ObjectTypeName, ScalarTypeName};
+    use query_engine_metadata::metadata::{self, ColumnInfo, CompositeTypes, Nullable};
+    use std::collections::{BTreeMap, BTreeSet};
+
+    fn column(name: &str, scalar_type: &str) -> ColumnInfo {
+        ColumnInfo {
+            name: name.to_string(),
+            r#type: metadata::Type::ScalarType(ScalarTypeName::new(scalar_type.into())),
+            nullable: Nullable::Nullable,
+            description: None,
+            stale: false,
+        }
+    }
+
+    /// Columns found by introspection, by name and scalar type.
+    fn columns(columns: &[(&str, &str)]) -> BTreeMap<FieldName, ColumnInfo> {
+        columns
+            .iter()
+            .map(|(name, scalar_type)| ((*name).into(), column(name, scalar_type)))
+            .collect()
+    }
+
+    /// A users table keyed on `id`, as the user left it in the previous configuration.
+    fn previous_users_table() -> metadata::TableInfo {
+        serde_json::from_value(serde_json::json!({
+            "tableName": "users",
+            "columns": {
+                "id": { "name": "id", "type": { "scalarType": "String" } },
+                "name": {
+                    "name": "name",
+                    "type": { "scalarType": "String" },
+                    "nullable": "nonNullable",
+                    "description": "The name of the user",
+                },
+                "created": { "name": "created", "type": { "scalarType": "Timestamp" } },
+                "updated": { "name": "updated", "type": { "scalarType": "Timestamp" } },
+                "nickname": { "name": "nickname", "type": { "scalarType": "String" } },
+                "legacy": { "name": "legacy", "type": { "scalarType": "String" } },
+                "visits": {
+                    "name": "visits",
+                    "type": { "arrayType": { "scalarType": "Timestamp" } },
+                },
+                "payload": { "name": "payload", "type": { "scalarType": "Any" } },
+            },
+            "partitionKey": "id",
+            "sortKey": "",
+            "gsi": {},
+            "excludedColumns": ["notes", "id", "legacy"],
+        }))
+        .unwrap()
+    }
+
+    /// A list type of the given element scalar type.
+    fn list_of(scalar_type: &str) -> metadata::Type {
+        metadata::Type::ArrayType(Box::new(metadata::Type::ScalarType(ScalarTypeName::new(
+            scalar_type.into(),
+        ))))
+    }
+
+    #[test]
+    fn test_merge_columns_keeps_the_edits_of_the_user() {
+        let mut scalars_list = BTreeSet::new();
+        let mut sampled_columns = columns(&[
+            ("id", "String"),
+            ("name", "String"),
+            ("created", "String"),
+            ("updated", "Number"),
+            ("email", "String"),
+            ("payload", "Number"),
+        ]);
+        sampled_columns.insert(
+            "visits".into(),
+            ColumnInfo {
+                r#type: list_of("String"),
+                ..column("visits", "String")
+            },
+        );
+        let merged = merge_columns(&previous_users_table(), sampled_columns, &mut scalars_list);
+
+        assert_eq!(
+            merged["name"],
+            ColumnInfo {
+                nullable: Nullable::NonNullable,
+                description: Some("The name of the user".to_string()),
+                ..column("name", "String")
+            }
+        );
+        // a type set by the user is kept while the values can be stored as it
+        assert_eq!(merged["created"], column("created", "Timestamp"));
+        assert_eq!(merged["updated"], column("updated", "Number"));
+        assert_eq!(merged["payload"], column("payload", "Any"));
+        // the elements of a list too
+        assert_eq!(merged["visits"].r#type, list_of("Timestamp"));
+        // new attributes are added, and missing ones flagged as stale
+        assert_eq!(merged["email"], column("email", "String"));
+        assert_eq!(
+            merged["nickname"],
+            ColumnInfo {
+                stale: true,
+                ..column("nickname", "String")
+            }
+        );
+        assert_eq!(
+            scalars_list,
+            BTreeSet::from(["Any".into(), "String".into(), "Timestamp".into()])
+        );
+    }
+
+    #[test]
+    fn test_merge_columns_leaves_out_excluded_columns() {
+        let merged = merge_columns(
+            &previous_users_table(),
+            columns(&[("id", "String"), ("notes", "String")]),
+            &mut BTreeSet::new(),
+        );
+
+        // the key attribute cannot be excluded
+        assert_eq!(merged["id"], column("id", "String"));
+        assert!(!merged.contains_key("notes"));
+        // nor are they kept as stale columns
+        assert!(!merged.contains_key("legacy"));
+    }
+
+    #[test]
+    fn test_merge_composite_types_keeps_the_descriptions_of_the_user() {
+        let composite_type = |type_name: &str, description: Option<&str>| metadata::CompositeType {
+            type_name: type_name.to_string(),
+            fields: BTreeMap::from([(
+                "city".into(),
+                metadata::FieldInfo {
+                    field_name: "city".to_string(),
+                    r#type: metadata::Type::ScalarType("String".into()),
+                    description: description.map(ToString::to_string),
+                },
+            )]),
+            description: description.map(ToString::to_string),
+        };
+        let previous_composite_types = CompositeTypes(BTreeMap::from([
+            (
+                ObjectTypeName::from("users_address_map"),
+                composite_type("users_address_map", Some("An address")),
+            ),
+            (
+                ObjectTypeName::from("users_legacy_map"),
+                composite_type("users_legacy_map", None),
+            ),
+        ]));
+        let merged = merge_composite_types(
+            &previous_composite_types,
+            BTreeMap::from([
+                (
+                    ObjectTypeName::from("users_address_map"),
+                    composite_type("users_address_map", None),
+                ),
+                (
+                    ObjectTypeName::from("users_settings_map"),
+                    composite_type("users_settings_map", None),
+                ),
+            ]),
+            &mut BTreeSet::new(),
+        );
+
+        assert_eq!(
+            merged["users_address_map"],
+            composite_type("users_address_map", Some("An address"))
+        );
+        // composite types that were not inferred again are kept, for the stale columns
+        assert_eq!(
+            merged["users_legacy_map"],
+            composite_type("users_legacy_map", None)
+        );
+        assert_eq!(
+            merged["users_settings_map"],
+            composite_type("users_settings_map", None)
+        );
+    }
+
+    #[test]
+    fn test_merge_composite_types_keeps_the_field_types_of_the_user() {
+        let composite_type = |since: &str, zip: &str| metadata::CompositeType {
+            type_name: "users_address_map".to_string(),
+            fields: [("since", since), ("zip", zip)]
+                .into_iter()
+                .map(|(field_name, scalar_type)| {
+                    (
+                        field_name.into(),
+                        metadata::FieldInfo {
+                            field_name: field_name.to_string(),
+                            r#type: metadata::Type::ScalarType(scalar_type.into()),
+                            description: None,
+                        },
+                    )
+                })
+                .collect(),
+            description: None,
+        };
+        let mut scalars_list = BTreeSet::new();
+        let merged = merge_composite_types(
+            &CompositeTypes(BTreeMap::from([(
+                ObjectTypeName::from("users_address_map"),
+                composite_type("Timestamp", "Number"),
+            )])),
+            BTreeMap::from([(
+                ObjectTypeName::from("users_address_map"),
+                composite_type("String", "String"),
+            )]),
+            &mut scalars_list,
+        );
+
+        // `zip` holds strings now, which cannot be stored as numbers
+        assert_eq!(
+            merged["users_address_map"],
+            composite_type("Timestamp", "String")
+        );
+        assert!(scalars_list.contains("Timestamp"));
+    }
+}
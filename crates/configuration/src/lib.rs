@@ -2,21 +2,17 @@ pub mod configuration;
 pub mod connection_settings;
 pub mod environment;
 pub mod error;
+pub mod introspection;
 mod to_runtime_configuration;
 mod values;
 pub mod version1;
+pub mod version2;
 
-pub use configuration::Configuration;
-pub use to_runtime_configuration::make_runtime_configuration;
-pub use values::connection_info::{AccessKeyId, ProviderName, Region, SecretAccessKey};
-pub use version1::{
-    introspect,
-    parse_configuration,
-    write_parsed_configuration,
-    // single_connection_uri, // for tests only
-    // validate_raw_configuration,
-    // Configuration,
-    // ConfigurationError,
-    // PoolSettings,
-    ParsedConfiguration,
+pub use configuration::{
+    introspect, parse_configuration, upgrade_to_latest_version, write_parsed_configuration,
+    Configuration, ParsedConfiguration,
+};
+pub use to_runtime_configuration::{convert_metadata, make_runtime_configuration};
+pub use values::connection_info::{
+    AccessKeyId, EndpointUrl, ProviderName, Region, SecretAccessKey,
 };
@@ -1,11 +1,14 @@
 //! Convert the parsed configuration metadata to internal engine metadata
 //! That can be used by the connector at runtime.
 
-use super::version1::ParsedConfiguration;
+use crate::configuration::ParsedConfiguration;
 use crate::environment::Environment;
 use crate::error::MakeRuntimeConfigurationError;
-use crate::values::{AccessKeyId, Region, Secret, SecretAccessKey};
+use crate::values::{AccessKeyId, EndpointUrl, Region, Secret, SecretAccessKey};
+use crate::version2;
+use ndc_models as models;
 use query_engine_metadata::{self, metadata};
+use std::collections::{BTreeMap, BTreeSet};
 // use crate::VersionTag;
 
 /// Convert the parsed configuration metadata to internal engine metadata
@@ -14,6 +17,11 @@ pub fn make_runtime_configuration(
     parsed_config: ParsedConfiguration,
     environment: impl Environment,
 ) -> Result<crate::Configuration, MakeRuntimeConfigurationError> {
+    // earlier versions convert losslessly to the latest one
+    let parsed_config = match parsed_config {
+        ParsedConfiguration::Version1(configuration) => version2::upgrade_from_v1(configuration),
+        ParsedConfiguration::Version2(configuration) => configuration,
+    };
     let access_key_id = match parsed_config.connection_settings.access_key_id {
         AccessKeyId(Secret::Plain(key)) => Ok(key),
         AccessKeyId(Secret::FromEnvironment { variable }) => {
@@ -47,28 +55,432 @@ pub fn make_runtime_configuration(
             })
         }
     }?;
+    let endpoint_url = match parsed_config.connection_settings.endpoint_url {
+        None => Ok(None),
+        Some(EndpointUrl(Secret::Plain(url))) => Ok(Some(url)),
+        Some(EndpointUrl(Secret::FromEnvironment { variable })) => {
+            environment.read(&variable).map(Some).map_err(|error| {
+                MakeRuntimeConfigurationError::MissingEnvironmentVariable {
+                    file_path: super::version1::CONFIGURATION_FILENAME.into(),
+                    message: error.to_string(),
+                }
+            })
+        }
+    }?;
+    validate_version_attributes(&parsed_config.metadata.tables)?;
+    validate_foreign_relations(&parsed_config.metadata.tables)?;
+    validate_native_queries(&parsed_config.metadata)?;
+    validate_native_mutations(&parsed_config.metadata)?;
+    validate_entities(&parsed_config.metadata)?;
+    validate_key_templates(&parsed_config.metadata)?;
     Ok(crate::Configuration {
         metadata: convert_metadata(parsed_config.metadata),
         access_key_id,
         secret_access_key,
         // provider_name,
         region,
+        endpoint_url,
+        explain_options: parsed_config.explain_options,
+        query_options: parsed_config.query_options,
         // pool_settings: parsed_config.pool_settings,
         // mutations_version: convert_mutations_version(parsed_config.mutations_version),
     })
 }
 
+/// Check that version attributes are not part of the primary key, and are numbers when the
+/// attribute is known.
+fn validate_version_attributes(
+    tables: &metadata::TablesInfo,
+) -> Result<(), MakeRuntimeConfigurationError> {
+    for (collection_name, table_info) in &tables.0 {
+        let Some(version_attribute) = &table_info.version_attribute else {
+            continue;
+        };
+        let error = |message: &str| MakeRuntimeConfigurationError::InvalidVersionAttribute {
+            table: collection_name.to_string(),
+            version_attribute: version_attribute.clone(),
+            message: message.to_string(),
+        };
+        if version_attribute == &table_info.partition_key
+            || version_attribute == &table_info.sort_key
+        {
+            return Err(error("the version attribute cannot be a key attribute"));
+        }
+        let column_type = table_info
+            .columns
+            .values()
+            .find(|column_info| &column_info.name == version_attribute)
+            .map(|column_info| &column_info.r#type);
+        match column_type {
+            None => {}
+            Some(metadata::Type::ScalarType(scalar_type)) if scalar_type.as_str() == "Number" => {}
+            Some(_) => return Err(error("the version attribute must be a Number")),
+        }
+    }
+    Ok(())
+}
+
+/// Check that foreign keys map known columns to the key attributes of a known table, or of one
+/// of its global secondary indexes. Stale tables are left out of the schema, so their foreign keys
+/// are not checked, and foreign keys to them are errors.
+fn validate_foreign_relations(
+    tables: &metadata::TablesInfo,
+) -> Result<(), MakeRuntimeConfigurationError> {
+    for (collection_name, table_info) in &tables.0 {
+        if table_info.stale {
+            continue;
+        }
+        for (foreign_key, foreign_relation) in &table_info.foreign_relations.0 {
+            let error = |message: String| MakeRuntimeConfigurationError::InvalidForeignKey {
+                table: collection_name.to_string(),
+                foreign_key: foreign_key.clone(),
+                message,
+            };
+            let Some(foreign_table) = tables.0.get(&foreign_relation.foreign_table) else {
+                return Err(error(format!(
+                    "unknown foreign table \"{}\"",
+                    foreign_relation.foreign_table
+                )));
+            };
+            if foreign_table.stale {
+                return Err(error(format!(
+                    "the foreign table \"{}\" is stale",
+                    foreign_relation.foreign_table
+                )));
+            }
+            if foreign_relation.column_mapping.is_empty() {
+                return Err(error("the column mapping is empty".to_string()));
+            }
+            let mut foreign_attributes = BTreeSet::new();
+            for (column, foreign_column) in &foreign_relation.column_mapping {
+                if !table_info.columns.contains_key(column) {
+                    return Err(error(format!("unknown column \"{column}\"")));
+                }
+                let Some(foreign_column_info) = foreign_table.columns.get(foreign_column) else {
+                    return Err(error(format!(
+                        "unknown column \"{foreign_column}\" of the foreign table"
+                    )));
+                };
+                foreign_attributes.insert(foreign_column_info.name.as_str());
+            }
+            let refers_to_key = foreign_table
+                .collection_keyed_on(&foreign_relation.foreign_table, &foreign_attributes)
+                .is_some();
+            if !refers_to_key {
+                return Err(error(
+                    "the foreign columns must be the key attributes of the foreign table or of one of its global secondary indexes"
+                        .to_string(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that native queries are SELECT statements, named apart from the tables, whose
+/// placeholders are all declared arguments.
+fn validate_native_queries(
+    metadata: &metadata::Metadata,
+) -> Result<(), MakeRuntimeConfigurationError> {
+    for (name, native_query) in &metadata.native_queries.0 {
+        let error = |message: String| MakeRuntimeConfigurationError::InvalidNativeQuery {
+            name: name.to_string(),
+            message,
+        };
+        if metadata.tables.0.contains_key(name) {
+            return Err(error("a table has the same name".to_string()));
+        }
+        if !starts_with_keyword(&native_query.sql, "SELECT") {
+            return Err(error("the statement must be a SELECT".to_string()));
+        }
+        check_placeholders(native_query.parts(), &native_query.arguments).map_err(error)?;
+    }
+    Ok(())
+}
+
+/// Check that native mutations are INSERT, UPDATE or DELETE statements whose placeholders are
+/// all declared arguments. Their result is an object type named after them, so they are named
+/// apart from the tables, native queries and composite types, and from the procedures and object
+/// types generated for the tables.
+fn validate_native_mutations(
+    metadata: &metadata::Metadata,
+) -> Result<(), MakeRuntimeConfigurationError> {
+    for (name, native_mutation) in &metadata.native_mutations.0 {
+        let error = |message: String| MakeRuntimeConfigurationError::InvalidNativeMutation {
+            name: name.to_string(),
+            message,
+        };
+        let type_name = models::CollectionName::new(name.as_str().into());
+        if metadata.tables.0.contains_key(&type_name) {
+            return Err(error("a table has the same name".to_string()));
+        }
+        if metadata.native_queries.0.contains_key(&type_name) {
+            return Err(error("a native query has the same name".to_string()));
+        }
+        if metadata.tables.0.iter().any(|(collection_name, table)| {
+            metadata::generated_procedure_names(collection_name, table).contains(name)
+        }) {
+            return Err(error(
+                "a procedure generated for a table has the same name".to_string(),
+            ));
+        }
+        // the item a native mutation returns is an object type of the same name
+        let object_type_name = models::ObjectTypeName::new(name.as_str().into());
+        if metadata.composite_types.0.contains_key(&object_type_name) {
+            return Err(error("a composite type has the same name".to_string()));
+        }
+        if metadata::generated_object_type_names(&metadata.tables).contains(&object_type_name) {
+            return Err(error(
+                "an object type generated for the procedures of the tables has the same name"
+                    .to_string(),
+            ));
+        }
+        if !["INSERT", "UPDATE", "DELETE"]
+            .iter()
+            .any(|keyword| starts_with_keyword(&native_mutation.sql, keyword))
+        {
+            return Err(error(
+                "the statement must be an INSERT, an UPDATE or a DELETE".to_string(),
+            ));
+        }
+        check_placeholders(native_mutation.parts(), &native_mutation.arguments).map_err(error)?;
+    }
+    Ok(())
+}
+
+/// Check that entities are named apart from the tables, native queries and native mutations, and
+/// read a known table whose items they tell apart by a known key prefix.
+fn validate_entities(metadata: &metadata::Metadata) -> Result<(), MakeRuntimeConfigurationError> {
+    for (name, entity) in &metadata.entities.0 {
+        let error = |message: String| MakeRuntimeConfigurationError::InvalidEntity {
+            name: name.to_string(),
+            message,
+        };
+        if metadata.tables.0.contains_key(name) {
+            return Err(error("a table has the same name".to_string()));
+        }
+        if metadata.native_queries.0.contains_key(name) {
+            return Err(error("a native query has the same name".to_string()));
+        }
+        if metadata
+            .native_mutations
+            .0
+            .contains_key(&models::ProcedureName::new(name.as_str().into()))
+        {
+            return Err(error("a native mutation has the same name".to_string()));
+        }
+        match metadata.tables.0.get(&entity.table) {
+            None => return Err(error(format!("unknown table \"{}\"", entity.table))),
+            Some(table_info) if table_info.stale => {
+                return Err(error(format!("the table \"{}\" is stale", entity.table)));
+            }
+            Some(_) => {}
+        }
+        if let metadata::Discriminator::KeyPrefix { attribute } = &entity.discriminator {
+            if !entity.key_prefixes.contains_key(attribute) {
+                return Err(error(format!(
+                    "the discriminator attribute \"{attribute}\" has no key prefix"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the key templates of the tables and entities.
+fn validate_key_templates(
+    metadata: &metadata::Metadata,
+) -> Result<(), MakeRuntimeConfigurationError> {
+    let tables = metadata.tables.0.iter().map(|(name, table_info)| {
+        (
+            "tables",
+            name,
+            &table_info.columns,
+            &table_info.key_templates,
+            None::<&BTreeMap<String, String>>,
+        )
+    });
+    let entities = metadata.entities.0.iter().map(|(name, entity_info)| {
+        (
+            "entities",
+            name,
+            &entity_info.columns,
+            &entity_info.key_templates,
+            Some(&entity_info.key_prefixes),
+        )
+    });
+    for (section, collection, columns, key_templates, key_prefixes) in tables.chain(entities) {
+        let mut virtual_fields = BTreeSet::new();
+        for (attribute, key_template) in &key_templates.0 {
+            let error = |message: String| MakeRuntimeConfigurationError::InvalidKeyTemplate {
+                section: section.to_string(),
+                collection: collection.to_string(),
+                attribute: attribute.clone(),
+                message,
+            };
+            if key_prefixes.is_some_and(|key_prefixes| key_prefixes.contains_key(attribute)) {
+                return Err(error("the attribute has a key prefix as well".to_string()));
+            }
+            check_key_template(key_template.parts(), &key_template.fields).map_err(error)?;
+            for (field_name, field) in &key_template.fields {
+                if columns.contains_key(field_name) || !virtual_fields.insert(field_name) {
+                    return Err(error(format!(
+                        "the field \"{field_name}\" has the name of another field"
+                    )));
+                }
+                if !["String", "Number"].contains(&field.r#type.as_str()) {
+                    return Err(error(format!(
+                        "the field \"{field_name}\" must be a String or a Number"
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that each field of a key template has a placeholder, and that placeholders are
+/// separated by text, so that values can be split back into fields.
+fn check_key_template(
+    parts: Vec<metadata::KeyTemplatePart>,
+    fields: &BTreeMap<models::FieldName, metadata::KeyTemplateField>,
+) -> Result<(), String> {
+    let mut placeholders = BTreeSet::new();
+    let mut previous_is_field = false;
+    for part in parts {
+        match part {
+            metadata::KeyTemplatePart::Text(_) => previous_is_field = false,
+            metadata::KeyTemplatePart::Field(field_name) => {
+                if previous_is_field {
+                    return Err("placeholders must be separated by text".to_string());
+                }
+                if !fields.contains_key(&field_name) {
+                    return Err(format!(
+                        "the placeholder {{{field_name}}} is not a declared field"
+                    ));
+                }
+                if !placeholders.insert(field_name.clone()) {
+                    return Err(format!("the placeholder {{{field_name}}} appears twice"));
+                }
+                previous_is_field = true;
+            }
+        }
+    }
+    match fields
+        .keys()
+        .find(|field_name| !placeholders.contains(*field_name))
+    {
+        Some(field_name) => Err(format!("the field \"{field_name}\" has no placeholder")),
+        None => Ok(()),
+    }
+}
+
+/// Whether a statement starts with a keyword, in any case.
+fn starts_with_keyword(sql: &str, keyword: &str) -> bool {
+    sql.trim_start()
+        .get(..keyword.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(keyword))
+}
+
+/// Check that every placeholder of a statement is a declared argument.
+fn check_placeholders(
+    parts: Vec<metadata::NativeQueryPart>,
+    arguments: &BTreeMap<models::ArgumentName, metadata::ColumnInfo>,
+) -> Result<(), String> {
+    for part in parts {
+        if let metadata::NativeQueryPart::Parameter(argument) = part {
+            if !arguments.contains_key(&models::ArgumentName::new(argument.as_str().into())) {
+                return Err(format!(
+                    "the placeholder {{{{{argument}}}}} is not a declared argument"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Convert the metadata specified in the parsed configuration to an engine metadata.
 /// This function is used by tests as well
 pub fn convert_metadata(metadata: metadata::Metadata) -> query_engine_metadata::metadata::Metadata {
+    let custom_scalar_types = used_custom_scalar_types(&metadata);
+    let mut scalar_types = convert_scalar_types(metadata.scalar_types);
+    // custom scalar types given to columns since the last introspection
+    for scalar_type_name in custom_scalar_types {
+        if !scalar_types.0.contains_key(&scalar_type_name) {
+            let scalar_type = crate::introspection::make_scalar_type(&scalar_type_name);
+            scalar_types.0.insert(scalar_type_name, scalar_type);
+        }
+    }
+    let mut tables = convert_tables(metadata.tables);
+    // entities are read as tables of their own
+    let entity_tables = entity_tables(&tables, &metadata.entities);
+    tables.merge(entity_tables);
     query_engine_metadata::metadata::Metadata {
-        tables: convert_tables(metadata.tables),
-        scalar_types: convert_scalar_types(metadata.scalar_types),
-        // composite_types: convert_composite_types(metadata.composite_types),
+        tables,
+        scalar_types,
+        composite_types: convert_composite_types(metadata.composite_types),
+        native_queries: metadata.native_queries,
+        native_mutations: metadata.native_mutations,
+        entities: metadata.entities,
         // native_operations: convert_native_operations(metadata.native_operations),
     }
 }
 
+/// The custom scalar types, such as `Timestamp`, of the columns, fields and arguments of the
+/// metadata.
+fn used_custom_scalar_types(metadata: &metadata::Metadata) -> BTreeSet<models::ScalarTypeName> {
+    let column_types = metadata
+        .tables
+        .0
+        .values()
+        .flat_map(|table_info| table_info.columns.values())
+        .chain(
+            metadata
+                .entities
+                .0
+                .values()
+                .flat_map(|entity_info| entity_info.columns.values()),
+        )
+        .chain(metadata.native_queries.0.values().flat_map(|native_query| {
+            native_query
+                .columns
+                .values()
+                .chain(native_query.arguments.values())
+        }))
+        .chain(
+            metadata
+                .native_mutations
+                .0
+                .values()
+                .flat_map(|native_mutation| {
+                    native_mutation
+                        .columns
+                        .values()
+                        .chain(native_mutation.arguments.values())
+                }),
+        )
+        .map(|column_info| &column_info.r#type);
+    let field_types = metadata
+        .composite_types
+        .0
+        .values()
+        .flat_map(|composite_type| composite_type.fields.values())
+        .map(|field_info| &field_info.r#type);
+
+    let mut scalar_types = BTreeSet::new();
+    for mut r#type in column_types.chain(field_types) {
+        while let metadata::Type::ArrayType(element_type) = r#type {
+            r#type = &**element_type;
+        }
+        if let metadata::Type::ScalarType(scalar_type_name) = r#type {
+            if metadata::CustomScalarType::from_name(scalar_type_name.as_str()).is_some() {
+                scalar_types.insert(scalar_type_name.clone());
+            }
+        }
+    }
+    scalar_types
+}
+
 fn convert_scalar_types(
     scalar_types: metadata::ScalarTypes,
 ) -> query_engine_metadata::metadata::ScalarTypes {
@@ -88,6 +500,11 @@ fn convert_scalar_types(
                             .into_iter()
                             .map(|(k, v)| (k, convert_comparison_operator(v)))
                             .collect(),
+                        aggregate_functions: scalar_type
+                            .aggregate_functions
+                            .into_iter()
+                            .map(|(k, v)| (k, convert_aggregate_function(v)))
+                            .collect(),
                         type_representation: scalar_type
                             .type_representation
                             .map(convert_type_representation),
@@ -108,6 +525,7 @@ fn convert_nullable(nullable: &metadata::Nullable) -> query_engine_metadata::met
 fn convert_type(r#type: metadata::Type) -> query_engine_metadata::metadata::Type {
     match r#type {
         metadata::Type::ScalarType(t) => query_engine_metadata::metadata::Type::ScalarType(t),
+        metadata::Type::CompositeType(t) => query_engine_metadata::metadata::Type::CompositeType(t),
         metadata::Type::ArrayType(t) => {
             query_engine_metadata::metadata::Type::ArrayType(Box::new(convert_type(*t)))
         }
@@ -196,6 +614,14 @@ fn convert_comparison_operator(
     }
 }
 
+fn convert_aggregate_function(
+    aggregate_function: metadata::AggregateFunction,
+) -> query_engine_metadata::metadata::AggregateFunction {
+    query_engine_metadata::metadata::AggregateFunction {
+        return_type: aggregate_function.return_type,
+    }
+}
+
 fn convert_operator_kind(
     operator_kind: &metadata::OperatorKind,
 ) -> query_engine_metadata::metadata::OperatorKind {
@@ -203,51 +629,65 @@ fn convert_operator_kind(
         metadata::OperatorKind::Equal => query_engine_metadata::metadata::OperatorKind::Equal,
         metadata::OperatorKind::In => query_engine_metadata::metadata::OperatorKind::In,
         metadata::OperatorKind::Custom => query_engine_metadata::metadata::OperatorKind::Custom,
+        metadata::OperatorKind::BeginsWith => {
+            query_engine_metadata::metadata::OperatorKind::BeginsWith
+        }
+        metadata::OperatorKind::Contains => query_engine_metadata::metadata::OperatorKind::Contains,
+        metadata::OperatorKind::Size => query_engine_metadata::metadata::OperatorKind::Size,
+        metadata::OperatorKind::AttributeType => {
+            query_engine_metadata::metadata::OperatorKind::AttributeType
+        }
+        metadata::OperatorKind::Between => query_engine_metadata::metadata::OperatorKind::Between,
+        metadata::OperatorKind::IsMissing => {
+            query_engine_metadata::metadata::OperatorKind::IsMissing
+        }
+        metadata::OperatorKind::Exists => query_engine_metadata::metadata::OperatorKind::Exists,
+    }
+}
+
+fn convert_composite_types(
+    composite_types: metadata::CompositeTypes,
+) -> query_engine_metadata::metadata::CompositeTypes {
+    query_engine_metadata::metadata::CompositeTypes(
+        composite_types
+            .0
+            .into_iter()
+            .map(|(k, composite_type)| (k, convert_composite_type(composite_type)))
+            .collect(),
+    )
+}
+
+fn convert_composite_type(
+    composite_type: metadata::CompositeType,
+) -> query_engine_metadata::metadata::CompositeType {
+    query_engine_metadata::metadata::CompositeType {
+        type_name: composite_type.type_name,
+        fields: composite_type
+            .fields
+            .into_iter()
+            .map(|(k, field)| (k, convert_composite_type_field_info(field)))
+            .collect(),
+        description: composite_type.description,
     }
 }
 
-// fn convert_composite_types(
-//     composite_types: metadata::CompositeTypes,
-// ) -> query_engine_metadata::metadata::CompositeTypes {
-//     query_engine_metadata::metadata::CompositeTypes(
-//         composite_types
-//             .0
-//             .into_iter()
-//             .map(|(k, composite_type)| (k, convert_composite_type(composite_type)))
-//             .collect(),
-//     )
-// }
-
-// fn convert_composite_type(
-//     composite_type: metadata::CompositeType,
-// ) -> query_engine_metadata::metadata::CompositeType {
-//     query_engine_metadata::metadata::CompositeType {
-//         type_name: composite_type.type_name,
-//         schema_name: (composite_type.schema_name),
-//         fields: composite_type
-//             .fields
-//             .into_iter()
-//             .map(|(k, field)| (k, convert_composite_type_field_info(field)))
-//             .collect(),
-//         description: composite_type.description,
-//     }
-// }
-
-// fn convert_composite_type_field_info(
-//     field: metadata::FieldInfo,
-// ) -> query_engine_metadata::metadata::FieldInfo {
-//     query_engine_metadata::metadata::FieldInfo {
-//         field_name: field.field_name,
-//         r#type: convert_type(field.r#type),
-//         description: field.description,
-//     }
-// }
+fn convert_composite_type_field_info(
+    field: metadata::FieldInfo,
+) -> query_engine_metadata::metadata::FieldInfo {
+    query_engine_metadata::metadata::FieldInfo {
+        field_name: field.field_name,
+        r#type: convert_type(field.r#type),
+        description: field.description,
+    }
+}
 
+/// Convert the tables, leaving out the stale ones: introspection did not find them anymore.
 pub fn convert_tables(tables: metadata::TablesInfo) -> query_engine_metadata::metadata::TablesInfo {
     query_engine_metadata::metadata::TablesInfo(
         tables
             .0
             .into_iter()
+            .filter(|(_, table_info)| !table_info.stale)
             .map(|(k, table_info)| (k, convert_table_info(table_info)))
             .collect(),
     )
@@ -265,24 +705,517 @@ fn convert_table_info(
             .map(|(k, column_info)| (k, convert_column_info(column_info)))
             .collect(),
         // uniqueness_constraints: (table_info.uniqueness_constraints),
-        // foreign_relations: convert_foreign_relations(table_info.foreign_relations),
+        foreign_relations: table_info.foreign_relations,
         description: table_info.description,
         partition_key: table_info.partition_key,
         sort_key: table_info.sort_key,
         gsi: table_info.gsi,
+        version_attribute: table_info.version_attribute,
+        scan_policy: table_info.scan_policy,
+        key_templates: table_info.key_templates,
+        excluded_columns: table_info.excluded_columns,
+        stale: table_info.stale,
+        entity: table_info.entity,
     }
 }
 
+/// The tables the entities are read as: the table holding their items, with the columns of the
+/// entity.
+fn entity_tables(
+    tables: &query_engine_metadata::metadata::TablesInfo,
+    entities: &metadata::Entities,
+) -> query_engine_metadata::metadata::TablesInfo {
+    query_engine_metadata::metadata::TablesInfo(
+        entities
+            .0
+            .iter()
+            .filter_map(|(entity_name, entity_info)| {
+                let table_info = tables.0.get(&entity_info.table)?;
+                Some((
+                    entity_name.clone(),
+                    query_engine_metadata::metadata::TableInfo {
+                        table_name: table_info.table_name.clone(),
+                        columns: entity_info
+                            .columns
+                            .clone()
+                            .into_iter()
+                            .map(|(k, column_info)| (k, convert_column_info(column_info)))
+                            .collect(),
+                        partition_key: table_info.partition_key.clone(),
+                        sort_key: table_info.sort_key.clone(),
+                        gsi: table_info.gsi.clone(),
+                        description: entity_info.description.clone(),
+                        version_attribute: None,
+                        scan_policy: table_info.scan_policy.clone(),
+                        foreign_relations: metadata::ForeignRelations::default(),
+                        key_templates: entity_info.key_templates.clone(),
+                        excluded_columns: BTreeSet::new(),
+                        stale: false,
+                        entity: Some(metadata::Entity {
+                            discriminator: entity_info.discriminator.clone(),
+                            key_prefixes: entity_info.key_prefixes.clone(),
+                        }),
+                    },
+                ))
+            })
+            .collect(),
+    )
+}
+
 fn convert_column_info(
     column_info: metadata::ColumnInfo,
 ) -> query_engine_metadata::metadata::ColumnInfo {
     query_engine_metadata::metadata::ColumnInfo {
         name: column_info.name,
         r#type: convert_type(column_info.r#type),
-        nullable: convert_nullable(&column_info.nullable),
+        // stale columns were found in no sampled item, so their values may be missing whatever
+        // the user declared
+        nullable: if column_info.stale {
+            query_engine_metadata::metadata::Nullable::Nullable
+        } else {
+            convert_nullable(&column_info.nullable)
+        },
         // has_default: convert_has_default(&column_info.has_default),
         // is_identity: convert_is_identity(&column_info.is_identity),
         // is_generated: convert_is_generated(&column_info.is_generated),
         description: column_info.description,
+        stale: column_info.stale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_key_template, convert_metadata, validate_entities, validate_foreign_relations,
+        validate_native_mutations,
+    };
+    use crate::error::MakeRuntimeConfigurationError;
+    use query_engine_metadata::metadata;
+
+    fn column(name: &str) -> serde_json::Value {
+        serde_json::json!({ "name": name, "type": { "scalarType": "String" } })
+    }
+
+    /// An orders table with a foreign key of the given column mapping to a users table, keyed
+    /// on `id` and `created`, with an index keyed on `email`.
+    fn tables(foreign_table: &str, column_mapping: serde_json::Value) -> metadata::TablesInfo {
+        serde_json::from_value(serde_json::json!({
+            "users": {
+                "tableName": "users",
+                "columns": {
+                    "id": column("id"),
+                    "created": column("created"),
+                    "email": column("email"),
+                    "name": column("name"),
+                },
+                "partitionKey": "id",
+                "sortKey": "created",
+                "gsi": {
+                    "by_email": {
+                        "partition_key": "email",
+                        "sort_key": null,
+                        "projection_type": { "projection_type": "ALL", "non_key_attributes": [] },
+                    },
+                },
+            },
+            "orders": {
+                "tableName": "orders",
+                "columns": {
+                    "order_id": column("order_id"),
+                    "user_id": column("user_id"),
+                    "user_created": column("user_created"),
+                    "user_email": column("user_email"),
+                },
+                "partitionKey": "order_id",
+                "sortKey": "",
+                "gsi": {},
+                "foreignRelations": {
+                    "user": { "foreignTable": foreign_table, "columnMapping": column_mapping },
+                },
+            },
+        }))
+        .unwrap()
+    }
+
+    fn error_message(tables: &metadata::TablesInfo) -> String {
+        match validate_foreign_relations(tables) {
+            Err(MakeRuntimeConfigurationError::InvalidForeignKey {
+                table,
+                foreign_key,
+                message,
+            }) => {
+                assert_eq!((table.as_str(), foreign_key.as_str()), ("orders", "user"));
+                message
+            }
+            result => panic!("expected an invalid foreign key, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_foreign_key_to_the_table_key() {
+        let partition_key = tables("users", serde_json::json!({ "user_id": "id" }));
+        assert!(validate_foreign_relations(&partition_key).is_ok());
+
+        let full_key = tables(
+            "users",
+            serde_json::json!({ "user_id": "id", "user_created": "created" }),
+        );
+        assert!(validate_foreign_relations(&full_key).is_ok());
+    }
+
+    #[test]
+    fn test_foreign_key_to_an_index_key() {
+        let tables = tables("users", serde_json::json!({ "user_email": "email" }));
+        assert!(validate_foreign_relations(&tables).is_ok());
+    }
+
+    #[test]
+    fn test_foreign_key_to_an_unknown_table() {
+        let tables = tables("accounts", serde_json::json!({ "user_id": "id" }));
+        assert_eq!(error_message(&tables), "unknown foreign table \"accounts\"");
+    }
+
+    #[test]
+    fn test_foreign_key_with_an_empty_mapping() {
+        let tables = tables("users", serde_json::json!({}));
+        assert_eq!(error_message(&tables), "the column mapping is empty");
+    }
+
+    #[test]
+    fn test_foreign_key_from_an_unknown_column() {
+        let tables = tables("users", serde_json::json!({ "customer_id": "id" }));
+        assert_eq!(error_message(&tables), "unknown column \"customer_id\"");
+    }
+
+    #[test]
+    fn test_foreign_key_to_an_unknown_column() {
+        let tables = tables("users", serde_json::json!({ "user_id": "uuid" }));
+        assert_eq!(
+            error_message(&tables),
+            "unknown column \"uuid\" of the foreign table"
+        );
+    }
+
+    #[test]
+    fn test_foreign_key_to_columns_that_are_not_a_key() {
+        let not_a_key = tables("users", serde_json::json!({ "user_id": "name" }));
+        assert!(error_message(&not_a_key).starts_with("the foreign columns must be the key"));
+
+        // the sort key alone is not a key
+        let sort_key = tables("users", serde_json::json!({ "user_created": "created" }));
+        assert!(error_message(&sort_key).starts_with("the foreign columns must be the key"));
+
+        // nor are the keys of the table and an index together
+        let mixed = tables(
+            "users",
+            serde_json::json!({ "user_id": "id", "user_email": "email" }),
+        );
+        assert!(error_message(&mixed).starts_with("the foreign columns must be the key"));
+    }
+
+    #[test]
+    fn test_foreign_keys_and_stale_tables() {
+        let mut to_stale = tables("users", serde_json::json!({ "user_id": "id" }));
+        to_stale.0.get_mut("users").unwrap().stale = true;
+        assert_eq!(
+            error_message(&to_stale),
+            "the foreign table \"users\" is stale"
+        );
+
+        // stale tables are left out of the schema, along with their foreign keys
+        let mut from_stale = tables("accounts", serde_json::json!({ "user_id": "id" }));
+        from_stale.0.get_mut("orders").unwrap().stale = true;
+        assert!(validate_foreign_relations(&from_stale).is_ok());
+    }
+
+    #[test]
+    fn test_stale_columns_are_nullable() {
+        let mut metadata = metadata::Metadata {
+            tables: tables("users", serde_json::json!({ "user_id": "id" })),
+            ..metadata::Metadata::default()
+        };
+        for (name, stale) in [("email", false), ("name", true)] {
+            let column_info = metadata
+                .tables
+                .0
+                .get_mut("users")
+                .unwrap()
+                .columns
+                .get_mut(name)
+                .unwrap();
+            column_info.nullable = metadata::Nullable::NonNullable;
+            column_info.stale = stale;
+        }
+        let metadata = convert_metadata(metadata);
+        let users = &metadata.tables.0["users"];
+
+        assert_eq!(
+            users.columns["email"].nullable,
+            metadata::Nullable::NonNullable
+        );
+        assert_eq!(users.columns["name"].nullable, metadata::Nullable::Nullable);
+    }
+
+    #[test]
+    fn test_stale_tables_are_left_out() {
+        let mut metadata = metadata::Metadata {
+            tables: tables("users", serde_json::json!({ "user_id": "id" })),
+            ..metadata::Metadata::default()
+        };
+        metadata.tables.0.get_mut("orders").unwrap().stale = true;
+        let metadata = convert_metadata(metadata);
+
+        assert_eq!(
+            metadata
+                .tables
+                .0
+                .keys()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>(),
+            ["users"]
+        );
+    }
+
+    /// The metadata of the tables of [`tables`], with a native mutation of the given name.
+    fn metadata_with_native_mutation(name: &str) -> metadata::Metadata {
+        metadata::Metadata {
+            tables: tables("users", serde_json::json!({ "user_id": "id" })),
+            composite_types: serde_json::from_value(serde_json::json!({
+                "users_address": {
+                    "typeName": "users_address",
+                    "fields": {},
+                    "description": null,
+                },
+            }))
+            .unwrap(),
+            native_mutations: serde_json::from_value(serde_json::json!({
+                name: {
+                    "sql": "UPDATE users SET name = {{name}} WHERE id = {{id}}",
+                    "arguments": { "id": column("id"), "name": column("name") },
+                },
+            }))
+            .unwrap(),
+            ..metadata::Metadata::default()
+        }
+    }
+
+    fn native_mutation_error_message(name: &str) -> String {
+        match validate_native_mutations(&metadata_with_native_mutation(name)) {
+            Err(MakeRuntimeConfigurationError::InvalidNativeMutation {
+                name: error_name,
+                message,
+            }) => {
+                assert_eq!(error_name, name);
+                message
+            }
+            result => panic!("expected an invalid native mutation, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_native_mutation_named_apart() {
+        assert!(validate_native_mutations(&metadata_with_native_mutation("rename_user")).is_ok());
+    }
+
+    #[test]
+    fn test_native_mutation_named_like_a_generated_procedure() {
+        for name in [
+            "insert_users_many",
+            "delete_users_many",
+            "update_orders_by_key",
+            "delete_orders_by_key",
+        ] {
+            assert_eq!(
+                native_mutation_error_message(name),
+                "a procedure generated for a table has the same name"
+            );
+        }
+    }
+
+    #[test]
+    fn test_native_mutation_named_like_a_generated_object_type() {
+        for name in [
+            "users_key",
+            "orders_update",
+            "batch_write_result",
+            "batch_write_item_result",
+        ] {
+            assert_eq!(
+                native_mutation_error_message(name),
+                "an object type generated for the procedures of the tables has the same name"
+            );
+        }
+    }
+
+    #[test]
+    fn test_native_mutation_named_like_a_table_or_composite_type() {
+        assert_eq!(
+            native_mutation_error_message("users"),
+            "a table has the same name"
+        );
+        assert_eq!(
+            native_mutation_error_message("users_address"),
+            "a composite type has the same name"
+        );
+    }
+
+    /// The metadata of the tables of [`tables`], with a `customer` entity of the given table
+    /// and discriminator.
+    fn metadata_with_entity(
+        name: &str,
+        table: &str,
+        discriminator: serde_json::Value,
+    ) -> metadata::Metadata {
+        metadata::Metadata {
+            tables: tables("users", serde_json::json!({ "user_id": "id" })),
+            entities: serde_json::from_value(serde_json::json!({
+                name: {
+                    "table": table,
+                    "discriminator": discriminator,
+                    "columns": { "id": column("id"), "email": column("email") },
+                    "keyPrefixes": { "id": "CUSTOMER#" },
+                },
+            }))
+            .unwrap(),
+            ..metadata::Metadata::default()
+        }
+    }
+
+    fn entity_error_message(metadata: &metadata::Metadata) -> String {
+        match validate_entities(metadata) {
+            Err(MakeRuntimeConfigurationError::InvalidEntity { message, .. }) => message,
+            result => panic!("expected an invalid entity, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_entities_over_a_known_table() {
+        let by_value = metadata_with_entity(
+            "customer",
+            "users",
+            serde_json::json!({ "attributeValue": { "attribute": "type", "value": "customer" } }),
+        );
+        assert!(validate_entities(&by_value).is_ok());
+
+        let by_prefix = metadata_with_entity(
+            "customer",
+            "users",
+            serde_json::json!({ "keyPrefix": { "attribute": "id" } }),
+        );
+        assert!(validate_entities(&by_prefix).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_entities() {
+        let discriminator =
+            serde_json::json!({ "attributeValue": { "attribute": "type", "value": "customer" } });
+        assert_eq!(
+            entity_error_message(&metadata_with_entity(
+                "orders",
+                "users",
+                discriminator.clone()
+            )),
+            "a table has the same name"
+        );
+        assert_eq!(
+            entity_error_message(&metadata_with_entity(
+                "customer",
+                "accounts",
+                discriminator.clone()
+            )),
+            "unknown table \"accounts\""
+        );
+        let mut over_a_stale_table = metadata_with_entity("customer", "users", discriminator);
+        over_a_stale_table.tables.0.get_mut("users").unwrap().stale = true;
+        assert_eq!(
+            entity_error_message(&over_a_stale_table),
+            "the table \"users\" is stale"
+        );
+        assert_eq!(
+            entity_error_message(&metadata_with_entity(
+                "customer",
+                "users",
+                serde_json::json!({ "keyPrefix": { "attribute": "created" } }),
+            )),
+            "the discriminator attribute \"created\" has no key prefix"
+        );
+    }
+
+    #[test]
+    fn test_entities_are_read_as_tables_of_their_own() {
+        let metadata = convert_metadata(metadata_with_entity(
+            "customer",
+            "users",
+            serde_json::json!({ "keyPrefix": { "attribute": "id" } }),
+        ));
+        let users = &metadata.tables.0["users"];
+        let customer = &metadata.tables.0["customer"];
+
+        assert_eq!(customer.table_name, "users");
+        assert_eq!(
+            (customer.partition_key.as_str(), customer.sort_key.as_str()),
+            ("id", "created")
+        );
+        assert_eq!(customer.gsi, users.gsi);
+        assert_eq!(
+            customer
+                .columns
+                .keys()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>(),
+            ["email", "id"]
+        );
+        assert!(customer.foreign_relations.is_empty());
+        assert_eq!(customer.version_attribute, None);
+        let entity = customer.entity.as_ref().unwrap();
+        assert_eq!(
+            entity.discriminator,
+            metadata::Discriminator::KeyPrefix {
+                attribute: "id".to_string()
+            }
+        );
+        assert_eq!(entity.key_prefixes["id"], "CUSTOMER#");
+        assert_eq!(users.entity, None);
+    }
+
+    fn check_template(template: &str, fields: &[&str]) -> Result<(), String> {
+        let key_template: metadata::KeyTemplate = serde_json::from_value(serde_json::json!({
+            "template": template,
+            "fields": fields
+                .iter()
+                .map(|field| (field.to_string(), serde_json::json!({ "type": "String" })))
+                .collect::<serde_json::Map<_, _>>(),
+        }))
+        .unwrap();
+        check_key_template(key_template.parts(), &key_template.fields)
+    }
+
+    #[test]
+    fn test_key_templates_that_split_back_into_fields() {
+        assert_eq!(
+            check_template("ORDER#{orderDate}#{orderId}", &["orderDate", "orderId"]),
+            Ok(())
+        );
+        assert_eq!(check_template("{userId}", &["userId"]), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_key_templates() {
+        assert_eq!(
+            check_template("ORDER#{orderDate}{orderId}", &["orderDate", "orderId"]),
+            Err("placeholders must be separated by text".to_string())
+        );
+        assert_eq!(
+            check_template("ORDER#{orderDate}#{orderId}", &["orderDate"]),
+            Err("the placeholder {orderId} is not a declared field".to_string())
+        );
+        assert_eq!(
+            check_template("ORDER#{orderId}#{orderId}", &["orderId"]),
+            Err("the placeholder {orderId} appears twice".to_string())
+        );
+        assert_eq!(
+            check_template("ORDER#{orderId}", &["orderDate", "orderId"]),
+            Err("the field \"orderDate\" has no placeholder".to_string())
+        );
     }
 }
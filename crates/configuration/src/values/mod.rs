@@ -2,5 +2,5 @@ pub mod connection_info;
 mod pool_settings;
 mod secret;
 
-pub use connection_info::{AccessKeyId, Region, SecretAccessKey};
+pub use connection_info::{AccessKeyId, EndpointUrl, Region, SecretAccessKey};
 pub use secret::Secret;
@@ -0,0 +1,140 @@
+//! Version 2 of the configuration format, adding the endpoint URL of the connection and the
+//! introspection options.
+
+use crate::configuration::{ExplainOptions, IntrospectionOptions, QueryOptions};
+use crate::connection_settings;
+use crate::environment::Environment;
+use crate::introspection::{introspect_metadata, make_client};
+use crate::values::{AccessKeyId, EndpointUrl, Region, Secret, SecretAccessKey};
+use crate::version1;
+
+use query_engine_metadata::metadata;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Initial configuration, just enough to connect to a database and elaborate a full
+/// 'Configuration'.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ParsedConfiguration {
+    // Which version of the configuration format are we using
+    pub version: u32,
+    pub connection_settings: DatabaseConnectionSettings,
+    #[serde(default)]
+    pub metadata: metadata::Metadata,
+    #[serde(default, skip_serializing_if = "IntrospectionOptions::is_default")]
+    pub introspection_options: IntrospectionOptions,
+    #[serde(default, skip_serializing_if = "ExplainOptions::is_default")]
+    pub explain_options: ExplainOptions,
+    #[serde(default, skip_serializing_if = "QueryOptions::is_default")]
+    pub query_options: QueryOptions,
+}
+
+/// Database connection settings.
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseConnectionSettings {
+    pub access_key_id: AccessKeyId,
+    pub secret_access_key: SecretAccessKey,
+    pub region: Region,
+    /// The endpoint to send requests to instead of the endpoint of the region, such as
+    /// `http://localhost:8000` for DynamoDB Local.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_url: Option<EndpointUrl>,
+}
+
+impl ParsedConfiguration {
+    pub fn empty() -> Self {
+        let connection_settings = connection_settings::DatabaseConnectionSettings::empty();
+        Self {
+            version: CURRENT_VERSION,
+            connection_settings: DatabaseConnectionSettings {
+                access_key_id: connection_settings.access_key_id,
+                secret_access_key: connection_settings.secret_access_key,
+                region: connection_settings.region,
+                endpoint_url: None,
+            },
+            metadata: metadata::Metadata::default(),
+            introspection_options: IntrospectionOptions::default(),
+            explain_options: ExplainOptions::default(),
+            query_options: QueryOptions::default(),
+        }
+    }
+}
+
+pub async fn introspect(
+    args: &ParsedConfiguration,
+    environment: impl Environment,
+) -> anyhow::Result<ParsedConfiguration> {
+    let read = |secret: &Secret| -> anyhow::Result<String> {
+        match secret {
+            Secret::Plain(value) => Ok(value.clone()),
+            Secret::FromEnvironment { variable } => Ok(environment.read(variable)?),
+        }
+    };
+    let AccessKeyId(access_key_id) = &args.connection_settings.access_key_id;
+    let SecretAccessKey(secret_access_key) = &args.connection_settings.secret_access_key;
+    let Region(region) = &args.connection_settings.region;
+    let endpoint_url = match &args.connection_settings.endpoint_url {
+        Some(EndpointUrl(endpoint_url)) => Some(read(endpoint_url)?),
+        None => None,
+    };
+    let client = make_client(
+        &read(access_key_id)?,
+        &read(secret_access_key)?,
+        &read(region)?,
+        endpoint_url.as_deref(),
+    );
+    let metadata =
+        introspect_metadata(&client, &args.metadata, &args.introspection_options).await?;
+    Ok(ParsedConfiguration {
+        metadata,
+        ..args.clone()
+    })
+}
+
+/// Upgrade a version 1 configuration. Version 2 only adds options, so nothing is lost, and the
+/// new options get their defaults.
+pub fn upgrade_from_v1(v1: version1::ParsedConfiguration) -> ParsedConfiguration {
+    let version1::ParsedConfiguration {
+        version: _,
+        connection_settings,
+        metadata,
+        explain_options,
+        query_options,
+    } = v1;
+    ParsedConfiguration {
+        version: CURRENT_VERSION,
+        connection_settings: DatabaseConnectionSettings {
+            access_key_id: connection_settings.access_key_id,
+            secret_access_key: connection_settings.secret_access_key,
+            region: connection_settings.region,
+            endpoint_url: None,
+        },
+        metadata,
+        introspection_options: IntrospectionOptions::default(),
+        explain_options,
+        query_options,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::upgrade_from_v1;
+    use crate::version1;
+
+    #[test]
+    fn test_upgrade_from_v1_keeps_everything_but_the_version() {
+        let v1: version1::ParsedConfiguration =
+            serde_json::from_str(include_str!("../../../static/configuration.json")).unwrap();
+        let v1_json = serde_json::to_value(&v1).unwrap();
+
+        let mut v2_json = serde_json::to_value(upgrade_from_v1(v1)).unwrap();
+        assert_eq!(v2_json["version"], 2);
+
+        v2_json["version"] = v1_json["version"].clone();
+        assert_eq!(v2_json, v1_json);
+    }
+}
@@ -10,21 +10,26 @@ pub fn get_capabilities() -> models::Capabilities {
     models::Capabilities {
         query: models::QueryCapabilities {
             aggregates: Some(models::LeafCapability {}),
-            variables: None,
-            explain: None,
+            variables: Some(models::LeafCapability {}),
+            explain: Some(models::LeafCapability {}),
             exists: models::ExistsCapabilities {
                 nested_collections: None,
             },
             nested_fields: models::NestedFieldCapabilities {
-                filter_by: None,
-                order_by: None,
+                filter_by: Some(models::LeafCapability {}),
+                order_by: Some(models::LeafCapability {}),
                 aggregates: None,
             },
         },
         mutation: models::MutationCapabilities {
             transactional: None,
-            explain: None,
+            explain: Some(models::LeafCapability {}),
         },
-        relationships: None,
+        // Relationships run as batched lookups. Ordering by columns or aggregates of related
+        // collections is not supported yet: orderings with a relationship path fail.
+        relationships: Some(models::RelationshipCapabilities {
+            relation_comparisons: None,
+            order_by_aggregate: None,
+        }),
     }
 }
@@ -18,7 +18,7 @@ use ndc_dynamodb_configuration as configuration;
 use ndc_dynamodb_configuration::environment::Environment;
 
 use super::capabilities;
-// use super::mutation;
+use super::mutation;
 use super::query;
 use super::schema;
 use super::state;
@@ -83,11 +83,23 @@ impl Connector for DynamoDB {
     /// This function implements the [query/explain endpoint](https://hasura.github.io/ndc-spec/specification/explain.html)
     /// from the NDC specification.
     async fn query_explain(
-        _configuration: &Self::Configuration,
-        _state: &Self::State,
-        _request: models::QueryRequest,
+        configuration: &Self::Configuration,
+        state: &Self::State,
+        request: models::QueryRequest,
     ) -> Result<JsonResponse<models::ExplainResponse>> {
-        todo!("query explain is currently not implemented")
+        query::explain(configuration, state, request)
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    meta.signal_type = "log",
+                    event.domain = "ndc",
+                    event.name = "Explain error",
+                    name = "Explain error",
+                    body = %err,
+                    error = true,
+                );
+                err
+            })
     }
 
     /// Explain a mutation by creating an execution plan
@@ -95,11 +107,23 @@ impl Connector for DynamoDB {
     /// This function implements the [mutation/explain endpoint](https://hasura.github.io/ndc-spec/specification/explain.html)
     /// from the NDC specification.
     async fn mutation_explain(
-        _configuration: &Self::Configuration,
-        _state: &Self::State,
-        _request: models::MutationRequest,
+        configuration: &Self::Configuration,
+        state: &Self::State,
+        request: models::MutationRequest,
     ) -> Result<JsonResponse<models::ExplainResponse>> {
-        todo!("mutation explain is currently not implemented")
+        mutation::explain(configuration, state, request)
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    meta.signal_type = "log",
+                    event.domain = "ndc",
+                    event.name = "Explain error",
+                    name = "Explain error",
+                    body = %err,
+                    error = true,
+                );
+                err
+            })
     }
 
     /// Execute a mutation
@@ -107,11 +131,23 @@ impl Connector for DynamoDB {
     /// This function implements the [mutation endpoint](https://hasura.github.io/ndc-spec/specification/mutations/index.html)
     /// from the NDC specification.
     async fn mutation(
-        _configuration: &Self::Configuration,
-        _state: &Self::State,
-        _request: models::MutationRequest,
+        configuration: &Self::Configuration,
+        state: &Self::State,
+        request: models::MutationRequest,
     ) -> Result<JsonResponse<models::MutationResponse>> {
-        todo!("mutation is currently not implemented")
+        mutation::mutation(configuration, state, request)
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    meta.signal_type = "log",
+                    event.domain = "ndc",
+                    event.name = "Mutation error",
+                    name = "Mutation error",
+                    body = %err,
+                    error = true,
+                );
+                err
+            })
     }
 
     /// Execute a query
@@ -234,6 +270,97 @@ impl<Env: Environment + Send + Sync> ConnectorSetup for DynamoDBSetup<Env> {
                     message,
                 },
             ])),
+            configuration::error::MakeRuntimeConfigurationError::InvalidVersionAttribute {
+                ref table,
+                ..
+            } => connector::ParseError::ValidateError(connector::InvalidNodes(vec![
+                connector::InvalidNode {
+                    file_path: configuration::version1::CONFIGURATION_FILENAME.into(),
+                    node_path: vec![
+                        connector::KeyOrIndex::Key("metadata".into()),
+                        connector::KeyOrIndex::Key("tables".into()),
+                        connector::KeyOrIndex::Key(table.clone()),
+                        connector::KeyOrIndex::Key("versionAttribute".into()),
+                    ],
+                    message: error.to_string(),
+                },
+            ])),
+            configuration::error::MakeRuntimeConfigurationError::InvalidNativeQuery {
+                ref name,
+                ..
+            } => connector::ParseError::ValidateError(connector::InvalidNodes(vec![
+                connector::InvalidNode {
+                    file_path: configuration::version1::CONFIGURATION_FILENAME.into(),
+                    node_path: vec![
+                        connector::KeyOrIndex::Key("metadata".into()),
+                        connector::KeyOrIndex::Key("nativeQueries".into()),
+                        connector::KeyOrIndex::Key(name.clone()),
+                    ],
+                    message: error.to_string(),
+                },
+            ])),
+            configuration::error::MakeRuntimeConfigurationError::InvalidNativeMutation {
+                ref name,
+                ..
+            } => connector::ParseError::ValidateError(connector::InvalidNodes(vec![
+                connector::InvalidNode {
+                    file_path: configuration::version1::CONFIGURATION_FILENAME.into(),
+                    node_path: vec![
+                        connector::KeyOrIndex::Key("metadata".into()),
+                        connector::KeyOrIndex::Key("nativeMutations".into()),
+                        connector::KeyOrIndex::Key(name.clone()),
+                    ],
+                    message: error.to_string(),
+                },
+            ])),
+            configuration::error::MakeRuntimeConfigurationError::InvalidEntity {
+                ref name, ..
+            } => connector::ParseError::ValidateError(connector::InvalidNodes(vec![
+                connector::InvalidNode {
+                    file_path: configuration::version1::CONFIGURATION_FILENAME.into(),
+                    node_path: vec![
+                        connector::KeyOrIndex::Key("metadata".into()),
+                        connector::KeyOrIndex::Key("entities".into()),
+                        connector::KeyOrIndex::Key(name.clone()),
+                    ],
+                    message: error.to_string(),
+                },
+            ])),
+            configuration::error::MakeRuntimeConfigurationError::InvalidKeyTemplate {
+                ref section,
+                ref collection,
+                ref attribute,
+                ..
+            } => connector::ParseError::ValidateError(connector::InvalidNodes(vec![
+                connector::InvalidNode {
+                    file_path: configuration::version1::CONFIGURATION_FILENAME.into(),
+                    node_path: vec![
+                        connector::KeyOrIndex::Key("metadata".into()),
+                        connector::KeyOrIndex::Key(section.clone()),
+                        connector::KeyOrIndex::Key(collection.clone()),
+                        connector::KeyOrIndex::Key("keyTemplates".into()),
+                        connector::KeyOrIndex::Key(attribute.clone()),
+                    ],
+                    message: error.to_string(),
+                },
+            ])),
+            configuration::error::MakeRuntimeConfigurationError::InvalidForeignKey {
+                ref table,
+                ref foreign_key,
+                ..
+            } => connector::ParseError::ValidateError(connector::InvalidNodes(vec![
+                connector::InvalidNode {
+                    file_path: configuration::version1::CONFIGURATION_FILENAME.into(),
+                    node_path: vec![
+                        connector::KeyOrIndex::Key("metadata".into()),
+                        connector::KeyOrIndex::Key("tables".into()),
+                        connector::KeyOrIndex::Key(table.clone()),
+                        connector::KeyOrIndex::Key("foreignRelations".into()),
+                        connector::KeyOrIndex::Key(foreign_key.clone()),
+                    ],
+                    message: error.to_string(),
+                },
+            ])),
         }
                 })?;
 
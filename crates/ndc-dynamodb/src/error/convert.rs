@@ -7,20 +7,26 @@ pub fn execution_error_to_response(error: query_engine_execution::error::Error)
     use query_engine_execution::error::*;
     match error {
         Error::Query(query_error) => match &query_error {
-            QueryError::VariableNotFound(_) => {
+            QueryError::VariableNotFound(_) | QueryError::InvalidValue(_) => {
                 connector::QueryError::new_invalid_request(&query_error.to_string()).into()
             }
             QueryError::NotSupported(_) => {
                 connector::QueryError::new_unsupported_operation(&query_error.to_string()).into()
             }
-            QueryError::DBError(_) => {
+            QueryError::DBError(_)
+            | QueryError::FullScanDenied { .. }
+            | QueryError::TooManyItemsToSort { .. }
+            | QueryError::TooManyJoinKeys { .. } => {
                 connector::QueryError::new_unprocessable_content(&query_error.to_string()).into()
             }
             QueryError::DBConstraintError(_) | QueryError::MutationConstraintFailed => {
                 connector::MutationError::new_constraint_not_met(&query_error.to_string()).into()
             }
+            QueryError::VersionConflict { .. } => {
+                connector::MutationError::new_conflict(&query_error.to_string()).into()
+            }
         },
-        Error::DB(_) => {
+        Error::DB(_) | Error::DynamoDB(_) => {
             ErrorResponse::new_internal_with_details(serde_json::Value::String(error.to_string()))
         }
     }
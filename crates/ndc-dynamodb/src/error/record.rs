@@ -9,8 +9,13 @@ pub fn execution_error(error: &query_engine_execution::error::Error, metrics: &m
     match error {
         Error::Query(err) => match &err {
             QueryError::VariableNotFound(_)
+            | QueryError::InvalidValue(_)
             | QueryError::DBError(_)
             | QueryError::MutationConstraintFailed
+            | QueryError::VersionConflict { .. }
+            | QueryError::FullScanDenied { .. }
+            | QueryError::TooManyItemsToSort { .. }
+            | QueryError::TooManyJoinKeys { .. }
             | QueryError::DBConstraintError(_) => {
                 metrics.error_metrics.record_invalid_request();
             }
@@ -18,7 +23,7 @@ pub fn execution_error(error: &query_engine_execution::error::Error, metrics: &m
                 metrics.error_metrics.record_unsupported_feature();
             }
         },
-        Error::DB(_) => {
+        Error::DB(_) | Error::DynamoDB(_) => {
             metrics.error_metrics.record_database_error();
         }
     }
@@ -4,6 +4,7 @@ pub mod capabilities;
 pub mod connector;
 pub mod error;
 pub mod health;
+pub mod mutation;
 pub mod query;
 pub mod schema;
 pub mod state;
@@ -0,0 +1,52 @@
+//! Implement the `/mutation/explain` endpoint to describe the writes a mutation would make.
+//! See the Hasura
+//! [Native Data Connector Specification](https://hasura.github.io/ndc-spec/specification/explain.html)
+//! for further details.
+
+use tracing::{info_span, Instrument};
+
+use ndc_sdk::connector;
+use ndc_sdk::json_response::JsonResponse;
+use ndc_sdk::models;
+
+use ndc_dynamodb_configuration as configuration;
+
+use crate::error::convert;
+use crate::error::record;
+use crate::state;
+
+/// Explain a mutation by creating an execution plan
+///
+/// This function implements the [mutation/explain endpoint](https://hasura.github.io/ndc-spec/specification/explain.html)
+/// from the NDC specification.
+pub async fn explain(
+    configuration: &configuration::Configuration,
+    state: &state::State,
+    request: models::MutationRequest,
+) -> Result<JsonResponse<models::ExplainResponse>, connector::ErrorResponse> {
+    async move {
+        tracing::info!(
+            request_json = serde_json::to_string(&request).unwrap(),
+            request = ?request
+        );
+
+        let plan = async {
+            super::plan_mutation(configuration, state, request).map_err(|err| {
+                record::translation_error(&err, &state.metrics);
+                convert::translation_error_to_response(&err)
+            })
+        }
+        .instrument(info_span!("Plan mutation"))
+        .await?;
+
+        let details = query_engine_execution::mutation::explain(
+            &plan,
+            configuration.explain_options.show_parameter_values,
+        );
+
+        state.metrics.record_successful_explain();
+        Ok(JsonResponse::Value(models::ExplainResponse { details }))
+    }
+    .instrument(info_span!("/mutation/explain"))
+    .await
+}
@@ -0,0 +1,86 @@
+//! Implement the `/mutation` endpoint to run procedures against DynamoDB.
+//! See the Hasura
+//! [Native Data Connector Specification](https://hasura.github.io/ndc-spec/specification/mutations/index.html)
+//! for further details.
+
+use tracing::{info_span, Instrument};
+
+pub use explain::explain;
+
+use ndc_sdk::connector;
+use ndc_sdk::json_response::JsonResponse;
+use ndc_sdk::models;
+use query_engine_sql::sql;
+use query_engine_translation::translation;
+
+use ndc_dynamodb_configuration as configuration;
+
+use crate::error::convert;
+use crate::error::record;
+use crate::state;
+
+mod explain;
+
+/// Execute a mutation
+///
+/// This function implements the [mutation endpoint](https://hasura.github.io/ndc-spec/specification/mutations/index.html)
+/// from the NDC specification.
+pub async fn mutation(
+    configuration: &configuration::Configuration,
+    state: &state::State,
+    request: models::MutationRequest,
+) -> Result<JsonResponse<models::MutationResponse>, connector::ErrorResponse> {
+    async move {
+        tracing::info!(
+            request_json = serde_json::to_string(&request).unwrap(),
+            request = ?request
+        );
+
+        let plan = async {
+            plan_mutation(configuration, state, request).map_err(|err| {
+                record::translation_error(&err, &state.metrics);
+                convert::translation_error_to_response(&err)
+            })
+        }
+        .instrument(info_span!("Plan mutation"))
+        .await?;
+
+        let result = async {
+            execute_mutation(state, plan).await.map_err(|err| {
+                record::execution_error(&err, &state.metrics);
+                convert::execution_error_to_response(err)
+            })
+        }
+        .instrument(info_span!("Execute mutation"))
+        .await?;
+
+        state.metrics.record_successful_mutation();
+        Ok(result)
+    }
+    .instrument(info_span!("/mutation"))
+    .await
+}
+
+pub(crate) fn plan_mutation(
+    configuration: &configuration::Configuration,
+    state: &state::State,
+    request: models::MutationRequest,
+) -> Result<
+    sql::execution_plan::ExecutionPlan<Vec<sql::execution_plan::Mutation>>,
+    translation::error::Error,
+> {
+    let timer = state.metrics.time_mutation_plan();
+    let result = translation::mutation::translate(&configuration.metadata, request);
+    timer.complete_with(result)
+}
+
+async fn execute_mutation(
+    state: &state::State,
+    plan: sql::execution_plan::ExecutionPlan<Vec<sql::execution_plan::Mutation>>,
+) -> Result<JsonResponse<models::MutationResponse>, query_engine_execution::error::Error> {
+    let timer = state.metrics.time_mutation_execution();
+    let result = query_engine_execution::mutation::execute(&state.client, &state.metrics, plan)
+        .await
+        .map(JsonResponse::Value);
+    timer.complete_with(result)
+}
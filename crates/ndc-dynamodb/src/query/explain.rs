@@ -0,0 +1,53 @@
+//! Implement the `/query/explain` endpoint to describe how a query would run.
+//! See the Hasura
+//! [Native Data Connector Specification](https://hasura.github.io/ndc-spec/specification/explain.html)
+//! for further details.
+
+use tracing::{info_span, Instrument};
+
+use ndc_sdk::connector;
+use ndc_sdk::json_response::JsonResponse;
+use ndc_sdk::models;
+
+use ndc_dynamodb_configuration as configuration;
+
+use crate::error::convert;
+use crate::error::record;
+use crate::state;
+
+/// Explain a query by creating an execution plan
+///
+/// This function implements the [query/explain endpoint](https://hasura.github.io/ndc-spec/specification/explain.html)
+/// from the NDC specification.
+pub async fn explain(
+    configuration: &configuration::Configuration,
+    state: &state::State,
+    query_request: models::QueryRequest,
+) -> Result<JsonResponse<models::ExplainResponse>, connector::ErrorResponse> {
+    async move {
+        tracing::info!(
+            query_request_json = serde_json::to_string(&query_request).unwrap(),
+            query_request = ?query_request
+        );
+
+        let plan = async {
+            super::plan_query(configuration, state, query_request).map_err(|err| {
+                record::translation_error(&err, &state.metrics);
+                convert::translation_error_to_response(&err)
+            })
+        }
+        .instrument(info_span!("Plan query"))
+        .await?;
+
+        let details = query_engine_execution::query::explain(
+            &plan,
+            configuration.explain_options.show_parameter_values,
+            configuration.query_options.max_items_sorted_in_memory,
+        );
+
+        state.metrics.record_successful_explain();
+        Ok(JsonResponse::Value(models::ExplainResponse { details }))
+    }
+    .instrument(info_span!("/query/explain"))
+    .await
+}
@@ -5,7 +5,7 @@
 
 use tracing::{info_span, Instrument};
 
-// pub use explain::explain;
+pub use explain::explain;
 use ndc_sdk::connector;
 use ndc_sdk::json_response::JsonResponse;
 use ndc_sdk::models;
@@ -19,6 +19,8 @@ use crate::error::convert;
 use crate::error::record;
 use crate::state;
 
+mod explain;
+
 /// Execute a query
 ///
 /// This function implements the [query endpoint](https://hasura.github.io/ndc-spec/specification/queries/index.html)
@@ -47,10 +49,12 @@ pub async fn query(
         .await?;
 
         let result = async {
-            execute_query(state, plan).await.map_err(|err| {
-                record::execution_error(&err, &state.metrics);
-                convert::execution_error_to_response(err)
-            })
+            execute_query(configuration, state, plan)
+                .await
+                .map_err(|err| {
+                    record::execution_error(&err, &state.metrics);
+                    convert::execution_error_to_response(err)
+                })
         }
         .instrument(info_span!("Execute query"))
         .await?;
@@ -64,7 +68,7 @@ pub async fn query(
     // timer.complete_with(result)
 }
 
-fn plan_query(
+pub(crate) fn plan_query(
     configuration: &configuration::Configuration,
     state: &state::State,
     query_request: models::QueryRequest,
@@ -76,12 +80,19 @@ fn plan_query(
 }
 
 async fn execute_query(
+    configuration: &configuration::Configuration,
     state: &state::State,
     plan: sql::execution_plan::ExecutionPlan<sql::execution_plan::Query>,
 ) -> Result<JsonResponse<models::QueryResponse>, query_engine_execution::error::Error> {
     let timer = state.metrics.time_query_execution();
-    let result = query_engine_execution::query::execute(&state.client, &state.metrics, plan)
-        .await
-        .map(JsonResponse::Serialized);
+    let result = query_engine_execution::query::execute(
+        &state.client,
+        &state.metrics,
+        plan,
+        configuration.query_options.max_exists_join_keys,
+        configuration.query_options.max_items_sorted_in_memory,
+    )
+    .await
+    .map(JsonResponse::Serialized);
     timer.complete_with(result)
 }
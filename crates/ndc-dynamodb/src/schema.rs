@@ -9,6 +9,8 @@ use ndc_sdk::connector;
 use ndc_sdk::models;
 use query_engine_metadata::metadata;
 use query_engine_metadata::metadata::OperatorKind;
+use query_engine_translation::translation::mutation::generate;
+use query_engine_translation::translation::query::pagination;
 
 use ndc_dynamodb_configuration::configuration;
 
@@ -20,7 +22,7 @@ pub fn get_schema(
     configuration: &configuration::Configuration,
 ) -> Result<models::SchemaResponse, connector::ErrorResponse> {
     let metadata = &configuration.metadata;
-    let scalar_types: BTreeMap<models::ScalarTypeName, models::ScalarType> = metadata
+    let mut scalar_types: BTreeMap<models::ScalarTypeName, models::ScalarType> = metadata
         .scalar_types
         .0
         .iter()
@@ -30,7 +32,23 @@ pub fn get_schema(
                     .type_representation
                     .as_ref()
                     .map(map_type_representation),
-                aggregate_functions: BTreeMap::new(),
+                aggregate_functions: scalar_type_info
+                    .aggregate_functions
+                    .iter()
+                    .map(|(function_name, function_info)| {
+                        (
+                            function_name.clone(),
+                            models::AggregateFunctionDefinition {
+                                // aggregates over no values are null
+                                result_type: models::Type::Nullable {
+                                    underlying_type: Box::new(models::Type::Named {
+                                        name: function_info.return_type.as_str().into(),
+                                    }),
+                                },
+                            },
+                        )
+                    })
+                    .collect(),
                 comparison_operators: scalar_type_info
                     .comparison_operators
                     .iter()
@@ -40,13 +58,29 @@ pub fn get_schema(
                             match op_def.operator_kind {
                                 OperatorKind::Equal => models::ComparisonOperatorDefinition::Equal,
                                 OperatorKind::In => models::ComparisonOperatorDefinition::In,
-                                OperatorKind::Custom => {
+                                OperatorKind::Custom
+                                | OperatorKind::BeginsWith
+                                | OperatorKind::Contains
+                                | OperatorKind::Size
+                                | OperatorKind::AttributeType
+                                | OperatorKind::IsMissing
+                                | OperatorKind::Exists => {
                                     models::ComparisonOperatorDefinition::Custom {
                                         argument_type: models::Type::Named {
                                             name: op_def.argument_type.as_str().into(),
                                         },
                                     }
                                 }
+                                // the lower and upper bounds, which must be literal values
+                                OperatorKind::Between => {
+                                    models::ComparisonOperatorDefinition::Custom {
+                                        argument_type: models::Type::Array {
+                                            element_type: Box::new(models::Type::Named {
+                                                name: op_def.argument_type.as_str().into(),
+                                            }),
+                                        },
+                                    }
+                                }
                             },
                         )
                     })
@@ -56,26 +90,81 @@ pub fn get_schema(
         })
         .collect();
 
-    let collections = metadata
+    let mut collections: Vec<models::CollectionInfo> = metadata
         .tables
         .0
         .iter()
         .map(|(table_name, table)| models::CollectionInfo {
             name: table_name.clone(),
             description: table.description.clone(),
-            arguments: BTreeMap::new(),
+            arguments: BTreeMap::from([(
+                pagination::AFTER_ARGUMENT.into(),
+                models::ArgumentInfo {
+                    description: Some(
+                        "Continue after the row with this cursor, as found in its cursor field. The cursor accounts for the offset of the query it came from, so the offset is not applied again"
+                            .to_string(),
+                    ),
+                    argument_type: models::Type::Nullable {
+                        underlying_type: Box::new(models::Type::Named {
+                            name: "String".into(),
+                        }),
+                    },
+                },
+            )]),
             collection_type: table_name.as_str().into(),
             uniqueness_constraints: BTreeMap::new(),
-            foreign_keys: BTreeMap::new(),
+            foreign_keys: table
+                .foreign_relations
+                .0
+                .iter()
+                .map(|(foreign_key, foreign_relation)| {
+                    (
+                        foreign_key.clone(),
+                        models::ForeignKeyConstraint {
+                            column_mapping: foreign_relation.column_mapping.clone(),
+                            foreign_collection: foreign_collection(
+                                &metadata.tables,
+                                foreign_relation,
+                            ),
+                        },
+                    )
+                })
+                .collect(),
         })
         .collect();
 
-    let object_types = metadata //BTreeMap::from_iter(metadata.tables.0.iter().map(|(table_name, table)| {
+    // Native queries take their parameters as arguments, and have no cursor to continue from.
+    collections.extend(metadata.native_queries.0.iter().map(
+        |(native_query_name, native_query)| {
+            models::CollectionInfo {
+                name: native_query_name.clone(),
+                description: native_query.description.clone(),
+                arguments: native_query
+                    .arguments
+                    .iter()
+                    .map(|(argument_name, column_info)| {
+                        (
+                            argument_name.clone(),
+                            models::ArgumentInfo {
+                                description: column_info.description.clone(),
+                                argument_type: column_to_type(column_info),
+                            },
+                        )
+                    })
+                    .collect(),
+                collection_type: native_query_name.as_str().into(),
+                uniqueness_constraints: BTreeMap::new(),
+                foreign_keys: BTreeMap::new(),
+            }
+        },
+    ));
+
+    let mut object_types = metadata //BTreeMap::from_iter(metadata.tables.0.iter().map(|(table_name, table)| {
         .tables
         .0
         .iter()
         .map(|(table_name, table)| {
-            let object_type = models::ObjectType {
+            let mut object_type = models::ObjectType {
                 description: table.description.clone(),
                 fields: table
                     .columns
@@ -92,19 +181,428 @@ pub fn get_schema(
                     })
                     .collect(),
             };
+            object_type
+                .fields
+                .extend(key_template_fields(table, |_| true));
+            if pagination::has_cursor_field(table) {
+                object_type.fields.insert(
+                    pagination::CURSOR_FIELD.into(),
+                    models::ObjectField {
+                        description: Some(
+                            "A cursor to pass as the 'after' argument to continue after this row"
+                                .to_string(),
+                        ),
+                        r#type: models::Type::Named {
+                            name: "String".into(),
+                        },
+                        arguments: BTreeMap::new(),
+                    },
+                );
+            }
             (table_name.as_str().into(), object_type)
         })
         .collect::<BTreeMap<_, _>>();
 
+    object_types.extend(metadata.native_queries.0.iter().map(
+        |(native_query_name, native_query)| {
+            let object_type = models::ObjectType {
+                description: native_query.description.clone(),
+                fields: native_query
+                    .columns
+                    .iter()
+                    .map(|(column_name, column_info)| {
+                        (
+                            column_name.clone(),
+                            models::ObjectField {
+                                description: column_info.description.clone(),
+                                r#type: column_to_type(column_info),
+                                arguments: BTreeMap::new(),
+                            },
+                        )
+                    })
+                    .collect(),
+            };
+            (native_query_name.as_str().into(), object_type)
+        },
+    ));
+
+    // Map attributes, whose fields may be missing from any item.
+    object_types.extend(metadata.composite_types.0.iter().map(
+        |(composite_type_name, composite_type)| {
+            let object_type = models::ObjectType {
+                description: composite_type.description.clone(),
+                fields: composite_type
+                    .fields
+                    .iter()
+                    .map(|(field_name, field_info)| {
+                        (
+                            field_name.clone(),
+                            models::ObjectField {
+                                description: field_info.description.clone(),
+                                r#type: models::Type::Nullable {
+                                    underlying_type: Box::new(type_to_type(&field_info.r#type)),
+                                },
+                                arguments: BTreeMap::new(),
+                            },
+                        )
+                    })
+                    .collect(),
+            };
+            (composite_type_name.clone(), object_type)
+        },
+    ));
+
+    let mutations = generate::generate(&metadata.tables);
+
+    // native mutations are named apart from the generated procedures
+    let mut procedures: Vec<models::ProcedureInfo> = mutations
+        .iter()
+        .map(|(procedure_name, mutation)| mutation_to_procedure(procedure_name, mutation))
+        .collect();
+
+    for (procedure_name, native_mutation) in &metadata.native_mutations.0 {
+        procedures.push(models::ProcedureInfo {
+            name: procedure_name.clone(),
+            description: native_mutation.description.clone(),
+            arguments: native_mutation
+                .arguments
+                .iter()
+                .map(|(argument_name, column_info)| {
+                    (
+                        argument_name.clone(),
+                        models::ArgumentInfo {
+                            description: column_info.description.clone(),
+                            argument_type: column_to_type(column_info),
+                        },
+                    )
+                })
+                .collect(),
+            // the item of the RETURNING clause, if there is one
+            result_type: models::Type::Nullable {
+                underlying_type: Box::new(models::Type::Named {
+                    name: procedure_name.as_str().into(),
+                }),
+            },
+        });
+        object_types.insert(
+            procedure_name.as_str().into(),
+            models::ObjectType {
+                description: Some(format!(
+                    "The item returned by the '{procedure_name}' native mutation"
+                )),
+                fields: native_mutation
+                    .columns
+                    .iter()
+                    .map(|(column_name, column_info)| {
+                        (
+                            column_name.clone(),
+                            models::ObjectField {
+                                description: column_info.description.clone(),
+                                r#type: column_to_type(column_info),
+                                arguments: BTreeMap::new(),
+                            },
+                        )
+                    })
+                    .collect(),
+            },
+        );
+    }
+
+    for (collection_name, table) in metadata
+        .tables
+        .0
+        .iter()
+        .filter(|(_, table)| table.entity.is_none())
+    {
+        object_types.insert(
+            generate::key_object_type_name(collection_name),
+            models::ObjectType {
+                description: Some(format!("The primary key of the '{collection_name}' table")),
+                // key attributes with a template may be given by their virtual fields instead
+                fields: generate::key_columns(table)
+                    .into_iter()
+                    .map(|(field_name, column_info)| {
+                        let r#type = type_to_type(&column_info.r#type);
+                        (
+                            field_name,
+                            models::ObjectField {
+                                description: column_info.description.clone(),
+                                r#type: if table.key_templates.0.contains_key(&column_info.name) {
+                                    models::Type::Nullable {
+                                        underlying_type: Box::new(r#type),
+                                    }
+                                } else {
+                                    r#type
+                                },
+                                arguments: BTreeMap::new(),
+                            },
+                        )
+                    })
+                    .chain(key_template_fields(table, |attribute| {
+                        attribute == table.partition_key || attribute == table.sort_key
+                    }))
+                    .collect(),
+            },
+        );
+        object_types.insert(
+            generate::update_object_type_name(collection_name),
+            models::ObjectType {
+                description: Some(format!(
+                    "The attributes that can be set on an item of the '{collection_name}' table"
+                )),
+                fields: generate::updatable_columns(table)
+                    .map(|(field_name, column_info)| {
+                        (
+                            field_name.clone(),
+                            models::ObjectField {
+                                description: column_info.description.clone(),
+                                r#type: models::Type::Nullable {
+                                    underlying_type: Box::new(type_to_type(&column_info.r#type)),
+                                },
+                                arguments: BTreeMap::new(),
+                            },
+                        )
+                    })
+                    .collect(),
+            },
+        );
+    }
+
+    // Cursors are strings, whether or not some attribute is.
+    scalar_types
+        .entry("String".into())
+        .or_insert_with(|| models::ScalarType {
+            representation: Some(models::TypeRepresentation::String),
+            aggregate_functions: BTreeMap::new(),
+            comparison_operators: BTreeMap::new(),
+        });
+
+    if !mutations.is_empty() {
+        object_types.extend(batch_write_object_types());
+        // The result types refer to these scalars, which introspection only adds when some
+        // attribute uses them.
+        for (scalar_type_name, representation) in [
+            ("Number", models::TypeRepresentation::JSON),
+            ("Boolean", models::TypeRepresentation::Boolean),
+            ("String", models::TypeRepresentation::String),
+        ] {
+            scalar_types
+                .entry(scalar_type_name.into())
+                .or_insert_with(|| models::ScalarType {
+                    representation: Some(representation),
+                    aggregate_functions: BTreeMap::new(),
+                    comparison_operators: BTreeMap::new(),
+                });
+        }
+    }
+
     Ok(models::SchemaResponse {
         collections,
-        procedures: vec![],
+        procedures,
         functions: vec![],
         object_types,
         scalar_types,
     })
 }
 
+/// The collection a foreign key refers to: the foreign table, or the index of the foreign table
+/// whose key the foreign columns are.
+fn foreign_collection(
+    tables: &metadata::TablesInfo,
+    foreign_relation: &metadata::ForeignRelation,
+) -> models::CollectionName {
+    tables
+        .0
+        .get(&foreign_relation.foreign_table)
+        .and_then(|foreign_table| {
+            let attributes = foreign_relation
+                .column_mapping
+                .values()
+                .filter_map(|column| foreign_table.columns.get(column))
+                .map(|column| column.name.as_str())
+                .collect();
+            foreign_table.collection_keyed_on(&foreign_relation.foreign_table, &attributes)
+        })
+        .unwrap_or_else(|| foreign_relation.foreign_table.clone())
+}
+
+/// The virtual fields of the key templates of the attributes of a table. Values that do not have
+/// the shape of their template are read as null.
+fn key_template_fields(
+    table: &metadata::TableInfo,
+    attributes: impl Fn(&str) -> bool,
+) -> BTreeMap<models::FieldName, models::ObjectField> {
+    table
+        .key_templates
+        .0
+        .iter()
+        .filter(|(attribute, _)| attributes(attribute))
+        .flat_map(|(_, key_template)| &key_template.fields)
+        .map(|(field_name, field)| {
+            (
+                field_name.clone(),
+                models::ObjectField {
+                    description: field.description.clone(),
+                    r#type: models::Type::Nullable {
+                        underlying_type: Box::new(models::Type::Named {
+                            name: field.r#type.as_str().into(),
+                        }),
+                    },
+                    arguments: BTreeMap::new(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Describe a generated procedure.
+fn mutation_to_procedure(
+    procedure_name: &models::ProcedureName,
+    mutation: &generate::Mutation,
+) -> models::ProcedureInfo {
+    let generate::TableMutation {
+        collection_name,
+        table,
+        description,
+    } = mutation.table_mutation();
+
+    let named = |name: &str| models::Type::Named { name: name.into() };
+    let argument = |description: &str, argument_type: models::Type| models::ArgumentInfo {
+        description: Some(description.to_string()),
+        argument_type,
+    };
+    let array_of = |element_type: models::Type| models::Type::Array {
+        element_type: Box::new(element_type),
+    };
+    let key_type = named(generate::key_object_type_name(collection_name).as_str());
+    let item_type = named(collection_name.as_str());
+
+    let mut arguments = BTreeMap::new();
+    let result_type = match mutation {
+        generate::Mutation::InsertMany(_) => {
+            arguments.insert(
+                generate::OBJECTS_ARGUMENT.into(),
+                argument("The items to insert", array_of(item_type)),
+            );
+            named(generate::BATCH_WRITE_RESULT_TYPE)
+        }
+        generate::Mutation::DeleteMany(_) => {
+            arguments.insert(
+                generate::KEYS_ARGUMENT.into(),
+                argument("The keys of the items to delete", array_of(key_type)),
+            );
+            named(generate::BATCH_WRITE_RESULT_TYPE)
+        }
+        generate::Mutation::UpdateByKey(_) => {
+            arguments.insert(
+                generate::KEY_ARGUMENT.into(),
+                argument("The key of the item to update", key_type),
+            );
+            arguments.insert(
+                generate::SET_ARGUMENT.into(),
+                argument(
+                    "The attributes to set",
+                    named(generate::update_object_type_name(collection_name).as_str()),
+                ),
+            );
+            models::Type::Nullable {
+                underlying_type: Box::new(item_type),
+            }
+        }
+        generate::Mutation::DeleteByKey(_) => {
+            arguments.insert(
+                generate::KEY_ARGUMENT.into(),
+                argument("The key of the item to delete", key_type),
+            );
+            models::Type::Nullable {
+                underlying_type: Box::new(item_type),
+            }
+        }
+    };
+
+    if matches!(
+        mutation,
+        generate::Mutation::UpdateByKey(_) | generate::Mutation::DeleteByKey(_)
+    ) && table.version_attribute.is_some()
+    {
+        arguments.insert(
+            generate::EXPECTED_VERSION_ARGUMENT.into(),
+            argument(
+                "The version the item is expected to have. The write fails with a conflict otherwise.",
+                named("Number"),
+            ),
+        );
+    }
+
+    models::ProcedureInfo {
+        name: procedure_name.clone(),
+        description: Some(description.clone()),
+        arguments,
+        result_type,
+    }
+}
+
+/// The object types describing the result of a batch write.
+fn batch_write_object_types() -> BTreeMap<models::ObjectTypeName, models::ObjectType> {
+    let named = |name: &str| models::Type::Named { name: name.into() };
+    let field = |description: &str, r#type: models::Type| models::ObjectField {
+        description: Some(description.to_string()),
+        r#type,
+        arguments: BTreeMap::new(),
+    };
+
+    BTreeMap::from([
+        (
+            generate::BATCH_WRITE_RESULT_TYPE.into(),
+            models::ObjectType {
+                description: Some("The result of a batch write".to_string()),
+                fields: BTreeMap::from([
+                    (
+                        "affected_rows".into(),
+                        field("The number of items written successfully", named("Number")),
+                    ),
+                    (
+                        "items".into(),
+                        field(
+                            "The outcome of each item, in the order they were given",
+                            models::Type::Array {
+                                element_type: Box::new(named(
+                                    generate::BATCH_WRITE_ITEM_RESULT_TYPE,
+                                )),
+                            },
+                        ),
+                    ),
+                ]),
+            },
+        ),
+        (
+            generate::BATCH_WRITE_ITEM_RESULT_TYPE.into(),
+            models::ObjectType {
+                description: Some("The outcome of a single item in a batch write".to_string()),
+                fields: BTreeMap::from([
+                    (
+                        "index".into(),
+                        field("The position of the item in the request", named("Number")),
+                    ),
+                    (
+                        "success".into(),
+                        field("Whether the item was written", named("Boolean")),
+                    ),
+                    (
+                        "error".into(),
+                        field(
+                            "Why the item was not written",
+                            models::Type::Nullable {
+                                underlying_type: Box::new(named("String")),
+                            },
+                        ),
+                    ),
+                ]),
+            },
+        ),
+    ])
+}
+
 /// Map our local type representation to ndc-spec type representation.
 #[allow(clippy::match_same_arms)] // merging arms would require changing the order, making this harder to understand
 fn map_type_representation(
@@ -174,5 +672,8 @@ pub fn type_to_type(typ: &metadata::Type) -> models::Type {
         metadata::Type::ScalarType(scalar_type) => models::Type::Named {
             name: scalar_type.as_str().into(),
         },
+        metadata::Type::CompositeType(composite_type) => models::Type::Named {
+            name: composite_type.as_str().into(),
+        },
     }
 }
@@ -31,6 +31,7 @@ pub async fn create_state(
     let access_key_id = configuration.access_key_id.clone();
     let secret_access_key = configuration.secret_access_key.clone();
     let region = configuration.region.clone();
+    let endpoint_url = configuration.endpoint_url.clone();
 
     let credentials = aws_sdk_dynamodb::config::Credentials::new(
         access_key_id,
@@ -43,6 +44,7 @@ pub async fn create_state(
     let config = Config::builder()
         .region(aws_config::Region::new(region))
         .credentials_provider(credentials)
+        .set_endpoint_url(endpoint_url)
         .behavior_version_latest()
         .build();
 
@@ -0,0 +1,296 @@
+//! Compute aggregates over the items read by a query.
+//!
+//! DynamoDB has no aggregate functions, so we fold every item into an accumulator per aggregate
+//! as the pages of results come in.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use aws_sdk_dynamodb::types::AttributeValue;
+
+use query_engine_sql::sql::execution_plan::{Aggregate, AggregateFunction};
+
+use crate::values;
+
+/// The running state of an aggregate.
+pub struct Accumulator {
+    aggregate: Aggregate,
+    state: State,
+}
+
+enum State {
+    Count(u64),
+    DistinctCount(HashSet<String>),
+    Ordered(Option<AttributeValue>),
+    Sum(Option<Sum>, u64),
+}
+
+/// A running sum, exact as long as every value is an integer and it does not overflow.
+#[derive(Clone, Copy)]
+enum Sum {
+    Integer(i128),
+    Float(f64),
+}
+
+impl Accumulator {
+    pub fn new(aggregate: Aggregate) -> Accumulator {
+        let state = match &aggregate {
+            Aggregate::StarCount
+            | Aggregate::ColumnCount {
+                distinct: false, ..
+            } => State::Count(0),
+            Aggregate::ColumnCount { distinct: true, .. } => State::DistinctCount(HashSet::new()),
+            Aggregate::SingleColumn { function, .. } => match function {
+                AggregateFunction::Min | AggregateFunction::Max => State::Ordered(None),
+                AggregateFunction::Sum | AggregateFunction::Avg => State::Sum(None, 0),
+            },
+        };
+        Accumulator { aggregate, state }
+    }
+
+    /// Fold an item into the aggregate.
+    pub fn add(&mut self, item: &HashMap<String, AttributeValue>) {
+        let value = match &self.aggregate {
+            Aggregate::StarCount => None,
+            Aggregate::ColumnCount { attribute, .. }
+            | Aggregate::SingleColumn { attribute, .. } => {
+                // missing attributes and nulls are not aggregated
+                match item.get(attribute) {
+                    None | Some(AttributeValue::Null(_)) => return,
+                    Some(value) => Some(value),
+                }
+            }
+        };
+
+        match (&mut self.state, value) {
+            (State::Count(count), _) => *count += 1,
+            (State::DistinctCount(seen), Some(value)) => {
+                seen.insert(values::from_attribute_value(value).to_string());
+            }
+            (State::Ordered(best), Some(value)) => {
+                let keep = match (&self.aggregate, best.as_ref()) {
+                    (_, None) => is_ordered(value),
+                    (
+                        Aggregate::SingleColumn {
+                            function: AggregateFunction::Min,
+                            ..
+                        },
+                        Some(best),
+                    ) => values::compare(value, best) == Some(Ordering::Less),
+                    (_, Some(best)) => values::compare(value, best) == Some(Ordering::Greater),
+                };
+                if keep {
+                    *best = Some(value.clone());
+                }
+            }
+            (State::Sum(sum, count), Some(AttributeValue::N(n))) => {
+                if let Some(next) = add_number(*sum, n) {
+                    *sum = Some(next);
+                    *count += 1;
+                }
+            }
+            (State::DistinctCount(_) | State::Ordered(_) | State::Sum(..), _) => {}
+        }
+    }
+
+    /// The value of the aggregate over every item added so far.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn finish(self) -> serde_json::Value {
+        match self.state {
+            State::Count(count) => serde_json::Value::from(count),
+            State::DistinctCount(seen) => serde_json::Value::from(seen.len()),
+            State::Ordered(best) => best
+                .as_ref()
+                .map_or(serde_json::Value::Null, values::from_attribute_value),
+            State::Sum(None, _) => serde_json::Value::Null,
+            State::Sum(Some(sum), count) => match &self.aggregate {
+                Aggregate::SingleColumn {
+                    function: AggregateFunction::Avg,
+                    ..
+                } => float_to_json(sum.as_f64() / count as f64),
+                _ => match sum {
+                    Sum::Integer(i) => i64::try_from(i).map_or_else(
+                        |_| serde_json::Value::String(i.to_string()),
+                        serde_json::Value::from,
+                    ),
+                    Sum::Float(f) => float_to_json(f),
+                },
+            },
+        }
+    }
+}
+
+impl Sum {
+    #[allow(clippy::cast_precision_loss)]
+    fn as_f64(self) -> f64 {
+        match self {
+            Sum::Integer(i) => i as f64,
+            Sum::Float(f) => f,
+        }
+    }
+}
+
+/// Add a DynamoDB number to a running sum. Returns `None` if it is not a valid number.
+#[allow(clippy::cast_precision_loss)]
+fn add_number(sum: Option<Sum>, n: &str) -> Option<Sum> {
+    let sum = sum.unwrap_or(Sum::Integer(0));
+    match (sum, n.parse::<i128>()) {
+        (Sum::Integer(total), Ok(i)) => Some(
+            total
+                .checked_add(i)
+                .map_or_else(|| Sum::Float(total as f64 + i as f64), Sum::Integer),
+        ),
+        _ => n.parse::<f64>().ok().map(|f| Sum::Float(sum.as_f64() + f)),
+    }
+}
+
+/// Only numbers and strings have an order.
+fn is_ordered(value: &AttributeValue) -> bool {
+    matches!(value, AttributeValue::N(_) | AttributeValue::S(_))
+}
+
+fn float_to_json(f: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(f).map_or(serde_json::Value::Null, serde_json::Value::Number)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use query_engine_sql::sql::execution_plan::{Aggregate, AggregateFunction};
+    use serde_json::json;
+
+    use super::Accumulator;
+
+    fn aggregate(aggregate: Aggregate, values: &[Option<AttributeValue>]) -> serde_json::Value {
+        let mut accumulator = Accumulator::new(aggregate);
+        for value in values {
+            let item: HashMap<String, AttributeValue> = value
+                .iter()
+                .map(|value| ("score".to_string(), value.clone()))
+                .collect();
+            accumulator.add(&item);
+        }
+        accumulator.finish()
+    }
+
+    fn single_column(function: AggregateFunction) -> Aggregate {
+        Aggregate::SingleColumn {
+            attribute: "score".to_string(),
+            function,
+        }
+    }
+
+    fn number(n: &str) -> Option<AttributeValue> {
+        Some(AttributeValue::N(n.to_string()))
+    }
+
+    fn string(s: &str) -> Option<AttributeValue> {
+        Some(AttributeValue::S(s.to_string()))
+    }
+
+    #[test]
+    fn test_min_and_max_compare_numbers_exactly() {
+        // both numbers are the same float
+        let values = [number("9007199254740993"), number("9007199254740992")];
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Min), &values),
+            json!(9_007_199_254_740_992_u64)
+        );
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Max), &values),
+            json!(9_007_199_254_740_993_u64)
+        );
+    }
+
+    #[test]
+    fn test_min_and_max_of_numbers_and_strings() {
+        let numbers = [number("-2.5"), number("10"), number("9.75")];
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Min), &numbers),
+            json!(-2.5)
+        );
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Max), &numbers),
+            json!(10)
+        );
+
+        let strings = [string("b"), string("a"), string("c")];
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Min), &strings),
+            json!("a")
+        );
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Max), &strings),
+            json!("c")
+        );
+    }
+
+    #[test]
+    fn test_nulls_and_missing_values_are_skipped() {
+        let values = [
+            None,
+            Some(AttributeValue::Null(true)),
+            number("3"),
+            number("1"),
+        ];
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Min), &values),
+            json!(1)
+        );
+        assert_eq!(
+            aggregate(
+                Aggregate::ColumnCount {
+                    attribute: "score".to_string(),
+                    distinct: false
+                },
+                &values
+            ),
+            json!(2)
+        );
+        assert_eq!(aggregate(Aggregate::StarCount, &values), json!(4));
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Max), &[None]),
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn test_sum_and_avg() {
+        let values = [number("1"), number("2"), number("4")];
+        assert_eq!(
+            aggregate(single_column(AggregateFunction::Sum), &values),
+            json!(7)
+        );
+        assert_eq!(
+            aggregate(
+                single_column(AggregateFunction::Avg),
+                &[number("1"), number("2")]
+            ),
+            json!(1.5)
+        );
+        assert_eq!(
+            aggregate(
+                single_column(AggregateFunction::Sum),
+                &[number("0.5"), number("1")]
+            ),
+            json!(1.5)
+        );
+    }
+
+    #[test]
+    fn test_distinct_count() {
+        let values = [number("1"), number("1"), string("1"), None];
+        assert_eq!(
+            aggregate(
+                Aggregate::ColumnCount {
+                    attribute: "score".to_string(),
+                    distinct: true
+                },
+                &values
+            ),
+            json!(2)
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Opaque continuation tokens, to resume a query where a previous page of rows ended.
+//!
+//! DynamoDB pages results with a `NextToken` per `ExecuteStatement` call, which does not line up
+//! with the rows we return. A continuation token pairs the `NextToken` of a page with the number
+//! of matched items to skip in it, so resuming only reads the items of that page again.
+
+use base64::Engine;
+
+use crate::error::{Error, QueryError};
+
+/// Where to resume reading the results of a statement.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContinuationToken {
+    /// The `NextToken` of the page to start reading from, or `None` for the first page.
+    pub next_token: Option<String>,
+    /// How many matched items to skip from the start of that page.
+    pub skip: usize,
+}
+
+impl ContinuationToken {
+    /// Encode the token as an opaque string.
+    pub fn encode(&self) -> String {
+        let token = serde_json::json!({
+            "nextToken": self.next_token,
+            "skip": self.skip,
+        });
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(token.to_string())
+    }
+
+    /// Decode a token given by a client.
+    pub fn decode(token: &str) -> Result<ContinuationToken, Error> {
+        let invalid = || {
+            Error::Query(QueryError::InvalidValue(format!(
+                "'{token}' is not a valid continuation token"
+            )))
+        };
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|_| invalid())?;
+        let value: serde_json::Value = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        let next_token = match value.get("nextToken") {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(next_token)) => Some(next_token.clone()),
+            Some(_) => return Err(invalid()),
+        };
+        let skip = value
+            .get("skip")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|skip| usize::try_from(skip).ok())
+            .ok_or_else(invalid)?;
+        Ok(ContinuationToken { next_token, skip })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContinuationToken;
+
+    #[test]
+    fn round_trips() {
+        let token = ContinuationToken {
+            next_token: Some("abc/def+==".to_string()),
+            skip: 42,
+        };
+        assert_eq!(ContinuationToken::decode(&token.encode()).unwrap(), token);
+        assert_eq!(
+            ContinuationToken::decode(&ContinuationToken::default().encode()).unwrap(),
+            ContinuationToken::default()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_tokens() {
+        assert!(ContinuationToken::decode("not a token").is_err());
+        assert!(ContinuationToken::decode("e30").is_err());
+    }
+}
@@ -5,6 +5,8 @@ pub enum Error {
     Query(QueryError),
     #[error("{0}")]
     DB(sqlx::Error),
+    #[error("{0}")]
+    DynamoDB(String),
 }
 
 /// Query planning error.
@@ -20,6 +22,24 @@ pub enum QueryError {
     DBConstraintError(sqlx::Error),
     #[error("Mutation constraint failed.")]
     MutationConstraintFailed,
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+    #[error("Version conflict on table '{table}': expected version {expected}, found {}.", .actual.as_ref().map_or_else(|| "no item".to_string(), ToString::to_string))]
+    VersionConflict {
+        table: String,
+        expected: serde_json::Value,
+        actual: Option<serde_json::Value>,
+    },
+    #[error("Query would scan the whole {}, which the scan policy denies. Filter on one of these key attributes with an equality or an IN condition: {}.", .index.as_ref().map_or_else(|| format!("table '{table}'"), |index| format!("index '{index}' of table '{table}'")), .suggested_keys.join(", "))]
+    FullScanDenied {
+        table: String,
+        index: Option<String>,
+        suggested_keys: Vec<String>,
+    },
+    #[error("The requested ordering is done in memory, which is limited to {limit} items, but the query matched more. Order by the sort key with an equality on the partition key, or narrow the filter.")]
+    TooManyItemsToSort { limit: usize },
+    #[error("An exists filter across a relationship is limited to {limit} join keys of the target collection, but its predicate matched more. Narrow the predicate, or raise queryOptions.maxExistsJoinKeys.")]
+    TooManyJoinKeys { limit: usize },
 }
 
 impl From<sqlx::Error> for Error {
@@ -1,6 +1,10 @@
 //! Query execution against a PostgreSQL database.
 //! See `/architecture.md#execution` in the repository for more details.
 
+mod aggregates;
+pub mod continuation;
 pub mod error;
 pub mod metrics;
+pub mod mutation;
 pub mod query;
+pub mod values;
@@ -9,8 +9,11 @@ use prometheus::{Gauge, Histogram, HistogramTimer, IntCounter, IntGauge, Registr
 pub struct Metrics {
     query_total: IntCounter,
     explain_total: IntCounter,
+    mutation_total: IntCounter,
     query_plan_time: Histogram,
     query_execution_time: Histogram,
+    mutation_plan_time: Histogram,
+    mutation_execution_time: Histogram,
     pool_max_connections: IntGauge,
     pool_min_connections: IntGauge,
     pool_acquire_timeout: Gauge,
@@ -34,6 +37,12 @@ impl Metrics {
             "Total successful explains.",
         )?;
 
+        let mutation_total = add_int_counter_metric(
+            metrics_registry,
+            "postgres_ndc_mutation_total",
+            "Total successful mutations.",
+        )?;
+
         let query_plan_time = add_histogram_metric(
             metrics_registry,
             "postgres_ndc_query_plan_time",
@@ -46,6 +55,18 @@ impl Metrics {
             "Time taken to execute an already-planned query, in seconds.",
         )?;
 
+        let mutation_plan_time = add_histogram_metric(
+            metrics_registry,
+            "postgres_ndc_mutation_plan_time",
+            "Time taken to plan a mutation for execution, in seconds.",
+        )?;
+
+        let mutation_execution_time = add_histogram_metric(
+            metrics_registry,
+            "postgres_ndc_mutation_execution_time",
+            "Time taken to execute an already-planned mutation, in seconds.",
+        )?;
+
         let pool_max_connections = add_int_gauge_metric(
             metrics_registry,
             "postgres_ndc_pool_max_connections",
@@ -81,8 +102,11 @@ impl Metrics {
         Ok(Self {
             query_total,
             explain_total,
+            mutation_total,
             query_plan_time,
             query_execution_time,
+            mutation_plan_time,
+            mutation_execution_time,
             pool_max_connections,
             pool_min_connections,
             pool_acquire_timeout,
@@ -100,6 +124,10 @@ impl Metrics {
         self.explain_total.inc();
     }
 
+    pub fn record_successful_mutation(&self) {
+        self.mutation_total.inc();
+    }
+
     pub fn time_query_plan(&self) -> Timer {
         Timer(self.query_plan_time.start_timer())
     }
@@ -108,6 +136,14 @@ impl Metrics {
         Timer(self.query_execution_time.start_timer())
     }
 
+    pub fn time_mutation_plan(&self) -> Timer {
+        Timer(self.mutation_plan_time.start_timer())
+    }
+
+    pub fn time_mutation_execution(&self) -> Timer {
+        Timer(self.mutation_execution_time.start_timer())
+    }
+
     // Set the metrics populated from the pool options.
     //
     // This only needs to be called once, as the options don't change.
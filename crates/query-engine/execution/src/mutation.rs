@@ -0,0 +1,873 @@
+//! Execute a mutation execution plan against the database.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use aws_sdk_dynamodb::error::DisplayErrorContext;
+use aws_sdk_dynamodb::operation::delete_item::DeleteItemError;
+use aws_sdk_dynamodb::operation::execute_statement::ExecuteStatementError;
+use aws_sdk_dynamodb::operation::put_item::PutItemError;
+use aws_sdk_dynamodb::operation::update_item::UpdateItemError;
+use aws_sdk_dynamodb::types::{
+    AttributeValue, DeleteRequest, PutRequest, ReturnValue, ReturnValuesOnConditionCheckFailure,
+    WriteRequest,
+};
+use aws_sdk_dynamodb::Client;
+use ndc_models as models;
+
+use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan::{TypedValue, VersionCheck, WriteExpression};
+
+use crate::error::{Error, QueryError};
+use crate::metrics;
+use crate::values;
+
+/// The maximum number of requests DynamoDB accepts in a single `BatchWriteItem` call.
+pub const BATCH_WRITE_CHUNK_SIZE: usize = 25;
+
+/// How many times we send unprocessed items again before giving up on them.
+const MAX_BATCH_WRITE_RETRIES: u32 = 5;
+
+/// The delay before the first retry of unprocessed items. It doubles after each attempt.
+const BATCH_WRITE_BASE_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Execute the mutations in order, and return the result of each of them.
+pub async fn execute(
+    client: &Client,
+    _metrics: &metrics::Metrics,
+    plan: sql::execution_plan::ExecutionPlan<Vec<sql::execution_plan::Mutation>>,
+) -> Result<models::MutationResponse, Error> {
+    let mut operation_results = Vec::with_capacity(plan.query.len());
+
+    for mutation in plan.query {
+        let result = match mutation.operation {
+            sql::execution_plan::MutationOperation::BatchWrite {
+                table_name,
+                requests,
+                ..
+            } => execute_batch_write(client, &table_name, requests).await?,
+            sql::execution_plan::MutationOperation::PutItems {
+                table_name,
+                items,
+                expression,
+                ..
+            } => execute_put_items(client, &table_name, &items, &expression).await?,
+            sql::execution_plan::MutationOperation::UpdateItem {
+                table_name,
+                key,
+                expression,
+                version,
+            } => execute_update_item(client, &table_name, &key, &expression, version).await?,
+            sql::execution_plan::MutationOperation::DeleteItem {
+                table_name,
+                key,
+                expression,
+                version,
+            } => execute_delete_item(client, &table_name, &key, &expression, version).await?,
+            sql::execution_plan::MutationOperation::ExecuteStatement {
+                statement,
+                parameters,
+            } => execute_statement(client, statement, &parameters).await?,
+        };
+        operation_results.push(models::MutationOperationResults::Procedure {
+            result: project(result, mutation.fields.as_ref()),
+        });
+    }
+
+    Ok(models::MutationResponse { operation_results })
+}
+
+/// The outcome of a single item in a batch write.
+struct ItemResult {
+    index: usize,
+    error: Option<String>,
+}
+
+/// Run the write requests against a table in chunks, retrying unprocessed items with
+/// exponential backoff, and report the outcome of every item.
+async fn execute_batch_write(
+    client: &Client,
+    table_name: &str,
+    requests: Vec<sql::execution_plan::WriteRequest>,
+) -> Result<serde_json::Value, Error> {
+    let requests = requests
+        .into_iter()
+        .map(|request| to_write_request(&request))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut results = Vec::with_capacity(requests.len());
+
+    for mut pending in numbered_chunks(&requests) {
+        let mut backoff = BATCH_WRITE_BASE_BACKOFF;
+        let mut attempt = 0;
+
+        while !pending.is_empty() {
+            let response = client
+                .batch_write_item()
+                .request_items(
+                    table_name,
+                    pending.iter().map(|(_, request)| request.clone()).collect(),
+                )
+                .send()
+                .await;
+
+            let unprocessed = match response {
+                Ok(output) => output
+                    .unprocessed_items
+                    .and_then(|mut unprocessed| unprocessed.remove(table_name))
+                    .unwrap_or_default(),
+                Err(err) => {
+                    let message = DisplayErrorContext(&err).to_string();
+                    tracing::error!("BatchWriteItem against '{table_name}' failed: {message}");
+                    results.extend(pending.drain(..).map(|(index, _)| ItemResult {
+                        index,
+                        error: Some(message.clone()),
+                    }));
+                    break;
+                }
+            };
+
+            let (still_pending, processed): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(_, request)| unprocessed.contains(request));
+            results.extend(
+                processed
+                    .into_iter()
+                    .map(|(index, _)| ItemResult { index, error: None }),
+            );
+            pending = still_pending;
+
+            if pending.is_empty() {
+                break;
+            }
+            if attempt == MAX_BATCH_WRITE_RETRIES {
+                results.extend(pending.drain(..).map(|(index, _)| ItemResult {
+                    index,
+                    error: Some(format!(
+                        "item was not processed after {MAX_BATCH_WRITE_RETRIES} retries"
+                    )),
+                }));
+                break;
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    Ok(batch_write_result(results))
+}
+
+/// Split requests into the chunks sent in a single `BatchWriteItem` call, numbering every request
+/// by its position in the whole batch.
+fn numbered_chunks<T: Clone>(requests: &[T]) -> impl Iterator<Item = Vec<(usize, T)>> + '_ {
+    requests
+        .chunks(BATCH_WRITE_CHUNK_SIZE)
+        .enumerate()
+        .map(|(chunk_index, chunk)| {
+            let offset = chunk_index * BATCH_WRITE_CHUNK_SIZE;
+            chunk
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(index, request)| (offset + index, request))
+                .collect()
+        })
+}
+
+/// Report the outcome of every item of a batch write, in the order the items were given.
+fn batch_write_result(mut results: Vec<ItemResult>) -> serde_json::Value {
+    results.sort_by_key(|result| result.index);
+    let affected_rows = results
+        .iter()
+        .filter(|result| result.error.is_none())
+        .count();
+
+    serde_json::json!({
+        "affected_rows": affected_rows,
+        "items": results
+            .into_iter()
+            .map(|result| serde_json::json!({
+                "index": result.index,
+                "success": result.error.is_none(),
+                "error": result.error,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Put the items one at a time, each only if its condition holds, and report the outcome of
+/// every item like a batch write does.
+async fn execute_put_items(
+    client: &Client,
+    table_name: &str,
+    items: &[BTreeMap<String, TypedValue>],
+    expression: &WriteExpression,
+) -> Result<serde_json::Value, Error> {
+    let mut results = Vec::with_capacity(items.len());
+
+    for (index, item) in items.iter().enumerate() {
+        let response = client
+            .put_item()
+            .table_name(table_name)
+            .set_item(Some(to_attribute_map(item)?))
+            .set_condition_expression(expression.condition_expression.clone())
+            .set_expression_attribute_names(expression_attribute_names(expression))
+            .set_expression_attribute_values(expression_attribute_values(expression)?)
+            .send()
+            .await;
+
+        let error = match response {
+            Ok(_) => None,
+            Err(err) => match err.into_service_error() {
+                PutItemError::ConditionalCheckFailedException(_) => {
+                    Some("an item with the same key already exists".to_string())
+                }
+                err => {
+                    let message = DisplayErrorContext(&err).to_string();
+                    tracing::error!("PutItem against '{table_name}' failed: {message}");
+                    Some(message)
+                }
+            },
+        };
+        results.push(ItemResult { index, error });
+    }
+
+    Ok(batch_write_result(results))
+}
+
+/// Update a single item and return it, or null if there is no such item.
+async fn execute_update_item(
+    client: &Client,
+    table_name: &str,
+    key: &BTreeMap<String, TypedValue>,
+    expression: &WriteExpression,
+    version: Option<VersionCheck>,
+) -> Result<serde_json::Value, Error> {
+    let response = client
+        .update_item()
+        .table_name(table_name)
+        .set_key(Some(to_attribute_map(key)?))
+        .set_update_expression(expression.update_expression.clone())
+        .set_condition_expression(expression.condition_expression.clone())
+        .set_expression_attribute_names(expression_attribute_names(expression))
+        .set_expression_attribute_values(expression_attribute_values(expression)?)
+        .return_values(ReturnValue::AllNew)
+        .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld)
+        .send()
+        .await;
+
+    match response {
+        Ok(output) => Ok(output
+            .attributes
+            .as_ref()
+            .map_or(serde_json::Value::Null, values::item_to_json)),
+        Err(err) => match err.into_service_error() {
+            UpdateItemError::ConditionalCheckFailedException(exception) => {
+                condition_check_failed(table_name, version, exception.item.as_ref())
+            }
+            err => Err(Error::DynamoDB(DisplayErrorContext(&err).to_string())),
+        },
+    }
+}
+
+/// Delete a single item and return it, or null if there was no such item.
+async fn execute_delete_item(
+    client: &Client,
+    table_name: &str,
+    key: &BTreeMap<String, TypedValue>,
+    expression: &WriteExpression,
+    version: Option<VersionCheck>,
+) -> Result<serde_json::Value, Error> {
+    let response = client
+        .delete_item()
+        .table_name(table_name)
+        .set_key(Some(to_attribute_map(key)?))
+        .set_condition_expression(expression.condition_expression.clone())
+        .set_expression_attribute_names(expression_attribute_names(expression))
+        .set_expression_attribute_values(expression_attribute_values(expression)?)
+        .return_values(ReturnValue::AllOld)
+        .return_values_on_condition_check_failure(ReturnValuesOnConditionCheckFailure::AllOld)
+        .send()
+        .await;
+
+    match response {
+        Ok(output) => Ok(output
+            .attributes
+            .as_ref()
+            .map_or(serde_json::Value::Null, values::item_to_json)),
+        Err(err) => match err.into_service_error() {
+            DeleteItemError::ConditionalCheckFailedException(exception) => {
+                condition_check_failed(table_name, version, exception.item.as_ref())
+            }
+            err => Err(Error::DynamoDB(DisplayErrorContext(&err).to_string())),
+        },
+    }
+}
+
+/// Run a native mutation and return the item of its `RETURNING` clause, or null if it returns
+/// none. A condition that does not hold fails the mutation.
+async fn execute_statement(
+    client: &Client,
+    statement: String,
+    parameters: &[TypedValue],
+) -> Result<serde_json::Value, Error> {
+    let parameters = parameters
+        .iter()
+        .map(values::to_attribute_value)
+        .collect::<Result<Vec<_>, Error>>()?;
+    let response = client
+        .execute_statement()
+        .statement(statement)
+        .set_parameters((!parameters.is_empty()).then_some(parameters))
+        .send()
+        .await;
+
+    match response {
+        Ok(output) => Ok(output
+            .items
+            .as_deref()
+            .and_then(<[_]>::first)
+            .map_or(serde_json::Value::Null, values::item_to_json)),
+        Err(err) => Err(statement_error(err.into_service_error())),
+    }
+}
+
+/// A statement whose condition does not hold, or which inserts an item whose key is taken, did
+/// not write anything: the mutation fails with a constraint error rather than returning null.
+fn statement_error(err: ExecuteStatementError) -> Error {
+    match err {
+        ExecuteStatementError::ConditionalCheckFailedException(_)
+        | ExecuteStatementError::DuplicateItemException(_) => {
+            Error::Query(QueryError::MutationConstraintFailed)
+        }
+        err => Error::DynamoDB(DisplayErrorContext(&err).to_string()),
+    }
+}
+
+/// A failed condition is a version conflict when the item is versioned.
+/// Otherwise the item we meant to update does not exist, and there is nothing to return.
+fn condition_check_failed(
+    table_name: &str,
+    version: Option<VersionCheck>,
+    stored_item: Option<&HashMap<String, AttributeValue>>,
+) -> Result<serde_json::Value, Error> {
+    match version {
+        None => Ok(serde_json::Value::Null),
+        Some(VersionCheck {
+            attribute,
+            expected,
+        }) => Err(Error::Query(QueryError::VersionConflict {
+            table: table_name.to_string(),
+            expected,
+            actual: stored_item
+                .and_then(|item| item.get(&attribute))
+                .map(values::from_attribute_value),
+        })),
+    }
+}
+
+/// The `#name` placeholders of an expression, if there are any.
+fn expression_attribute_names(expression: &WriteExpression) -> Option<HashMap<String, String>> {
+    if expression.attribute_names.is_empty() {
+        None
+    } else {
+        Some(
+            expression
+                .attribute_names
+                .iter()
+                .map(|(placeholder, name)| (placeholder.clone(), name.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// The `:value` placeholders of an expression, if there are any.
+fn expression_attribute_values(
+    expression: &WriteExpression,
+) -> Result<Option<HashMap<String, AttributeValue>>, Error> {
+    if expression.attribute_values.is_empty() {
+        Ok(None)
+    } else {
+        to_attribute_map(&expression.attribute_values).map(Some)
+    }
+}
+
+/// Build the SDK representation of a write request.
+fn to_write_request(request: &sql::execution_plan::WriteRequest) -> Result<WriteRequest, Error> {
+    let write_request = match request {
+        sql::execution_plan::WriteRequest::Put { item } => WriteRequest::builder()
+            .put_request(
+                PutRequest::builder()
+                    .set_item(Some(to_attribute_map(item)?))
+                    .build()
+                    .map_err(|err| Error::Query(QueryError::InvalidValue(err.to_string())))?,
+            )
+            .build(),
+        sql::execution_plan::WriteRequest::Delete { key } => WriteRequest::builder()
+            .delete_request(
+                DeleteRequest::builder()
+                    .set_key(Some(to_attribute_map(key)?))
+                    .build()
+                    .map_err(|err| Error::Query(QueryError::InvalidValue(err.to_string())))?,
+            )
+            .build(),
+    };
+    Ok(write_request)
+}
+
+/// Convert typed values to a map of attribute values.
+pub fn to_attribute_map<'a>(
+    attributes: impl IntoIterator<Item = (&'a String, &'a TypedValue)>,
+) -> Result<HashMap<String, AttributeValue>, Error> {
+    attributes
+        .into_iter()
+        .map(|(name, value)| Ok((name.clone(), values::to_attribute_value(value)?)))
+        .collect()
+}
+
+/// Keep only the requested fields of a procedure result.
+fn project(value: serde_json::Value, fields: Option<&models::NestedField>) -> serde_json::Value {
+    match (value, fields) {
+        (value, None) => value,
+        (serde_json::Value::Object(object), Some(models::NestedField::Object(nested))) => {
+            serde_json::Value::Object(
+                nested
+                    .fields
+                    .iter()
+                    .map(|(alias, field)| {
+                        let value = match field {
+                            models::Field::Column { column, fields, .. } => project(
+                                object.get(column.as_str()).cloned().unwrap_or_default(),
+                                fields.as_ref(),
+                            ),
+                            models::Field::Relationship { .. } => serde_json::Value::Null,
+                        };
+                        (alias.to_string(), value)
+                    })
+                    .collect(),
+            )
+        }
+        (serde_json::Value::Array(values), Some(models::NestedField::Array(nested))) => values
+            .into_iter()
+            .map(|value| project(value, Some(nested.fields.as_ref())))
+            .collect(),
+        (value, Some(_)) => value,
+    }
+}
+
+/// Describe the writes of a mutation execution plan without running them.
+///
+/// Parameter and key values are redacted unless `show_parameter_values` is set. Details are
+/// keyed by the zero-padded position of the mutation, so they sort in the order they run.
+pub fn explain(
+    plan: &sql::execution_plan::ExecutionPlan<Vec<sql::execution_plan::Mutation>>,
+    show_parameter_values: bool,
+) -> BTreeMap<String, String> {
+    let mut details = BTreeMap::new();
+    let width = plan.query.len().saturating_sub(1).to_string().len();
+
+    for (index, mutation) in plan.query.iter().enumerate() {
+        let mut detail = |field: &str, value: String| {
+            details.insert(
+                format!("{index:0width$} {} {field}", mutation.procedure_name),
+                value,
+            );
+        };
+
+        match &mutation.operation {
+            sql::execution_plan::MutationOperation::BatchWrite {
+                table_name,
+                key_attributes,
+                requests,
+            } => {
+                detail(
+                    "Operation",
+                    format!(
+                        "BatchWriteItem on '{table_name}': {} request(s) in {} call(s) of up to {BATCH_WRITE_CHUNK_SIZE}",
+                        requests.len(),
+                        requests.len().div_ceil(BATCH_WRITE_CHUNK_SIZE)
+                    ),
+                );
+                detail(
+                    "Parameters",
+                    requests
+                        .iter()
+                        .enumerate()
+                        .map(|(item_index, request)| match request {
+                            sql::execution_plan::WriteRequest::Put { item } => format!(
+                                "[{item_index}] PutRequest {}",
+                                explain_values(item, show_parameter_values)
+                            ),
+                            sql::execution_plan::WriteRequest::Delete { key } => format!(
+                                "[{item_index}] DeleteRequest {}",
+                                explain_values(key, show_parameter_values)
+                            ),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+                detail(
+                    "Affected Keys",
+                    requests
+                        .iter()
+                        .map(|request| {
+                            let (sql::execution_plan::WriteRequest::Put { item: attributes }
+                            | sql::execution_plan::WriteRequest::Delete { key: attributes }) =
+                                request;
+                            explain_key(
+                                key_attributes
+                                    .iter()
+                                    .filter_map(|name| attributes.get_key_value(name)),
+                                show_parameter_values,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+            sql::execution_plan::MutationOperation::PutItems {
+                table_name,
+                key_attributes,
+                items,
+                expression,
+            } => {
+                detail(
+                    "Operation",
+                    format!(
+                        "PutItem on '{table_name}': {} call(s), one per item",
+                        items.len()
+                    ),
+                );
+                explain_expression(&mut detail, expression, show_parameter_values);
+                detail(
+                    "Parameters",
+                    items
+                        .iter()
+                        .enumerate()
+                        .map(|(item_index, item)| {
+                            format!(
+                                "[{item_index}] PutItem {}",
+                                explain_values(item, show_parameter_values)
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+                detail(
+                    "Affected Keys",
+                    items
+                        .iter()
+                        .map(|item| {
+                            explain_key(
+                                key_attributes
+                                    .iter()
+                                    .filter_map(|name| item.get_key_value(name)),
+                                show_parameter_values,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+            sql::execution_plan::MutationOperation::UpdateItem {
+                table_name,
+                key,
+                expression,
+                ..
+            } => {
+                detail("Operation", format!("UpdateItem on '{table_name}'"));
+                explain_expression(&mut detail, expression, show_parameter_values);
+                detail("Affected Keys", explain_key(key, show_parameter_values));
+            }
+            sql::execution_plan::MutationOperation::DeleteItem {
+                table_name,
+                key,
+                expression,
+                ..
+            } => {
+                detail("Operation", format!("DeleteItem on '{table_name}'"));
+                explain_expression(&mut detail, expression, show_parameter_values);
+                detail("Affected Keys", explain_key(key, show_parameter_values));
+            }
+            sql::execution_plan::MutationOperation::ExecuteStatement {
+                statement,
+                parameters,
+            } => {
+                detail("Operation", "ExecuteStatement".to_string());
+                detail("PartiQL", statement.clone());
+                if !parameters.is_empty() {
+                    detail(
+                        "Parameters",
+                        parameters
+                            .iter()
+                            .enumerate()
+                            .map(|(index, typed_value)| {
+                                let attribute_type = values::attribute_type_name(typed_value);
+                                if show_parameter_values {
+                                    format!(
+                                        "?{}: {attribute_type} = {}",
+                                        index + 1,
+                                        typed_value.value
+                                    )
+                                } else {
+                                    format!("?{}: {attribute_type} = <redacted>", index + 1)
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    );
+                }
+            }
+        }
+    }
+
+    details
+}
+
+/// Describe the expressions of a single item write.
+fn explain_expression(
+    detail: &mut impl FnMut(&str, String),
+    expression: &WriteExpression,
+    show_parameter_values: bool,
+) {
+    if let Some(update_expression) = &expression.update_expression {
+        detail("Update Expression", update_expression.clone());
+    }
+    if let Some(condition_expression) = &expression.condition_expression {
+        detail("Condition Expression", condition_expression.clone());
+    }
+    if !expression.attribute_names.is_empty() {
+        detail(
+            "Expression Attribute Names",
+            expression
+                .attribute_names
+                .iter()
+                .map(|(placeholder, name)| format!("{placeholder} = {name}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+    }
+    if !expression.attribute_values.is_empty() {
+        detail(
+            "Parameters",
+            explain_values(&expression.attribute_values, show_parameter_values),
+        );
+    }
+}
+
+/// Describe a set of attributes by type, and by value when asked to.
+fn explain_values(
+    typed_values: &BTreeMap<String, TypedValue>,
+    show_parameter_values: bool,
+) -> String {
+    let attributes = typed_values
+        .iter()
+        .map(|(name, typed_value)| {
+            let attribute_type = values::attribute_type_name(typed_value);
+            if show_parameter_values {
+                format!("{name}: {attribute_type} = {}", typed_value.value)
+            } else {
+                format!("{name}: {attribute_type} = <redacted>")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{{{attributes}}}")
+}
+
+/// Describe the key of an item, with its values when asked to.
+fn explain_key<'a>(
+    key: impl IntoIterator<Item = (&'a String, &'a TypedValue)>,
+    show_parameter_values: bool,
+) -> String {
+    let attributes = key
+        .into_iter()
+        .map(|(name, typed_value)| {
+            if show_parameter_values {
+                format!("{name} = {}", typed_value.value)
+            } else {
+                format!("{name} = <redacted>")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{{{attributes}}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numbered_chunks_fit_a_batch_write_call() {
+        let requests = (0..60).collect::<Vec<_>>();
+        let chunks = numbered_chunks(&requests).collect::<Vec<_>>();
+
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![25, 25, 10]
+        );
+        for chunk in &chunks {
+            for (index, request) in chunk {
+                assert_eq!(index, request, "requests are numbered across chunks");
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_write_result_reports_items_in_order() {
+        let result = batch_write_result(vec![
+            ItemResult {
+                index: 2,
+                error: None,
+            },
+            ItemResult {
+                index: 0,
+                error: Some("throttled".to_string()),
+            },
+            ItemResult {
+                index: 1,
+                error: None,
+            },
+        ]);
+
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "affected_rows": 2,
+                "items": [
+                    { "index": 0, "success": false, "error": "throttled" },
+                    { "index": 1, "success": true, "error": null },
+                    { "index": 2, "success": true, "error": null },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_failed_version_check_is_a_conflict() {
+        let version = VersionCheck {
+            attribute: "version".to_string(),
+            expected: serde_json::json!(3),
+        };
+        let stored_item = HashMap::from([
+            ("id".to_string(), AttributeValue::S("a".to_string())),
+            ("version".to_string(), AttributeValue::N("4".to_string())),
+        ]);
+
+        let result = condition_check_failed("users", Some(version), Some(&stored_item));
+        assert!(
+            matches!(
+                result,
+                Err(Error::Query(QueryError::VersionConflict { ref table, ref expected, ref actual }))
+                    if table == "users"
+                        && expected == &serde_json::json!(3)
+                        && actual == &Some(serde_json::json!(4))
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_failed_existence_check_returns_null() {
+        let result = condition_check_failed("users", None, None);
+        assert!(matches!(result, Ok(serde_json::Value::Null)), "{result:?}");
+    }
+
+    #[test]
+    fn test_failed_statement_condition_is_a_constraint_error() {
+        use aws_sdk_dynamodb::types::error::{
+            ConditionalCheckFailedException, DuplicateItemException, ResourceNotFoundException,
+        };
+
+        let condition_failed =
+            statement_error(ExecuteStatementError::ConditionalCheckFailedException(
+                ConditionalCheckFailedException::builder()
+                    .message("The conditional request failed")
+                    .build(),
+            ));
+        assert!(
+            matches!(
+                condition_failed,
+                Error::Query(QueryError::MutationConstraintFailed)
+            ),
+            "{condition_failed:?}"
+        );
+
+        let duplicate_item = statement_error(ExecuteStatementError::DuplicateItemException(
+            DuplicateItemException::builder()
+                .message("Duplicate primary key exists in table")
+                .build(),
+        ));
+        assert!(
+            matches!(
+                duplicate_item,
+                Error::Query(QueryError::MutationConstraintFailed)
+            ),
+            "{duplicate_item:?}"
+        );
+
+        let not_found = statement_error(ExecuteStatementError::ResourceNotFoundException(
+            ResourceNotFoundException::builder()
+                .message("Requested resource not found")
+                .build(),
+        ));
+        assert!(matches!(not_found, Error::DynamoDB(_)), "{not_found:?}");
+    }
+
+    fn delete_item_plan(
+        count: usize,
+    ) -> sql::execution_plan::ExecutionPlan<Vec<sql::execution_plan::Mutation>> {
+        sql::execution_plan::simple_mutations_execution_plan(
+            (0..count)
+                .map(|index| sql::execution_plan::Mutation {
+                    procedure_name: "delete_users_by_key".into(),
+                    operation: sql::execution_plan::MutationOperation::DeleteItem {
+                        table_name: "users".to_string(),
+                        key: BTreeMap::from([(
+                            "id".to_string(),
+                            TypedValue {
+                                value: serde_json::json!(format!("user-{index}")),
+                                attribute_type: sql::execution_plan::AttributeType::String,
+                            },
+                        )]),
+                        expression: WriteExpression::default(),
+                        version: None,
+                    },
+                    fields: None,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_explain_lists_mutations_in_order() {
+        let details = explain(&delete_item_plan(11), true);
+        let operations = details
+            .iter()
+            .filter(|(key, _)| key.ends_with("Affected Keys"))
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(operations.len(), 11);
+        assert_eq!(
+            operations[1],
+            ("01 delete_users_by_key Affected Keys", "{id = \"user-1\"}")
+        );
+        assert_eq!(
+            operations[10],
+            ("10 delete_users_by_key Affected Keys", "{id = \"user-10\"}")
+        );
+    }
+
+    #[test]
+    fn test_explain_redacts_key_values() {
+        let details = explain(&delete_item_plan(1), false);
+        assert_eq!(
+            details["0 delete_users_by_key Affected Keys"],
+            "{id = <redacted>}"
+        );
+    }
+}
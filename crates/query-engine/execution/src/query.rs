@@ -1,129 +1,2080 @@
 //! Execute an execution plan against the database.
 
-use std::{collections::HashMap, vec};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
 
-use crate::error::Error;
+use crate::aggregates;
+use crate::continuation::ContinuationToken;
+use crate::error::{Error, QueryError};
 use crate::metrics;
+use crate::values;
+use aws_sdk_dynamodb::error::DisplayErrorContext;
+use aws_sdk_dynamodb::types::AttributeValue;
 use aws_sdk_dynamodb::Client;
-use base64;
 use bytes::{BufMut, Bytes, BytesMut};
+use ndc_models as models;
 use serde_json::{self, to_string, Value};
 
 use query_engine_sql::sql;
 
 /// Execute a query against dynamodb.
-#[allow(clippy::cast_possible_wrap)]
 pub async fn execute(
     client: &Client,
     _metrics: &metrics::Metrics,
     plan: sql::execution_plan::ExecutionPlan<sql::execution_plan::Query>,
+    max_exists_join_keys: usize,
+    max_items_sorted_in_memory: usize,
 ) -> Result<Bytes, Error> {
+    check_scan_policies(&plan.query)?;
+    for semi_join in &plan.query.semi_joins {
+        check_scan_policy(&semi_join.access_pattern)?;
+    }
+
     let mut buffer = BytesMut::new();
+    let context = |variables| Context {
+        client,
+        variables,
+        semi_joins: &plan.query.semi_joins,
+        max_exists_join_keys,
+        max_items_sorted_in_memory,
+    };
 
     // run the query on each set of variables. The result is a vector of rows each
     // element in the vector is the result of running the query on one set of variables.
-    match plan.query.variables {
-        None => {
-            let query_request = plan.query.query_sql().sql;
-            let query_limit: Option<i32> = plan.query.limit.map(|limit| limit as i32);
-
-            // Query
-            let rs = client
-                .execute_statement()
-                .statement(query_request.to_string())
-                .set_parameters(None)
-                .set_limit(query_limit)
-                .send()
-                .await
-                .unwrap();
-
-            let mut res_map: Vec<HashMap<String, String>> = vec![];
-
-            for item in &rs.items.unwrap() {
-                let mut hashmap = HashMap::new();
-                for (key, attribute_value) in item.clone() {
-                    if attribute_value.is_s() {
-                        let s = attribute_value.as_s().unwrap().to_string();
-                        println!("String: {s}");
-                        hashmap.insert(key, s);
-                    } else if attribute_value.is_n() {
-                        let n = attribute_value.as_n().unwrap().to_string();
-                        println!("Number: {n}");
-                        hashmap.insert(key, n);
-                    } else if attribute_value.is_bool() {
-                        let bool = attribute_value.as_bool().unwrap();
-                        let bool_str = bool.to_string();
-                        hashmap.insert(key, bool_str);
-                    } else if attribute_value.is_null() {
-                        hashmap.insert(key, "null".to_string());
-                    } else if attribute_value.is_b() {
-                        let b = attribute_value.as_b().unwrap();
-                        let b_str = base64::Engine::encode(
-                            &base64::engine::general_purpose::STANDARD,
-                            b.as_ref(),
-                        );
-                        hashmap.insert(key, b_str);
-                    } else if attribute_value.is_ss() {
-                        let ss = attribute_value.as_ss().unwrap();
-                        let ss_str = ss.join(", ");
-                        hashmap.insert(key, ss_str);
-                    } else if attribute_value.is_ns() {
-                        let ns = attribute_value.as_ns().unwrap();
-                        let ns_str = ns.join(", ");
-                        hashmap.insert(key, ns_str);
-                    } else if attribute_value.is_bs() {
-                        let bs = attribute_value.as_bs().unwrap();
-                        let bs_str = bs
+    let rowsets = match &plan.query.variables {
+        None => vec![execute_query(context(None), &plan.query).await?],
+        Some(variable_sets) => {
+            let mut rowsets = Vec::with_capacity(variable_sets.len());
+            for variables in variable_sets {
+                rowsets.push(execute_query(context(Some(variables)), &plan.query).await?);
+            }
+            rowsets
+        }
+    };
+
+    let final_row = to_string(&Value::Array(rowsets)).unwrap();
+
+    let b: Bytes = Bytes::from(final_row);
+    buffer.put(b);
+
+    Ok(buffer.freeze())
+}
+
+/// Enforce the scan policies of a query and of the queries of its relationships, before any
+/// statement is sent to DynamoDB.
+fn check_scan_policies(query: &sql::execution_plan::Query) -> Result<(), Error> {
+    check_scan_policy(&query.access_pattern)?;
+    for (_, field) in query.rowset.fields.iter().flatten() {
+        if let sql::execution_plan::Field::Relationship(relationship) = field {
+            check_scan_policies(&relationship.query)?;
+        }
+    }
+    Ok(())
+}
+
+/// Enforce the scan policy of a statement DynamoDB runs as a full Scan.
+fn check_scan_policy(access_pattern: &sql::execution_plan::AccessPattern) -> Result<(), Error> {
+    let sql::execution_plan::AccessPattern::Scan {
+        table,
+        index,
+        suggested_keys,
+        policy,
+    } = access_pattern
+    else {
+        return Ok(());
+    };
+    match policy {
+        sql::execution_plan::ScanPolicy::Allow => Ok(()),
+        sql::execution_plan::ScanPolicy::Warn => {
+            tracing::warn!(
+                table,
+                index = index.as_deref(),
+                suggested_keys = suggested_keys.join(", "),
+                "query runs as a full scan"
+            );
+            Ok(())
+        }
+        sql::execution_plan::ScanPolicy::Deny => Err(Error::Query(QueryError::FullScanDenied {
+            table: table.clone(),
+            index: index.clone(),
+            suggested_keys: suggested_keys.clone(),
+        })),
+    }
+}
+
+/// Run a query with one set of variables, reading every page of results up to the limit, and
+/// build its rowset.
+/// Aggregates are computed as the pages come in, so items are only kept when rows are requested
+/// or the executor has to sort them.
+async fn execute_query(
+    context: Context<'_>,
+    query: &sql::execution_plan::Query,
+) -> Result<Value, Error> {
+    let mut rowset = RowsetBuilder::new(&query.rowset);
+    // DynamoDB rejects a limit of 0, and no item would be returned anyway
+    if query.limit == Some(0) {
+        return Ok(rowset.finish());
+    }
+    let (statement, parameters) = prepare_statement(context, &query.query, vec![]).await?;
+    let limit = query.limit.map(|limit| limit as usize);
+    let start = resume_point(query.continuation_token.as_deref(), query.offset)?;
+    let skip = start.skip;
+
+    if query.order_by.is_empty() {
+        let mut pages = Pages::new(
+            context.client,
+            statement,
+            parameters,
+            &query.document_paths,
+            query.limit,
+            start.next_token,
+        );
+        let mut items_matched: usize = 0;
+        let mut items_returned: usize = 0;
+        while let Some(page) = pages.next().await? {
+            for (index, item) in page.items.iter().enumerate() {
+                if limit.is_some_and(|limit| items_returned >= limit) {
+                    break;
+                }
+                items_matched += 1;
+                if items_matched > skip {
+                    items_returned += 1;
+                    rowset.add(item, || page.cursor_after(index));
+                }
+            }
+            if limit.is_some_and(|limit| items_returned >= limit) {
+                break;
+            }
+        }
+    } else {
+        if start.next_token.is_some() {
+            return Err(Error::Query(QueryError::InvalidValue(
+                "the continuation token was not issued for a query with this ordering".to_string(),
+            )));
+        }
+        // Every matching item has to be read before the first one is known.
+        let mut pages = Pages::new(
+            context.client,
+            statement,
+            parameters,
+            &query.document_paths,
+            None,
+            None,
+        );
+        let mut items = vec![];
+        while let Some(page) = pages.next().await? {
+            items.extend(page.items);
+            if items.len() > context.max_items_sorted_in_memory {
+                return Err(Error::Query(QueryError::TooManyItemsToSort {
+                    limit: context.max_items_sorted_in_memory,
+                }));
+            }
+        }
+        sort_items(&mut items, &query.order_by);
+        add_sorted_items(&mut rowset, &items, skip, limit);
+    }
+
+    add_relationships(context, std::slice::from_mut(&mut rowset)).await?;
+    Ok(rowset.finish())
+}
+
+/// Where a query starts reading: from its continuation token, or from its first page after
+/// skipping its offset. The cursors of rows count the items skipped by the offset already, so the
+/// offset only applies to the first page and a query resumed with the same offset continues right
+/// after the row of its cursor.
+fn resume_point(
+    continuation_token: Option<&str>,
+    offset: Option<u32>,
+) -> Result<ContinuationToken, Error> {
+    match continuation_token {
+        Some(token) => ContinuationToken::decode(token),
+        None => Ok(ContinuationToken {
+            next_token: None,
+            skip: offset.map_or(0, |offset| offset as usize),
+        }),
+    }
+}
+
+/// Add the items of a page of sorted items to a rowset. Sorted items are read again from the
+/// start to resume, so their cursors only count the items to skip.
+fn add_sorted_items(
+    rowset: &mut RowsetBuilder<'_>,
+    items: &[HashMap<String, AttributeValue>],
+    skip: usize,
+    limit: Option<usize>,
+) {
+    for (position, item) in items
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .take(limit.unwrap_or(usize::MAX))
+    {
+        rowset.add(item, || ContinuationToken {
+            next_token: None,
+            skip: position + 1,
+        });
+    }
+}
+
+/// The most join key values the IN conditions of a relationship statement are given, as
+/// DynamoDB limits the size of IN conditions.
+const MAX_BATCH_KEY_VALUES: usize = 50;
+
+/// Fill in the relationship fields of the rows of rowsets built with the same plan, running the
+/// query of each relationship once for the rows of all of them.
+async fn add_relationships(
+    context: Context<'_>,
+    builders: &mut [RowsetBuilder<'_>],
+) -> Result<(), Error> {
+    let Some(rowset) = builders.first().map(|builder| builder.rowset) else {
+        return Ok(());
+    };
+    for (alias, field) in rowset.fields.iter().flatten() {
+        let sql::execution_plan::Field::Relationship(relationship) = field else {
+            continue;
+        };
+        let items = builders
+            .iter()
+            .flat_map(|builder| &builder.items)
+            .collect::<Vec<_>>();
+        let rowsets = execute_relationship(context, relationship, &items).await?;
+        let rows = builders.iter_mut().flat_map(|builder| &mut builder.rows);
+        for (row, rowset) in rows.zip(rowsets) {
+            if let Value::Object(row) = row {
+                row.insert(alias.clone(), rowset);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run the query of a relationship for source items, and return the rowset of each of them.
+///
+/// The target items of the distinct join keys of the source items are read in batches, by a
+/// statement with each join attribute in the values of the batch. They are then grouped by join
+/// key, and the ordering, offset and limit of the query apply to each group.
+fn execute_relationship<'a>(
+    context: Context<'a>,
+    relationship: &'a sql::execution_plan::RelationshipQuery,
+    source_items: &'a [&'a HashMap<String, AttributeValue>],
+) -> Pin<Box<dyn Future<Output = Result<Vec<Value>, Error>> + Send + 'a>> {
+    Box::pin(async move {
+        let query = &relationship.query;
+        // items missing part of their join key have no related items
+        let keys = source_items
+            .iter()
+            .map(|item| {
+                relationship
+                    .join
+                    .iter()
+                    .map(|column| {
+                        item.get(&column.source)
+                            .filter(|value| !matches!(value, AttributeValue::Null(_)))
+                            .cloned()
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .collect::<Vec<_>>();
+        let mut seen = HashSet::new();
+        let distinct_keys = keys
+            .iter()
+            .flatten()
+            .filter(|key| seen.insert(JoinKey(key.to_vec())))
+            .collect::<Vec<_>>();
+
+        // the semi-joins of the query are the same for every batch, so they are read once
+        let semi_join_keys = read_semi_joins(context, &query.query).await?;
+        let mut target_items: HashMap<JoinKey, Vec<HashMap<String, AttributeValue>>> =
+            HashMap::new();
+        let batch_size = (MAX_BATCH_KEY_VALUES / relationship.join.len().max(1)).max(1);
+        for batch in distinct_keys.chunks(batch_size) {
+            let mut select = query.query.clone();
+            let condition = batch_condition(&relationship.join, batch.len());
+            select.where_ = sql::ast::Where(if select.where_.0 == sql::helpers::true_expr() {
+                condition
+            } else {
+                sql::ast::Expression::And {
+                    left: Box::new(select.where_.0),
+                    right: Box::new(condition),
+                }
+            });
+            let batch_keys = batch
+                .iter()
+                .flat_map(|key| key.iter().cloned())
+                .collect::<Vec<_>>();
+            let (statement, parameters) =
+                render_statement(context, &select, &semi_join_keys, batch_keys)?;
+            let mut pages = Pages::new(
+                context.client,
+                statement,
+                parameters,
+                &query.document_paths,
+                None,
+                None,
+            );
+            while let Some(page) = pages.next().await? {
+                for item in page.items {
+                    let key = relationship
+                        .join
+                        .iter()
+                        .map(|column| item.get(&column.target).cloned())
+                        .collect::<Option<Vec<_>>>();
+                    if let Some(key) = key {
+                        target_items.entry(JoinKey(key)).or_default().push(item);
+                    }
+                }
+            }
+        }
+
+        let offset = query.offset.map_or(0, |offset| offset as usize);
+        let limit = query.limit.map_or(usize::MAX, |limit| limit as usize);
+        let mut builders = keys
+            .iter()
+            .map(|key| {
+                let mut items = key
+                    .as_ref()
+                    .and_then(|key| target_items.get(&JoinKey(key.clone())))
+                    .cloned()
+                    .unwrap_or_default();
+                sort_items(&mut items, &query.order_by);
+                let mut rowset = RowsetBuilder::new(&query.rowset);
+                for (position, item) in items.iter().enumerate().skip(offset).take(limit) {
+                    rowset.add(item, || ContinuationToken {
+                        next_token: None,
+                        skip: position + 1,
+                    });
+                }
+                rowset
+            })
+            .collect::<Vec<_>>();
+        add_relationships(context, &mut builders).await?;
+        Ok(builders.into_iter().map(RowsetBuilder::finish).collect())
+    })
+}
+
+/// The join condition of a batch of join keys: every join attribute of the target items is one of
+/// its values in the keys. The values are bound in order, key after key.
+fn batch_condition(join: &[sql::execution_plan::JoinColumn], keys: usize) -> sql::ast::Expression {
+    join.iter()
+        .enumerate()
+        .map(
+            |(position, column)| sql::ast::Expression::BinaryArrayOperation {
+                left: Box::new(column.target_column.clone()),
+                operator: sql::ast::BinaryArrayOperator::In,
+                right: (0..keys)
+                    .map(|key| {
+                        sql::ast::Expression::Value(sql::ast::Value::BatchKey(
+                            key * join.len() + position,
+                        ))
+                    })
+                    .collect(),
+            },
+        )
+        .reduce(|left, right| sql::ast::Expression::And {
+            left: Box::new(left),
+            right: Box::new(right),
+        })
+        .unwrap_or_else(sql::helpers::true_expr)
+}
+
+/// What the statements of a query run with.
+#[derive(Clone, Copy)]
+struct Context<'a> {
+    client: &'a Client,
+    /// The variable set the query runs with.
+    variables: Option<&'a BTreeMap<models::VariableName, Value>>,
+    /// The semi-joins of the plan, by the index of their placeholders.
+    semi_joins: &'a [sql::execution_plan::SemiJoin],
+    /// The most distinct join keys a semi-join may read.
+    max_exists_join_keys: usize,
+    /// The most items a query may sort in memory.
+    max_items_sorted_in_memory: usize,
+}
+
+/// Read the join keys of the semi-joins of a statement, then render it and bind its parameters.
+fn prepare_statement<'a>(
+    context: Context<'a>,
+    select: &'a sql::ast::Select,
+    batch_keys: Vec<AttributeValue>,
+) -> Pin<Box<dyn Future<Output = Result<(String, Option<Vec<AttributeValue>>), Error>> + Send + 'a>>
+{
+    Box::pin(async move {
+        let semi_join_keys = read_semi_joins(context, select).await?;
+        render_statement(context, select, &semi_join_keys, batch_keys)
+    })
+}
+
+/// Read the distinct join keys of each semi-join of a statement, by the index of its placeholder.
+async fn read_semi_joins(
+    context: Context<'_>,
+    select: &sql::ast::Select,
+) -> Result<BTreeMap<usize, Vec<Vec<AttributeValue>>>, Error> {
+    let mut indices = vec![];
+    semi_join_indices(&select.where_.0, &mut indices);
+    let mut semi_join_keys = BTreeMap::new();
+    for index in indices {
+        let semi_join = context.semi_joins.get(index).ok_or_else(|| {
+            Error::Query(QueryError::InvalidValue(format!(
+                "the plan has no semi-join {index}"
+            )))
+        })?;
+        semi_join_keys.insert(index, read_semi_join_keys(context, semi_join).await?);
+    }
+    Ok(semi_join_keys)
+}
+
+/// Render a statement, with the condition of each of its semi-joins in place of its placeholder,
+/// and bind its parameters. The join keys of the semi-joins are bound after the join keys of the
+/// batch the statement reads, if any.
+fn render_statement(
+    context: Context<'_>,
+    select: &sql::ast::Select,
+    semi_join_keys: &BTreeMap<usize, Vec<Vec<AttributeValue>>>,
+    mut batch_keys: Vec<AttributeValue>,
+) -> Result<(String, Option<Vec<AttributeValue>>), Error> {
+    let statement = if semi_join_keys.is_empty() {
+        sql::execution_plan::select_to_sql(select)
+    } else {
+        let mut conditions = BTreeMap::new();
+        for (index, keys) in semi_join_keys {
+            let semi_join = &context.semi_joins[*index];
+            let condition = semi_join_condition(&semi_join.join, keys.len(), batch_keys.len());
+            batch_keys.extend(keys.iter().flatten().cloned());
+            conditions.insert(*index, condition);
+        }
+        let mut select = select.clone();
+        select.where_ = sql::ast::Where(replace_semi_joins(select.where_.0, &conditions));
+        sql::execution_plan::select_to_sql(&select)
+    };
+    let parameters = bind_parameters(&statement.params, context.variables, &batch_keys)?;
+    Ok((statement.sql, parameters))
+}
+
+/// Read the distinct join keys of the target items matching a semi-join.
+async fn read_semi_join_keys(
+    context: Context<'_>,
+    semi_join: &sql::execution_plan::SemiJoin,
+) -> Result<Vec<Vec<AttributeValue>>, Error> {
+    let (statement, parameters) = prepare_statement(context, &semi_join.query, vec![]).await?;
+    let mut pages = Pages::new(context.client, statement, parameters, &[], None, None);
+    let mut seen = HashSet::new();
+    let mut keys = vec![];
+    while let Some(page) = pages.next().await? {
+        for item in page.items {
+            // items missing part of their join key match no source item
+            let key = semi_join
+                .join
+                .iter()
+                .map(|column| {
+                    item.get(&column.target)
+                        .filter(|value| !matches!(value, AttributeValue::Null(_)))
+                        .cloned()
+                })
+                .collect::<Option<Vec<_>>>();
+            if let Some(key) = key {
+                if seen.insert(JoinKey(key.clone())) {
+                    keys.push(key);
+                }
+            }
+        }
+        if keys.len() > context.max_exists_join_keys {
+            return Err(Error::Query(QueryError::TooManyJoinKeys {
+                limit: context.max_exists_join_keys,
+            }));
+        }
+    }
+    Ok(keys)
+}
+
+/// The condition of a semi-join: the join attributes of the source items are those of one of the
+/// join keys, bound from the given parameter index on, key after key.
+fn semi_join_condition(
+    join: &[sql::execution_plan::JoinColumn],
+    keys: usize,
+    first: usize,
+) -> sql::ast::Expression {
+    let or = |left, right| sql::ast::Expression::Or {
+        left: Box::new(left),
+        right: Box::new(right),
+    };
+    let indices = (first..first + keys).collect::<Vec<_>>();
+    let condition = match join {
+        // IN conditions are limited in size, so the keys are split across several of them
+        [column] => indices
+            .chunks(MAX_BATCH_KEY_VALUES)
+            .map(|chunk| sql::ast::Expression::BinaryArrayOperation {
+                left: Box::new(column.source_column.clone()),
+                operator: sql::ast::BinaryArrayOperator::In,
+                right: chunk
+                    .iter()
+                    .map(|index| sql::ast::Expression::Value(sql::ast::Value::BatchKey(*index)))
+                    .collect(),
+            })
+            .reduce(or),
+        _ => (0..keys)
+            .map(|key| {
+                join.iter()
+                    .enumerate()
+                    .map(|(position, column)| sql::ast::Expression::BinaryOperation {
+                        left: Box::new(column.source_column.clone()),
+                        operator: sql::ast::BinaryOperator("=".to_string()),
+                        right: Box::new(sql::ast::Expression::Value(sql::ast::Value::BatchKey(
+                            first + key * join.len() + position,
+                        ))),
+                    })
+                    .reduce(|left, right| sql::ast::Expression::And {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    })
+                    .unwrap_or_else(sql::helpers::true_expr)
+            })
+            .reduce(or),
+    };
+    // no target item matches
+    condition.unwrap_or(sql::ast::Expression::Value(sql::ast::Value::Bool(false)))
+}
+
+/// Find the semi-join placeholders of a condition. Filters only combine them with AND, OR and NOT.
+fn semi_join_indices(expression: &sql::ast::Expression, indices: &mut Vec<usize>) {
+    match expression {
+        sql::ast::Expression::SemiJoin(index) => indices.push(*index),
+        sql::ast::Expression::And { left, right } | sql::ast::Expression::Or { left, right } => {
+            semi_join_indices(left, indices);
+            semi_join_indices(right, indices);
+        }
+        sql::ast::Expression::Not(expression) => semi_join_indices(expression, indices),
+        _ => {}
+    }
+}
+
+/// Replace the semi-join placeholders of a condition with their conditions.
+fn replace_semi_joins(
+    expression: sql::ast::Expression,
+    conditions: &BTreeMap<usize, sql::ast::Expression>,
+) -> sql::ast::Expression {
+    match expression {
+        sql::ast::Expression::SemiJoin(index) => conditions
+            .get(&index)
+            .cloned()
+            .unwrap_or(sql::ast::Expression::SemiJoin(index)),
+        sql::ast::Expression::And { left, right } => sql::ast::Expression::And {
+            left: Box::new(replace_semi_joins(*left, conditions)),
+            right: Box::new(replace_semi_joins(*right, conditions)),
+        },
+        sql::ast::Expression::Or { left, right } => sql::ast::Expression::Or {
+            left: Box::new(replace_semi_joins(*left, conditions)),
+            right: Box::new(replace_semi_joins(*right, conditions)),
+        },
+        sql::ast::Expression::Not(expression) => {
+            sql::ast::Expression::Not(Box::new(replace_semi_joins(*expression, conditions)))
+        }
+        expression => expression,
+    }
+}
+
+/// A join key, compared and hashed by the attribute values it is made of.
+#[derive(Debug, Clone, PartialEq)]
+struct JoinKey(Vec<AttributeValue>);
+
+impl Eq for JoinKey {}
+
+impl Hash for JoinKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.len().hash(state);
+        for value in &self.0 {
+            hash_attribute_value(value, state);
+        }
+    }
+}
+
+/// Hash an attribute value consistently with its equality. The fields of maps have no order, so
+/// they are hashed sorted by name.
+fn hash_attribute_value<H: Hasher>(value: &AttributeValue, state: &mut H) {
+    std::mem::discriminant(value).hash(state);
+    match value {
+        AttributeValue::S(s) | AttributeValue::N(s) => s.hash(state),
+        AttributeValue::B(bytes) => bytes.as_ref().hash(state),
+        AttributeValue::Bool(b) | AttributeValue::Null(b) => b.hash(state),
+        AttributeValue::Ss(values) | AttributeValue::Ns(values) => values.hash(state),
+        AttributeValue::Bs(values) => {
+            values.len().hash(state);
+            for bytes in values {
+                bytes.as_ref().hash(state);
+            }
+        }
+        AttributeValue::L(values) => {
+            values.len().hash(state);
+            for value in values {
+                hash_attribute_value(value, state);
+            }
+        }
+        AttributeValue::M(fields) => {
+            let mut fields = fields.iter().collect::<Vec<_>>();
+            fields.sort_by_key(|(name, _)| *name);
+            fields.len().hash(state);
+            for (name, value) in fields {
+                name.hash(state);
+                hash_attribute_value(value, state);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The values of the `?` placeholders of a statement, in order. Variables are looked up in the
+/// variable set the statement runs with, and join keys in the batch of keys it reads.
+fn bind_parameters(
+    params: &[sql::string::Param],
+    variables: Option<&BTreeMap<models::VariableName, Value>>,
+    batch_keys: &[AttributeValue],
+) -> Result<Option<Vec<AttributeValue>>, Error> {
+    if params.is_empty() {
+        return Ok(None);
+    }
+    params
+        .iter()
+        .map(|param| match param {
+            sql::string::Param::String(s) => Ok(AttributeValue::S(s.clone())),
+            sql::string::Param::Value(value) => Ok(values::json_to_attribute_value(value)),
+            sql::string::Param::Variable {
+                name,
+                attribute_type,
+                key_prefix,
+                custom_scalar,
+            } => {
+                let value = variables
+                    .and_then(|variables| {
+                        variables
                             .iter()
-                            .map(|b| {
-                                base64::Engine::encode(
-                                    &base64::engine::general_purpose::STANDARD,
-                                    b.as_ref(),
-                                )
-                            })
-                            .collect::<Vec<String>>()
-                            .join(", ");
-                        hashmap.insert(key, bs_str);
-                    } else if attribute_value.is_m() {
-                        let m = attribute_value.as_m().unwrap();
-                        let mut m_str = "{".to_string();
-                        for (k, v) in m {
-                            m_str.push_str(&format!("{k}: {v:?}, "));
-                        }
-                        m_str.push('}');
-                        hashmap.insert(key, m_str);
-                    } else if attribute_value.is_l() {
-                        let l = attribute_value.as_l().unwrap();
-                        let mut l_str = "[".to_string();
-                        for v in l {
-                            l_str.push_str(&format!("{v:?}, "));
-                        }
-                        l_str.push(']');
-                        hashmap.insert(key, l_str);
-                    } else {
-                        println!("Unknown");
+                            .find(|(variable, _)| variable.as_str() == name)
+                    })
+                    .map(|(_, value)| value)
+                    .ok_or_else(|| Error::Query(QueryError::VariableNotFound(name.clone())))?;
+                // variables are only known here, so their format is checked as they are bound
+                if let Some(custom_scalar) = custom_scalar {
+                    if !custom_scalar.is_valid_value(value) {
+                        return Err(Error::Query(QueryError::InvalidValue(format!(
+                            "variable '{name}' is not of type '{}': {value}",
+                            custom_scalar.name()
+                        ))));
+                    }
+                }
+                values::to_attribute_value(&sql::execution_plan::TypedValue {
+                    value: match key_prefix {
+                        None => value.clone(),
+                        Some(key_prefix) => add_key_prefix(key_prefix, value),
+                    },
+                    attribute_type: *attribute_type,
+                })
+            }
+            sql::string::Param::BatchKey(index) => Ok(batch_keys
+                .get(*index)
+                .cloned()
+                .unwrap_or(AttributeValue::Null(true))),
+            sql::string::Param::Typed {
+                value,
+                attribute_type,
+            } => values::to_attribute_value(&sql::execution_plan::TypedValue {
+                value: value.clone(),
+                attribute_type: *attribute_type,
+            }),
+        })
+        .collect::<Result<Vec<_>, Error>>()
+        .map(Some)
+}
+
+/// Add a key prefix to a string value, or to the strings of a list of values.
+fn add_key_prefix(key_prefix: &str, value: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(format!("{key_prefix}{s}")),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| add_key_prefix(key_prefix, value))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// The pages of results of a statement, read one `ExecuteStatement` call at a time.
+struct Pages<'a> {
+    client: &'a Client,
+    statement: String,
+    parameters: Option<Vec<AttributeValue>>,
+    document_paths: &'a [Vec<String>],
+    page_limit: Option<u32>,
+    next_token: Option<String>,
+    done: bool,
+}
+
+impl<'a> Pages<'a> {
+    fn new(
+        client: &'a Client,
+        statement: String,
+        parameters: Option<Vec<AttributeValue>>,
+        document_paths: &'a [Vec<String>],
+        page_limit: Option<u32>,
+        next_token: Option<String>,
+    ) -> Pages<'a> {
+        Pages {
+            client,
+            statement,
+            parameters,
+            document_paths,
+            page_limit,
+            next_token,
+            done: false,
+        }
+    }
+
+    /// Read the next page, or `None` once every page has been read.
+    #[allow(clippy::cast_possible_wrap)]
+    async fn next(&mut self) -> Result<Option<Page>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+        let token = self.next_token.take();
+        let page = self
+            .client
+            .execute_statement()
+            .statement(self.statement.clone())
+            .set_parameters(self.parameters.clone())
+            .set_limit(self.page_limit.map(|limit| limit as i32))
+            .set_next_token(token.clone())
+            .send()
+            .await
+            .map_err(|err| Error::DynamoDB(DisplayErrorContext(&err).to_string()))?;
+        self.next_token.clone_from(&page.next_token);
+        self.done = self.next_token.is_none();
+        let mut items = page.items.unwrap_or_default();
+        for item in &mut items {
+            nest_document_paths(item, self.document_paths);
+        }
+        Ok(Some(Page {
+            token,
+            items,
+            next_token: page.next_token,
+        }))
+    }
+}
+
+/// A page of results.
+struct Page {
+    /// The `NextToken` the page was read with.
+    token: Option<String>,
+    items: Vec<HashMap<String, AttributeValue>>,
+    /// The `NextToken` of the following page, if any.
+    next_token: Option<String>,
+}
+
+impl Page {
+    /// Where to continue reading after an item of the page.
+    fn cursor_after(&self, index: usize) -> ContinuationToken {
+        match &self.next_token {
+            Some(next_token) if index + 1 == self.items.len() => ContinuationToken {
+                next_token: Some(next_token.clone()),
+                skip: 0,
+            },
+            _ => ContinuationToken {
+                next_token: self.token.clone(),
+                skip: index + 1,
+            },
+        }
+    }
+}
+
+/// Builds the rows and aggregates of a rowset from the items added to it.
+/// The relationship fields of the rows are filled in afterwards, from the items kept for them.
+struct RowsetBuilder<'a> {
+    rowset: &'a sql::execution_plan::Rowset,
+    rows: Vec<Value>,
+    /// The item of each row, when the rows have relationship fields.
+    items: Vec<HashMap<String, AttributeValue>>,
+    accumulators: Vec<(String, aggregates::Accumulator)>,
+}
+
+impl<'a> RowsetBuilder<'a> {
+    fn new(rowset: &'a sql::execution_plan::Rowset) -> RowsetBuilder<'a> {
+        RowsetBuilder {
+            rowset,
+            rows: vec![],
+            items: vec![],
+            accumulators: rowset
+                .aggregates
+                .iter()
+                .flatten()
+                .map(|(alias, aggregate)| {
+                    (
+                        alias.clone(),
+                        aggregates::Accumulator::new(aggregate.clone()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Add an item, with the cursor to continue reading after it.
+    fn add(
+        &mut self,
+        item: &HashMap<String, AttributeValue>,
+        cursor: impl Fn() -> ContinuationToken,
+    ) {
+        for (_, accumulator) in &mut self.accumulators {
+            accumulator.add(item);
+        }
+        if let Some(fields) = &self.rowset.fields {
+            self.rows.push(make_row(fields, item, cursor));
+            if fields
+                .iter()
+                .any(|(_, field)| matches!(field, sql::execution_plan::Field::Relationship(_)))
+            {
+                self.items.push(item.clone());
+            }
+        }
+    }
+
+    fn finish(self) -> Value {
+        let mut rowset = serde_json::Map::new();
+        if self.rowset.fields.is_some() {
+            rowset.insert("rows".to_string(), Value::Array(self.rows));
+        }
+        if self.rowset.aggregates.is_some() {
+            rowset.insert(
+                "aggregates".to_string(),
+                Value::Object(
+                    self.accumulators
+                        .into_iter()
+                        .map(|(alias, accumulator)| (alias, accumulator.finish()))
+                        .collect(),
+                ),
+            );
+        }
+        Value::Object(rowset)
+    }
+}
+
+/// Sort items by their attributes, or fields nested in maps of their attributes.
+///
+/// Values compare the way DynamoDB orders them: numbers by value and strings by their UTF-8
+/// bytes. Missing and null values are greater than any other value, and values DynamoDB cannot
+/// compare, such as values of different types, keep their relative order.
+fn sort_items(
+    items: &mut [HashMap<String, AttributeValue>],
+    order_by: &[sql::execution_plan::OrderByElement],
+) {
+    items.sort_by(|left, right| {
+        order_by
+            .iter()
+            .map(|element| {
+                let value = |item: &HashMap<String, AttributeValue>| {
+                    element
+                        .field_path
+                        .iter()
+                        .try_fold(item.get(&element.attribute)?, |value, field| match value {
+                            AttributeValue::M(fields) => fields.get(field),
+                            _ => None,
+                        })
+                        .filter(|value| !matches!(value, AttributeValue::Null(_)))
+                };
+                let ordering = match (value(left), value(right)) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(_), None) => Ordering::Less,
+                    (Some(left), Some(right)) => {
+                        values::compare(left, right).unwrap_or(Ordering::Equal)
+                    }
+                };
+                match element.direction {
+                    sql::ast::OrderByDirection::Asc => ordering,
+                    sql::ast::OrderByDirection::Desc => ordering.reverse(),
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Build a row from the requested fields of an item.
+fn make_row(
+    fields: &[(String, sql::execution_plan::Field)],
+    item: &HashMap<String, AttributeValue>,
+    cursor: impl Fn() -> ContinuationToken,
+) -> Value {
+    Value::Object(
+        fields
+            .iter()
+            .map(|(alias, field)| {
+                let value = match field {
+                    sql::execution_plan::Field::Column { attribute, fields } => item
+                        .get(attribute)
+                        .map_or(Value::Null, |value| match fields {
+                            None => values::from_attribute_value(value),
+                            Some(fields) => project_nested_field(value, fields),
+                        }),
+                    sql::execution_plan::Field::PrefixedColumn {
+                        attribute,
+                        key_prefix,
+                    } => item
+                        .get(attribute)
+                        .map_or(Value::Null, |value| match value {
+                            AttributeValue::S(s) => Value::String(
+                                s.strip_prefix(key_prefix.as_str()).unwrap_or(s).to_string(),
+                            ),
+                            value => values::from_attribute_value(value),
+                        }),
+                    sql::execution_plan::Field::KeyTemplateField {
+                        attribute,
+                        template,
+                        field,
+                        is_number,
+                    } => match item.get(attribute) {
+                        Some(AttributeValue::S(s)) => split_key_template(s, template, field)
+                            .map_or(Value::Null, |text| {
+                                key_template_field_value(text, *is_number)
+                            }),
+                        _ => Value::Null,
+                    },
+                    sql::execution_plan::Field::Cursor => Value::String(cursor().encode()),
+                    // filled in once the relationship queries have run
+                    sql::execution_plan::Field::Relationship(_) => Value::Null,
+                };
+                (alias.clone(), value)
+            })
+            .collect(),
+    )
+}
+
+/// Split the value of a field from an attribute value following its key template. Each field
+/// runs up to the first occurrence of the text after it.
+fn split_key_template<'a>(
+    value: &'a str,
+    template: &[sql::execution_plan::KeyTemplatePart],
+    field: &str,
+) -> Option<&'a str> {
+    let mut rest = value;
+    let mut found = None;
+    for (index, part) in template.iter().enumerate() {
+        match part {
+            sql::execution_plan::KeyTemplatePart::Text(text) => {
+                rest = rest.strip_prefix(text.as_str())?;
+            }
+            sql::execution_plan::KeyTemplatePart::Field(name) => {
+                let end = match template.get(index + 1) {
+                    Some(sql::execution_plan::KeyTemplatePart::Text(text)) => {
+                        rest.find(text.as_str())?
                     }
+                    _ => rest.len(),
+                };
+                if name == field {
+                    found = Some(&rest[..end]);
                 }
-                res_map.push(hashmap);
+                rest = &rest[end..];
             }
+        }
+    }
+    if rest.is_empty() {
+        found
+    } else {
+        None
+    }
+}
 
-            let mut rows: HashMap<String, Vec<HashMap<String, String>>> = HashMap::new();
-            rows.insert("rows".into(), res_map);
+/// The value of a virtual field from its text in the attribute value. Numbers are only read as
+/// they are written when a key template is filled, so that they compare with the attribute value:
+/// `007` or `7.0` is no number, and is read as null.
+fn key_template_field_value(text: &str, is_number: bool) -> Value {
+    if !is_number {
+        return Value::String(text.to_string());
+    }
+    match text.parse::<serde_json::Number>() {
+        Ok(number) if number.to_string() == text => Value::Number(number),
+        _ => Value::Null,
+    }
+}
 
-            let rows_stringified = serde_json::to_string(&rows).unwrap();
+/// Select the requested fields of a map or list value.
+fn project_nested_field(
+    value: &AttributeValue,
+    nested_field: &sql::execution_plan::NestedField,
+) -> Value {
+    match (value, nested_field) {
+        (AttributeValue::M(map), sql::execution_plan::NestedField::Object(fields)) => {
+            Value::Object(
+                fields
+                    .iter()
+                    .map(|(alias, field)| {
+                        let value = match field {
+                            sql::execution_plan::Field::Column { attribute, fields } => map
+                                .get(attribute)
+                                .map_or(Value::Null, |value| match fields {
+                                    None => values::from_attribute_value(value),
+                                    Some(fields) => project_nested_field(value, fields),
+                                }),
+                            sql::execution_plan::Field::PrefixedColumn { .. }
+                            | sql::execution_plan::Field::KeyTemplateField { .. }
+                            | sql::execution_plan::Field::Cursor
+                            | sql::execution_plan::Field::Relationship(_) => Value::Null,
+                        };
+                        (alias.clone(), value)
+                    })
+                    .collect(),
+            )
+        }
+        (AttributeValue::L(elements), sql::execution_plan::NestedField::Array(fields)) => {
+            Value::Array(
+                elements
+                    .iter()
+                    .map(|element| project_nested_field(element, fields))
+                    .collect(),
+            )
+        }
+        // nulls, and values which do not have the shape of the selection
+        _ => Value::Null,
+    }
+}
 
-            let row_value: Value = serde_json::from_str(&rows_stringified).unwrap();
+/// Put the values of nested document paths DynamoDB returned under the name of their last step
+/// back in place, so that items have the same shape whether or not their top-level attributes
+/// were read whole.
+fn nest_document_paths(item: &mut HashMap<String, AttributeValue>, document_paths: &[Vec<String>]) {
+    // attributes returned as they are
+    let top_level = document_paths
+        .iter()
+        .filter_map(|path| path.first())
+        .filter(|attribute| item.contains_key(*attribute))
+        .cloned()
+        .collect::<Vec<_>>();
+    for path in document_paths {
+        let (Some(attribute), Some(last)) = (path.first(), path.last()) else {
+            continue;
+        };
+        if top_level.contains(attribute) {
+            continue;
+        }
+        if let Some(value) = item.remove(last) {
+            insert_at_path(item, path, value);
+        }
+    }
+}
+
+/// Insert a value at a path of nested maps, creating the maps missing along the way.
+fn insert_at_path(
+    map: &mut HashMap<String, AttributeValue>,
+    path: &[String],
+    value: AttributeValue,
+) {
+    match path {
+        [] => {}
+        [last] => {
+            map.insert(last.clone(), value);
+        }
+        [step, rest @ ..] => {
+            if let AttributeValue::M(fields) = map
+                .entry(step.clone())
+                .or_insert_with(|| AttributeValue::M(HashMap::new()))
+            {
+                insert_at_path(fields, rest, value);
+            }
+        }
+    }
+}
 
-            let row_value_array = Value::Array(vec![row_value]);
-            let final_row = to_string(&row_value_array).unwrap();
+/// The type of a variable parameter in an explanation: the attribute type it is bound as, and
+/// the custom scalar type its values are checked against, if any.
+fn variable_type_name(
+    attribute_type: sql::execution_plan::AttributeType,
+    custom_scalar: Option<&str>,
+) -> String {
+    let attribute_type_name = match attribute_type {
+        sql::execution_plan::AttributeType::String => "String",
+        sql::execution_plan::AttributeType::Number => "Number",
+        sql::execution_plan::AttributeType::Binary => "Binary",
+        sql::execution_plan::AttributeType::Boolean => "Boolean",
+        sql::execution_plan::AttributeType::List => "List",
+        sql::execution_plan::AttributeType::Map => "Map",
+        sql::execution_plan::AttributeType::Any => "Any",
+    };
+    match custom_scalar {
+        None => format!("{attribute_type_name} variable"),
+        Some(custom_scalar) => format!("{custom_scalar} ({attribute_type_name}) variable"),
+    }
+}
+
+/// Describe how a query would run without running it.
+///
+/// Parameter values are redacted unless `show_parameter_values` is set.
+pub fn explain(
+    plan: &sql::execution_plan::ExecutionPlan<sql::execution_plan::Query>,
+    show_parameter_values: bool,
+    max_items_sorted_in_memory: usize,
+) -> BTreeMap<String, String> {
+    let query_sql = plan.query.query_sql();
+    let mut details = BTreeMap::new();
+
+    details.insert("PartiQL".to_string(), query_sql.sql.clone());
+
+    if !query_sql.params.is_empty() {
+        let params = query_sql
+            .params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| {
+                let (param_type, value) = match param {
+                    sql::string::Param::String(s) => ("String".to_string(), serde_json::json!(s)),
+                    sql::string::Param::Value(value) => {
+                        (json_type_name(value).to_string(), value.clone())
+                    }
+                    // the value of a variable is only known when the statement runs
+                    sql::string::Param::Variable {
+                        name,
+                        attribute_type,
+                        custom_scalar,
+                        ..
+                    } => (
+                        variable_type_name(
+                            *attribute_type,
+                            custom_scalar.map(|custom_scalar| custom_scalar.name()),
+                        ),
+                        serde_json::Value::String(name.clone()),
+                    ),
+                    sql::string::Param::BatchKey(index) => {
+                        ("Join Key".to_string(), serde_json::json!(index))
+                    }
+                    sql::string::Param::Typed {
+                        value,
+                        attribute_type,
+                    } => (
+                        values::attribute_type_name(&sql::execution_plan::TypedValue {
+                            value: value.clone(),
+                            attribute_type: *attribute_type,
+                        })
+                        .to_string(),
+                        value.clone(),
+                    ),
+                };
+                if show_parameter_values {
+                    format!("?{}: {param_type} = {value}", index + 1)
+                } else {
+                    format!("?{}: {param_type} = <redacted>", index + 1)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        details.insert("Parameters".to_string(), params);
+    }
 
-            let b: Bytes = Bytes::from(final_row);
-            buffer.put(b);
+    let access_pattern = match &plan.query.access_pattern {
+        sql::execution_plan::AccessPattern::KeyLookup { table } => {
+            format!("Key lookup on table '{table}'")
         }
-        Some(_variable_sets) => {
-            todo!("foreach/variables not implemented in query engine / execution")
+        sql::execution_plan::AccessPattern::Query { table, index: None } => {
+            format!("Query on table '{table}'")
+        }
+        sql::execution_plan::AccessPattern::Query {
+            table,
+            index: Some(index),
+        } => format!("Query on index '{index}' of table '{table}'"),
+        sql::execution_plan::AccessPattern::Scan {
+            table,
+            index,
+            suggested_keys,
+            policy,
+        } => {
+            let target = match index {
+                None => format!("table '{table}'"),
+                Some(index) => format!("index '{index}' of table '{table}'"),
+            };
+            details.insert(
+                "Warning".to_string(),
+                format!(
+                    "This query reads every item of {target}. Filter on one of these attributes with an equality to run a Query instead: {}",
+                    suggested_keys.join(", ")
+                ),
+            );
+            match policy {
+                sql::execution_plan::ScanPolicy::Allow => {}
+                sql::execution_plan::ScanPolicy::Warn => {
+                    details.insert(
+                        "Scan Policy".to_string(),
+                        "warn: running this query logs a warning".to_string(),
+                    );
+                }
+                sql::execution_plan::ScanPolicy::Deny => {
+                    details.insert(
+                        "Scan Policy".to_string(),
+                        format!("deny: running this query fails, as the scan policy of {target} denies full scans"),
+                    );
+                }
+            }
+            format!("Scan of {target}")
+        }
+        sql::execution_plan::AccessPattern::NativeQuery { name } => {
+            format!("Native query '{name}'")
         }
     };
+    details.insert("Access Pattern".to_string(), access_pattern);
 
-    Ok(buffer.freeze())
+    if let Some(offset) = plan.query.offset {
+        details.insert(
+            "Offset".to_string(),
+            format!("The first {offset} matched items are read and skipped, unless the query continues after a cursor"),
+        );
+    }
+
+    if !plan.query.order_by.is_empty() {
+        let order_by = plan
+            .query
+            .order_by
+            .iter()
+            .map(|element| {
+                let target = std::iter::once(&element.attribute)
+                    .chain(&element.field_path)
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(".");
+                match element.direction {
+                    sql::ast::OrderByDirection::Asc => format!("{target} ASC"),
+                    sql::ast::OrderByDirection::Desc => format!("{target} DESC"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        details.insert(
+            "Order By".to_string(),
+            format!("In memory by {order_by}, over at most {max_items_sorted_in_memory} items"),
+        );
+    }
+
+    for (index, semi_join) in plan.query.semi_joins.iter().enumerate() {
+        let join = semi_join
+            .join
+            .iter()
+            .map(|column| format!("{} = {}", column.source, column.target))
+            .collect::<Vec<_>>()
+            .join(", ");
+        details.insert(
+            format!("Semi-join {index}"),
+            format!(
+                "{} reads the join keys that SEMI_JOIN({index}) filters on, joined on {join}",
+                sql::execution_plan::select_to_sql(&semi_join.query).sql
+            ),
+        );
+    }
+
+    for (alias, field) in plan.query.rowset.fields.iter().flatten() {
+        if let sql::execution_plan::Field::Relationship(relationship) = field {
+            let join = relationship
+                .join
+                .iter()
+                .map(|column| format!("{} = {}", column.source, column.target))
+                .collect::<Vec<_>>()
+                .join(", ");
+            details.insert(
+                format!("Relationship '{alias}'"),
+                format!(
+                    "{} joined on {join}, in batches of at most {MAX_BATCH_KEY_VALUES} join key values",
+                    relationship.query.query_sql().sql
+                ),
+            );
+        }
+    }
+
+    details
+}
+
+/// The DynamoDB type name of a json value.
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "Null",
+        Value::Bool(_) => "Boolean",
+        Value::Number(_) => "Number",
+        Value::String(_) => "String",
+        Value::Array(_) => "List",
+        Value::Object(_) => "Map",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sql::execution_plan::AccessPattern;
+
+    fn plan(
+        access_pattern: AccessPattern,
+        offset: Option<u32>,
+    ) -> sql::execution_plan::ExecutionPlan<sql::execution_plan::Query> {
+        sql::execution_plan::simple_query_execution_plan(
+            None,
+            "users".into(),
+            sql::helpers::simple_select(vec![]),
+            None,
+            offset,
+            None,
+            access_pattern,
+            sql::execution_plan::Rowset::default(),
+            vec![],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_explain_warns_about_scans() {
+        let details = explain(
+            &plan(
+                AccessPattern::Scan {
+                    table: "users".to_string(),
+                    index: None,
+                    suggested_keys: vec!["id".to_string(), "email (users:by_email)".to_string()],
+                    policy: sql::execution_plan::ScanPolicy::Allow,
+                },
+                None,
+            ),
+            false,
+            10_000,
+        );
+
+        assert_eq!(details["Access Pattern"], "Scan of table 'users'");
+        assert!(
+            details["Warning"].ends_with("id, email (users:by_email)"),
+            "{}",
+            details["Warning"]
+        );
+    }
+
+    #[test]
+    fn test_explain_describes_queries_on_indexes() {
+        let details = explain(
+            &plan(
+                AccessPattern::Query {
+                    table: "users".to_string(),
+                    index: Some("by_email".to_string()),
+                },
+                Some(10),
+            ),
+            false,
+            10_000,
+        );
+
+        assert_eq!(
+            details["Access Pattern"],
+            "Query on index 'by_email' of table 'users'"
+        );
+        assert!(!details.contains_key("Warning"));
+        assert_eq!(
+            details["Offset"],
+            "The first 10 matched items are read and skipped, unless the query continues after a cursor"
+        );
+    }
+
+    #[test]
+    fn test_explain_shows_denied_scans() {
+        let details = explain(
+            &plan(
+                AccessPattern::Scan {
+                    table: "users".to_string(),
+                    index: None,
+                    suggested_keys: vec!["id".to_string()],
+                    policy: sql::execution_plan::ScanPolicy::Deny,
+                },
+                None,
+            ),
+            false,
+            10_000,
+        );
+
+        assert_eq!(details["Access Pattern"], "Scan of table 'users'");
+        assert!(
+            details["Scan Policy"].starts_with("deny"),
+            "{}",
+            details["Scan Policy"]
+        );
+    }
+
+    #[test]
+    fn test_denied_scans_fail_before_running() {
+        let scan = |policy| AccessPattern::Scan {
+            table: "users".to_string(),
+            index: None,
+            suggested_keys: vec!["id".to_string()],
+            policy,
+        };
+
+        assert!(check_scan_policy(&scan(sql::execution_plan::ScanPolicy::Warn)).is_ok());
+        let result = check_scan_policy(&scan(sql::execution_plan::ScanPolicy::Deny));
+        assert!(
+            matches!(
+                result,
+                Err(Error::Query(QueryError::FullScanDenied { ref table, .. })) if table == "users"
+            ),
+            "{result:?}"
+        );
+    }
+
+    fn item(id: &str, score: Option<&str>) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::from([("id".to_string(), AttributeValue::S(id.to_string()))]);
+        if let Some(score) = score {
+            item.insert("score".to_string(), AttributeValue::N(score.to_string()));
+        }
+        item
+    }
+
+    fn order_by(
+        attribute: &str,
+        direction: sql::ast::OrderByDirection,
+    ) -> sql::execution_plan::OrderByElement {
+        sql::execution_plan::OrderByElement {
+            attribute: attribute.to_string(),
+            field_path: vec![],
+            direction,
+        }
+    }
+
+    fn ids(items: &[HashMap<String, AttributeValue>]) -> Vec<&str> {
+        items
+            .iter()
+            .map(|item| match &item["id"] {
+                AttributeValue::S(id) => id.as_str(),
+                _ => "",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_items_by_number_with_missing_values_last() {
+        let mut items = vec![
+            item("a", Some("10")),
+            item("b", None),
+            item("c", Some("9.5")),
+            item("d", Some("-1")),
+        ];
+
+        sort_items(
+            &mut items,
+            &[order_by("score", sql::ast::OrderByDirection::Asc)],
+        );
+        assert_eq!(ids(&items), ["d", "c", "a", "b"]);
+
+        sort_items(
+            &mut items,
+            &[order_by("score", sql::ast::OrderByDirection::Desc)],
+        );
+        assert_eq!(ids(&items), ["b", "a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_sort_items_breaks_ties_with_the_next_element() {
+        let mut items = vec![
+            item("b", Some("1")),
+            item("c", Some("2")),
+            item("a", Some("1")),
+        ];
+
+        sort_items(
+            &mut items,
+            &[
+                order_by("score", sql::ast::OrderByDirection::Asc),
+                order_by("id", sql::ast::OrderByDirection::Desc),
+            ],
+        );
+        assert_eq!(ids(&items), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_sorted_pages_resume_from_their_cursor() {
+        let items = ["a", "b", "c", "d", "e"]
+            .into_iter()
+            .map(|id| item(id, None))
+            .collect::<Vec<_>>();
+        let rowset = sql::execution_plan::Rowset {
+            fields: Some(vec![
+                (
+                    "id".to_string(),
+                    sql::execution_plan::Field::Column {
+                        attribute: "id".to_string(),
+                        fields: None,
+                    },
+                ),
+                ("cursor".to_string(), sql::execution_plan::Field::Cursor),
+            ]),
+            aggregates: None,
+        };
+
+        let mut builder = RowsetBuilder::new(&rowset);
+        add_sorted_items(&mut builder, &items, 1, Some(2));
+        let rows = builder.finish()["rows"].clone();
+        assert_eq!(rows[0]["id"], "b");
+        assert_eq!(rows[1]["id"], "c");
+        assert_eq!(rows.as_array().map(Vec::len), Some(2));
+
+        let cursor = ContinuationToken::decode(rows[1]["cursor"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            cursor,
+            ContinuationToken {
+                next_token: None,
+                skip: 3,
+            }
+        );
+
+        let mut builder = RowsetBuilder::new(&rowset);
+        add_sorted_items(&mut builder, &items, cursor.skip, None);
+        let rows = builder.finish()["rows"].clone();
+        assert_eq!(rows[0]["id"], "d");
+        assert_eq!(rows[1]["id"], "e");
+        assert_eq!(rows.as_array().map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn test_page_cursors_point_after_their_item() {
+        let page = Page {
+            token: Some("first".to_string()),
+            items: vec![item("a", None), item("b", None)],
+            next_token: Some("second".to_string()),
+        };
+
+        assert_eq!(
+            page.cursor_after(0),
+            ContinuationToken {
+                next_token: Some("first".to_string()),
+                skip: 1,
+            }
+        );
+        // the last item of a page resumes from the next page
+        assert_eq!(
+            page.cursor_after(1),
+            ContinuationToken {
+                next_token: Some("second".to_string()),
+                skip: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_explain_reports_the_types_of_variables() {
+        let variable = |name: &str, attribute_type, custom_scalar| {
+            sql::ast::Expression::Value(sql::ast::Value::Variable {
+                name: name.to_string(),
+                attribute_type,
+                key_prefix: None,
+                custom_scalar,
+            })
+        };
+        let mut plan = plan(
+            AccessPattern::KeyLookup {
+                table: "users".to_string(),
+            },
+            None,
+        );
+        plan.query.query.where_ = sql::ast::Where(sql::ast::Expression::BinaryOperation {
+            left: Box::new(variable(
+                "min_age",
+                sql::execution_plan::AttributeType::Number,
+                None,
+            )),
+            operator: sql::ast::BinaryOperator("<".to_string()),
+            right: Box::new(variable(
+                "since",
+                sql::execution_plan::AttributeType::String,
+                Some(query_engine_metadata::metadata::CustomScalarType::Timestamp),
+            )),
+        });
+
+        assert_eq!(
+            explain(&plan, false, 10_000)["Parameters"],
+            "?1: Number variable = <redacted>, ?2: Timestamp (String) variable = <redacted>"
+        );
+        assert_eq!(
+            explain(&plan, true, 10_000)["Parameters"],
+            "?1: Number variable = \"min_age\", ?2: Timestamp (String) variable = \"since\""
+        );
+    }
+
+    #[test]
+    fn test_explain_reports_the_in_memory_sort_bound() {
+        let mut plan = plan(
+            AccessPattern::KeyLookup {
+                table: "users".to_string(),
+            },
+            None,
+        );
+        plan.query.order_by = vec![order_by("score", sql::ast::OrderByDirection::Desc)];
+
+        let details = explain(&plan, false, 500);
+        assert_eq!(
+            details["Order By"],
+            "In memory by score DESC, over at most 500 items"
+        );
+    }
+
+    #[test]
+    fn test_offsets_only_apply_before_a_cursor() {
+        assert_eq!(
+            resume_point(None, Some(10)).unwrap(),
+            ContinuationToken {
+                next_token: None,
+                skip: 10,
+            }
+        );
+
+        // a cursor of the first page counts the offset already
+        let cursor = ContinuationToken {
+            next_token: Some("page".to_string()),
+            skip: 12,
+        };
+        assert_eq!(
+            resume_point(Some(&cursor.encode()), Some(10)).unwrap(),
+            cursor
+        );
+        assert_eq!(resume_point(Some(&cursor.encode()), None).unwrap(), cursor);
+        assert!(resume_point(Some("not a cursor"), Some(10)).is_err());
+    }
+
+    fn path(steps: &[&str]) -> Vec<String> {
+        steps.iter().map(ToString::to_string).collect()
+    }
+
+    fn string(s: &str) -> AttributeValue {
+        AttributeValue::S(s.to_string())
+    }
+
+    #[test]
+    fn test_prefixed_attributes_are_read_without_their_prefix() {
+        let fields = [
+            (
+                "id".to_string(),
+                sql::execution_plan::Field::PrefixedColumn {
+                    attribute: "PK".to_string(),
+                    key_prefix: "CUSTOMER#".to_string(),
+                },
+            ),
+            (
+                "legacy_id".to_string(),
+                sql::execution_plan::Field::PrefixedColumn {
+                    attribute: "legacy".to_string(),
+                    key_prefix: "CUSTOMER#".to_string(),
+                },
+            ),
+        ];
+        let item = HashMap::from([
+            ("PK".to_string(), string("CUSTOMER#42")),
+            ("legacy".to_string(), string("42")),
+        ]);
+
+        assert_eq!(
+            make_row(&fields, &item, ContinuationToken::default),
+            serde_json::json!({ "id": "42", "legacy_id": "42" })
+        );
+    }
+
+    #[test]
+    fn test_variables_get_their_key_prefix_when_bound() {
+        let params = [sql::string::Param::Variable {
+            name: "ids".to_string(),
+            attribute_type: sql::execution_plan::AttributeType::List,
+            key_prefix: Some("CUSTOMER#".to_string()),
+            custom_scalar: None,
+        }];
+        let variables = BTreeMap::from([("ids".into(), serde_json::json!(["1", "2"]))]);
+
+        assert_eq!(
+            bind_parameters(&params, Some(&variables), &[]).unwrap(),
+            Some(vec![AttributeValue::L(vec![
+                string("CUSTOMER#1"),
+                string("CUSTOMER#2"),
+            ])])
+        );
+    }
+
+    #[test]
+    fn test_variables_of_custom_scalar_types_are_checked_when_bound() {
+        let params = [sql::string::Param::Variable {
+            name: "since".to_string(),
+            attribute_type: sql::execution_plan::AttributeType::String,
+            key_prefix: None,
+            custom_scalar: Some(query_engine_metadata::metadata::CustomScalarType::Timestamp),
+        }];
+        let bind = |value| {
+            let variables = BTreeMap::from([("since".into(), value)]);
+            bind_parameters(&params, Some(&variables), &[])
+        };
+
+        assert_eq!(
+            bind(serde_json::json!("2024-01-05T10:30:00Z")).unwrap(),
+            Some(vec![string("2024-01-05T10:30:00Z")])
+        );
+        for value in [
+            serde_json::json!("2024-01-05T10:30:00+02:00"),
+            serde_json::json!("yesterday"),
+        ] {
+            let result = bind(value);
+            assert!(
+                matches!(result, Err(Error::Query(QueryError::InvalidValue(_)))),
+                "{result:?}"
+            );
+        }
+    }
+
+    /// The template `ORDER#{date}#{number}`.
+    fn order_template() -> Vec<sql::execution_plan::KeyTemplatePart> {
+        use sql::execution_plan::KeyTemplatePart;
+        vec![
+            KeyTemplatePart::Text("ORDER#".to_string()),
+            KeyTemplatePart::Field("date".to_string()),
+            KeyTemplatePart::Text("#".to_string()),
+            KeyTemplatePart::Field("number".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_split_key_template() {
+        let template = order_template();
+        let value = "ORDER#2024-01-05#1234";
+        assert_eq!(
+            split_key_template(value, &template, "date"),
+            Some("2024-01-05")
+        );
+        assert_eq!(split_key_template(value, &template, "number"), Some("1234"));
+        // a field runs up to the first occurrence of the text after it
+        assert_eq!(
+            split_key_template("ORDER#a#b#c", &template, "number"),
+            Some("b#c")
+        );
+    }
+
+    #[test]
+    fn test_split_values_without_the_shape_of_their_template() {
+        let template = order_template();
+        for value in ["PROFILE", "ORDER#2024-01-05", "order#2024-01-05#1234", ""] {
+            assert_eq!(
+                split_key_template(value, &template, "date"),
+                None,
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_number_fields_round_trip_as_written() {
+        assert_eq!(key_template_field_value("7", true), serde_json::json!(7));
+        assert_eq!(
+            key_template_field_value("-2.5", true),
+            serde_json::json!(-2.5)
+        );
+        // a zero padded value is not what filling the template with its number writes
+        assert_eq!(key_template_field_value("007", true), Value::Null);
+        assert_eq!(key_template_field_value("7.0", true), Value::Null);
+        assert_eq!(key_template_field_value("seven", true), Value::Null);
+        assert_eq!(
+            key_template_field_value("007", false),
+            serde_json::json!("007")
+        );
+    }
+
+    #[test]
+    fn test_virtual_fields_are_read_from_their_attribute() {
+        let field = |name: &str, is_number| sql::execution_plan::Field::KeyTemplateField {
+            attribute: "SK".to_string(),
+            template: order_template(),
+            field: name.to_string(),
+            is_number,
+        };
+        let fields = [
+            ("date".to_string(), field("date", false)),
+            ("number".to_string(), field("number", true)),
+        ];
+        let row = |sort_key: &str| {
+            make_row(
+                &fields,
+                &HashMap::from([("SK".to_string(), string(sort_key))]),
+                ContinuationToken::default,
+            )
+        };
+
+        assert_eq!(
+            row("ORDER#2024-01-05#1234"),
+            serde_json::json!({ "date": "2024-01-05", "number": 1234 })
+        );
+        assert_eq!(
+            row("ORDER#2024-01-05#007"),
+            serde_json::json!({ "date": "2024-01-05", "number": null })
+        );
+        assert_eq!(
+            row("PROFILE"),
+            serde_json::json!({ "date": null, "number": null })
+        );
+    }
+
+    #[test]
+    fn test_nested_paths_are_put_back_in_place() {
+        let mut item = HashMap::from([
+            ("city".to_string(), string("Paris")),
+            ("zip".to_string(), string("75001")),
+            ("id".to_string(), string("a")),
+        ]);
+        nest_document_paths(
+            &mut item,
+            &[path(&["address", "city"]), path(&["address", "zip"])],
+        );
+
+        assert_eq!(
+            item,
+            HashMap::from([
+                (
+                    "address".to_string(),
+                    AttributeValue::M(HashMap::from([
+                        ("city".to_string(), string("Paris")),
+                        ("zip".to_string(), string("75001")),
+                    ]))
+                ),
+                ("id".to_string(), string("a")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_attributes_read_whole_are_left_as_they_are() {
+        let address = AttributeValue::M(HashMap::from([("city".to_string(), string("Paris"))]));
+        let mut item = HashMap::from([
+            ("address".to_string(), address.clone()),
+            ("city".to_string(), string("Lyon")),
+        ]);
+        nest_document_paths(&mut item, &[path(&["address", "city"])]);
+
+        assert_eq!(item["address"], address);
+        assert_eq!(item["city"], string("Lyon"));
+    }
+
+    #[test]
+    fn test_insert_at_overlapping_paths() {
+        let mut map = HashMap::new();
+        insert_at_path(
+            &mut map,
+            &path(&["a"]),
+            AttributeValue::M(HashMap::from([("x".to_string(), string("1"))])),
+        );
+        insert_at_path(&mut map, &path(&["a", "b"]), string("2"));
+        insert_at_path(&mut map, &path(&["a", "c", "d"]), string("3"));
+
+        assert_eq!(
+            map["a"],
+            AttributeValue::M(HashMap::from([
+                ("x".to_string(), string("1")),
+                ("b".to_string(), string("2")),
+                (
+                    "c".to_string(),
+                    AttributeValue::M(HashMap::from([("d".to_string(), string("3"))]))
+                ),
+            ]))
+        );
+
+        // values which are not maps are kept rather than replaced
+        insert_at_path(&mut map, &path(&["a", "b", "e"]), string("4"));
+        assert_eq!(
+            map["a"],
+            AttributeValue::M(HashMap::from([
+                ("x".to_string(), string("1")),
+                ("b".to_string(), string("2")),
+                (
+                    "c".to_string(),
+                    AttributeValue::M(HashMap::from([("d".to_string(), string("3"))]))
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_join_keys_compare_by_their_values() {
+        let map = |fields: &[(&str, &str)]| {
+            AttributeValue::M(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), string(value)))
+                    .collect(),
+            )
+        };
+        let mut keys = HashSet::new();
+        assert!(keys.insert(JoinKey(vec![string("1"), map(&[("a", "x"), ("b", "y")])])));
+        // the same fields in another order
+        assert!(!keys.insert(JoinKey(vec![string("1"), map(&[("b", "y"), ("a", "x")])])));
+        // a number is not the string of its digits
+        assert!(keys.insert(JoinKey(vec![
+            AttributeValue::N("1".to_string()),
+            map(&[("a", "x"), ("b", "y")])
+        ])));
+        assert!(keys.insert(JoinKey(vec![string("1")])));
+        assert_eq!(keys.len(), 3);
+    }
+
+    #[test]
+    fn test_batch_condition_binds_the_join_keys_in_order() {
+        let join = join_columns(&["user_id", "region"]);
+
+        let mut rendered = sql::string::SQL::new();
+        batch_condition(&join, 2).to_sql(&mut rendered);
+        assert_eq!(rendered.sql, "((user_id IN [?, ?]) AND (region IN [?, ?]))");
+        assert_eq!(
+            rendered.params,
+            [
+                sql::string::Param::BatchKey(0),
+                sql::string::Param::BatchKey(2),
+                sql::string::Param::BatchKey(1),
+                sql::string::Param::BatchKey(3),
+            ]
+        );
+    }
+
+    fn column(name: &str) -> sql::ast::Expression {
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
+            table: sql::ast::TableReference::DBTable {
+                table: sql::ast::TableName("orders".to_string()),
+                gsi: None,
+            },
+            name: sql::ast::ColumnName(name.to_string()),
+        })
+    }
+
+    /// Join columns with the same name on both sides.
+    fn join_columns(names: &[&str]) -> Vec<sql::execution_plan::JoinColumn> {
+        names
+            .iter()
+            .map(|name| sql::execution_plan::JoinColumn {
+                source: (*name).to_string(),
+                target: (*name).to_string(),
+                source_column: column(name),
+                target_column: column(name),
+            })
+            .collect()
+    }
+
+    fn render(expression: &sql::ast::Expression) -> sql::string::SQL {
+        let mut sql = sql::string::SQL::new();
+        expression.to_sql(&mut sql);
+        sql
+    }
+
+    #[test]
+    fn test_semi_join_condition_on_one_column() {
+        let condition = render(&semi_join_condition(&join_columns(&["user_id"]), 3, 2));
+        assert_eq!(condition.sql, "(user_id IN [?, ?, ?])");
+        assert_eq!(
+            condition.params,
+            [2, 3, 4].map(sql::string::Param::BatchKey)
+        );
+    }
+
+    #[test]
+    fn test_semi_join_condition_splits_large_in_lists() {
+        let condition = render(&semi_join_condition(
+            &join_columns(&["user_id"]),
+            MAX_BATCH_KEY_VALUES + 1,
+            0,
+        ));
+        assert_eq!(
+            condition.sql.matches(" IN ").count(),
+            2,
+            "{}",
+            condition.sql
+        );
+        assert!(
+            condition.sql.ends_with(" OR (user_id IN [?]))"),
+            "{}",
+            condition.sql
+        );
+        assert_eq!(condition.params.len(), MAX_BATCH_KEY_VALUES + 1);
+    }
+
+    #[test]
+    fn test_semi_join_condition_on_several_columns() {
+        let condition = render(&semi_join_condition(
+            &join_columns(&["user_id", "region"]),
+            2,
+            0,
+        ));
+        assert_eq!(
+            condition.sql,
+            "(((user_id = ?) AND (region = ?)) OR ((user_id = ?) AND (region = ?)))"
+        );
+        assert_eq!(
+            condition.params,
+            [0, 1, 2, 3].map(sql::string::Param::BatchKey)
+        );
+    }
+
+    #[test]
+    fn test_semi_join_condition_without_keys_matches_nothing() {
+        let condition = semi_join_condition(&join_columns(&["user_id"]), 0, 0);
+        assert_eq!(
+            condition,
+            sql::ast::Expression::Value(sql::ast::Value::Bool(false))
+        );
+    }
+
+    #[test]
+    fn test_semi_join_placeholders_are_replaced_with_their_conditions() {
+        let placeholders = sql::ast::Expression::And {
+            left: Box::new(sql::ast::Expression::SemiJoin(0)),
+            right: Box::new(sql::ast::Expression::Not(Box::new(
+                sql::ast::Expression::SemiJoin(1),
+            ))),
+        };
+        let mut indices = vec![];
+        semi_join_indices(&placeholders, &mut indices);
+        assert_eq!(indices, [0, 1]);
+
+        let conditions = BTreeMap::from([
+            (0, sql::ast::Expression::Value(sql::ast::Value::Bool(true))),
+            (1, sql::ast::Expression::Value(sql::ast::Value::Bool(false))),
+        ]);
+        assert_eq!(
+            replace_semi_joins(placeholders, &conditions),
+            sql::ast::Expression::And {
+                left: Box::new(sql::ast::Expression::Value(sql::ast::Value::Bool(true))),
+                right: Box::new(sql::ast::Expression::Not(Box::new(
+                    sql::ast::Expression::Value(sql::ast::Value::Bool(false))
+                ))),
+            }
+        );
+    }
+
+    #[test]
+    fn test_semi_join_keys_are_bound_after_the_batch_keys() {
+        let client = aws_sdk_dynamodb::Client::from_conf(
+            aws_sdk_dynamodb::Config::builder()
+                .behavior_version_latest()
+                .build(),
+        );
+        let semi_joins = [sql::execution_plan::SemiJoin {
+            query: sql::helpers::simple_select(vec![]),
+            access_pattern: sql::execution_plan::AccessPattern::KeyLookup {
+                table: "orders".to_string(),
+            },
+            join: join_columns(&["user_id"]),
+        }];
+        let context = Context {
+            client: &client,
+            variables: None,
+            semi_joins: &semi_joins,
+            max_exists_join_keys: 100,
+            max_items_sorted_in_memory: 10_000,
+        };
+        let mut select = sql::helpers::simple_select(vec![]);
+        select.where_ = sql::ast::Where(sql::ast::Expression::And {
+            left: Box::new(sql::ast::Expression::SemiJoin(0)),
+            right: Box::new(batch_condition(&join_columns(&["region"]), 1)),
+        });
+        // read once, and rendered for each batch
+        let semi_join_keys = BTreeMap::from([(0, vec![vec![string("1")], vec![string("2")]])]);
+
+        for region in ["eu", "us"] {
+            let (_, parameters) =
+                render_statement(context, &select, &semi_join_keys, vec![string(region)]).unwrap();
+            assert_eq!(
+                parameters,
+                Some(vec![string(region), string("1"), string("2")])
+            );
+        }
+    }
 }
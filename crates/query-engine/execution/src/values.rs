@@ -0,0 +1,274 @@
+//! Convert between json values and DynamoDB attribute values.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::primitives::Blob;
+use aws_sdk_dynamodb::types::AttributeValue;
+use base64::Engine;
+
+use query_engine_sql::sql::execution_plan::{AttributeType, TypedValue};
+
+use crate::error::{Error, QueryError};
+
+/// Convert a typed json value to the attribute value it should be written as.
+pub fn to_attribute_value(typed_value: &TypedValue) -> Result<AttributeValue, Error> {
+    let TypedValue {
+        value,
+        attribute_type,
+    } = typed_value;
+    match (attribute_type, value) {
+        (_, serde_json::Value::Null) => Ok(AttributeValue::Null(true)),
+        (AttributeType::Binary, serde_json::Value::String(s)) => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .map_err(|err| {
+                    Error::Query(QueryError::InvalidValue(format!(
+                        "expected a base64 encoded binary value: {err}"
+                    )))
+                })?;
+            Ok(AttributeValue::B(Blob::new(bytes)))
+        }
+        _ => Ok(json_to_attribute_value(value)),
+    }
+}
+
+/// The name of the DynamoDB type a typed value is written as.
+pub fn attribute_type_name(typed_value: &TypedValue) -> &'static str {
+    match (typed_value.attribute_type, &typed_value.value) {
+        (_, serde_json::Value::Null) => "Null",
+        (AttributeType::String, _) => "String",
+        (AttributeType::Number, _) => "Number",
+        (AttributeType::Binary, _) => "Binary",
+        (AttributeType::Boolean, _) => "Boolean",
+        (AttributeType::List, _) => "List",
+        (AttributeType::Map, _) => "Map",
+        (AttributeType::Any, value) => match json_to_attribute_value(value) {
+            AttributeValue::S(_) => "String",
+            AttributeValue::N(_) => "Number",
+            AttributeValue::Bool(_) => "Boolean",
+            AttributeValue::L(_) => "List",
+            AttributeValue::M(_) => "Map",
+            _ => "Null",
+        },
+    }
+}
+
+/// Infer the attribute value of a json value.
+pub fn json_to_attribute_value(value: &serde_json::Value) -> AttributeValue {
+    match value {
+        serde_json::Value::Null => AttributeValue::Null(true),
+        serde_json::Value::Bool(b) => AttributeValue::Bool(*b),
+        serde_json::Value::Number(n) => AttributeValue::N(n.to_string()),
+        serde_json::Value::String(s) => AttributeValue::S(s.clone()),
+        serde_json::Value::Array(values) => {
+            AttributeValue::L(values.iter().map(json_to_attribute_value).collect())
+        }
+        serde_json::Value::Object(fields) => AttributeValue::M(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), json_to_attribute_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Convert an attribute value read from the database to json.
+/// Numbers that do not fit a json number are returned as strings, and binary values are base64
+/// encoded.
+pub fn from_attribute_value(attribute_value: &AttributeValue) -> serde_json::Value {
+    match attribute_value {
+        AttributeValue::S(s) => serde_json::Value::String(s.clone()),
+        AttributeValue::N(n) => number_to_json(n),
+        AttributeValue::Bool(b) => serde_json::Value::Bool(*b),
+        AttributeValue::B(b) => {
+            serde_json::Value::String(base64::engine::general_purpose::STANDARD.encode(b.as_ref()))
+        }
+        AttributeValue::Ss(ss) => ss.iter().cloned().map(serde_json::Value::String).collect(),
+        AttributeValue::Ns(ns) => ns.iter().map(|n| number_to_json(n)).collect(),
+        AttributeValue::Bs(bs) => bs
+            .iter()
+            .map(|b| {
+                serde_json::Value::String(
+                    base64::engine::general_purpose::STANDARD.encode(b.as_ref()),
+                )
+            })
+            .collect(),
+        AttributeValue::L(values) => values.iter().map(from_attribute_value).collect(),
+        AttributeValue::M(fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), from_attribute_value(value)))
+                .collect(),
+        ),
+        _ => serde_json::Value::Null,
+    }
+}
+
+/// Convert an item read from the database to a json object.
+pub fn item_to_json(item: &HashMap<String, AttributeValue>) -> serde_json::Value {
+    serde_json::Value::Object(
+        item.iter()
+            .map(|(key, value)| (key.clone(), from_attribute_value(value)))
+            .collect(),
+    )
+}
+
+/// Compare two values the way DynamoDB orders them: numbers by value and strings by their UTF-8
+/// bytes. Values of different types are not comparable.
+pub fn compare(left: &AttributeValue, right: &AttributeValue) -> Option<Ordering> {
+    match (left, right) {
+        (AttributeValue::N(left), AttributeValue::N(right)) => compare_numbers(left, right),
+        (AttributeValue::S(left), AttributeValue::S(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+/// Compare two DynamoDB numbers by their exact decimal value. They have up to 38 significant
+/// digits, more than a float holds, so they are compared digit by digit.
+fn compare_numbers(left: &str, right: &str) -> Option<Ordering> {
+    let (left, right) = (Decimal::parse(left)?, Decimal::parse(right)?);
+    let sign = left.signum().cmp(&right.signum());
+    if sign != Ordering::Equal {
+        return Some(sign);
+    }
+    // the digits all follow the decimal point, so the larger exponent is the larger magnitude
+    let magnitude = left
+        .exponent
+        .cmp(&right.exponent)
+        .then_with(|| left.digits.cmp(&right.digits));
+    Some(if left.negative {
+        magnitude.reverse()
+    } else {
+        magnitude
+    })
+}
+
+/// A decimal number, as `0.<digits> * 10^exponent`.
+struct Decimal {
+    negative: bool,
+    /// The significant digits, without leading or trailing zeros. Empty for zero.
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Parse a number such as `-12.50` or `1.5E+3`.
+    fn parse(number: &str) -> Option<Decimal> {
+        let (negative, number) = match number.strip_prefix('-') {
+            Some(number) => (true, number),
+            None => (false, number.strip_prefix('+').unwrap_or(number)),
+        };
+        let (mantissa, exponent) = match number.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (number, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let is_valid = !(integer.is_empty() && fraction.is_empty())
+            && integer
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|byte| byte.is_ascii_digit());
+        if !is_valid {
+            return None;
+        }
+
+        let digits = format!("{integer}{fraction}");
+        let significant = digits.trim_start_matches('0');
+        // every leading zero moves the decimal point one place to the right
+        let leading_zeros = i64::try_from(digits.len() - significant.len()).ok()?;
+        let exponent = exponent
+            .checked_add(i64::try_from(integer.len()).ok()?)?
+            .checked_sub(leading_zeros)?;
+        Some(Decimal {
+            negative,
+            digits: significant.trim_end_matches('0').to_string(),
+            exponent,
+        })
+    }
+
+    fn signum(&self) -> i8 {
+        match (self.digits.is_empty(), self.negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        }
+    }
+}
+
+fn number_to_json(n: &str) -> serde_json::Value {
+    serde_json::from_str::<serde_json::Number>(n).map_or_else(
+        |_| serde_json::Value::String(n.to_string()),
+        serde_json::Value::Number,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use aws_sdk_dynamodb::types::AttributeValue;
+
+    use super::compare;
+
+    fn compare_numbers(left: &str, right: &str) -> Option<Ordering> {
+        compare(
+            &AttributeValue::N(left.to_string()),
+            &AttributeValue::N(right.to_string()),
+        )
+    }
+
+    #[test]
+    fn test_numbers_compare_beyond_float_precision() {
+        assert_eq!(
+            compare_numbers(
+                "12345678901234567890123456789012345678",
+                "12345678901234567890123456789012345679"
+            ),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare_numbers("0.10000000000000000000000000000000000001", "0.1"),
+            Some(Ordering::Greater)
+        );
+    }
+
+    #[test]
+    fn test_numbers_compare_by_value() {
+        assert_eq!(compare_numbers("1.50", "1.5"), Some(Ordering::Equal));
+        assert_eq!(compare_numbers("007", "7"), Some(Ordering::Equal));
+        assert_eq!(compare_numbers("1.5", "1"), Some(Ordering::Greater));
+        assert_eq!(compare_numbers("1E+2", "99"), Some(Ordering::Greater));
+        assert_eq!(compare_numbers("1.5e2", "150"), Some(Ordering::Equal));
+        assert_eq!(compare_numbers("0.001", "0.01"), Some(Ordering::Less));
+        assert_eq!(compare_numbers("10", "9.99"), Some(Ordering::Greater));
+    }
+
+    #[test]
+    fn test_numbers_compare_by_sign() {
+        assert_eq!(compare_numbers("-1", "-2"), Some(Ordering::Greater));
+        assert_eq!(compare_numbers("-0.5", "0.1"), Some(Ordering::Less));
+        assert_eq!(compare_numbers("0", "-0.0"), Some(Ordering::Equal));
+        assert_eq!(compare_numbers("-100", "-99"), Some(Ordering::Less));
+    }
+
+    #[test]
+    fn test_invalid_numbers_and_mixed_types_do_not_compare() {
+        assert_eq!(compare_numbers("1.2.3", "1"), None);
+        assert_eq!(compare_numbers(".", "1"), None);
+        assert_eq!(
+            compare(
+                &AttributeValue::N("1".to_string()),
+                &AttributeValue::S("1".to_string())
+            ),
+            None
+        );
+        assert_eq!(
+            compare(
+                &AttributeValue::S("a".to_string()),
+                &AttributeValue::S("b".to_string())
+            ),
+            Some(Ordering::Less)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Scalar types the connector offers on top of the DynamoDB attribute types, for attributes
+//! whose values follow a format. Columns get them by overriding their type in the configuration.
+
+use super::database::TypeRepresentation;
+
+/// A scalar type held in string or number attributes of a known format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomScalarType {
+    /// An ISO-8601 date and time string in UTC to the second, `2024-01-05T10:30:00Z`. Only this
+    /// shape is accepted, so that values sort as their instants.
+    Timestamp,
+    /// A number of seconds since the Unix epoch.
+    EpochTimestamp,
+    /// An ISO-8601 date string, such as `2024-01-05`.
+    Date,
+    /// A UUID string, such as `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    Uuid,
+}
+
+impl CustomScalarType {
+    pub const ALL: [CustomScalarType; 4] = [
+        CustomScalarType::Timestamp,
+        CustomScalarType::EpochTimestamp,
+        CustomScalarType::Date,
+        CustomScalarType::Uuid,
+    ];
+
+    /// The custom scalar type of a name, if it is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scalar| scalar.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CustomScalarType::Timestamp => "Timestamp",
+            CustomScalarType::EpochTimestamp => "EpochTimestamp",
+            CustomScalarType::Date => "Date",
+            CustomScalarType::Uuid => "UUID",
+        }
+    }
+
+    /// The scalar type of the attributes holding its values, `String` or `Number`.
+    pub fn storage_type(self) -> &'static str {
+        match self {
+            CustomScalarType::Timestamp | CustomScalarType::Date | CustomScalarType::Uuid => {
+                "String"
+            }
+            CustomScalarType::EpochTimestamp => "Number",
+        }
+    }
+
+    pub fn type_representation(self) -> TypeRepresentation {
+        match self {
+            CustomScalarType::Timestamp => TypeRepresentation::Timestamptz,
+            CustomScalarType::EpochTimestamp => TypeRepresentation::Int64,
+            CustomScalarType::Date => TypeRepresentation::Date,
+            CustomScalarType::Uuid => TypeRepresentation::UUID,
+        }
+    }
+
+    /// Whether values can be ordered. Values of the accepted ISO-8601 shapes sort as their dates.
+    pub fn is_ordered(self) -> bool {
+        match self {
+            CustomScalarType::Timestamp
+            | CustomScalarType::EpochTimestamp
+            | CustomScalarType::Date => true,
+            CustomScalarType::Uuid => false,
+        }
+    }
+
+    /// Whether a string is a valid value of a type held in string attributes.
+    pub fn is_valid_string(self, value: &str) -> bool {
+        match self {
+            CustomScalarType::Timestamp => is_timestamp(value),
+            CustomScalarType::Date => is_date(value),
+            CustomScalarType::Uuid => is_uuid(value),
+            CustomScalarType::EpochTimestamp => false,
+        }
+    }
+
+    /// Whether a JSON value is a valid value of the type: null, a string of its format, or whole
+    /// seconds for an `EpochTimestamp`.
+    pub fn is_valid_value(self, value: &serde_json::Value) -> bool {
+        match value {
+            serde_json::Value::Null => true,
+            serde_json::Value::String(text) => self.is_valid_string(text),
+            serde_json::Value::Number(number) => {
+                self == CustomScalarType::EpochTimestamp && (number.is_i64() || number.is_u64())
+            }
+            serde_json::Value::Bool(_)
+            | serde_json::Value::Array(_)
+            | serde_json::Value::Object(_) => false,
+        }
+    }
+}
+
+/// `YYYY-MM-DD`, with a month and day in range.
+fn is_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if !value.is_ascii() || bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return false;
+    }
+    let (Some(_), Some(month), Some(day)) = (
+        digits(&value[0..4]),
+        digits(&value[5..7]),
+        digits(&value[8..10]),
+    ) else {
+        return false;
+    };
+    (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ`: UTC to the second, with an uppercase `T` and `Z`.
+fn is_timestamp(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if !value.is_ascii()
+        || bytes.len() != 20
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+        || bytes[19] != b'Z'
+        || !is_date(&value[0..10])
+    {
+        return false;
+    }
+    matches!(
+        (
+            digits(&value[11..13]),
+            digits(&value[14..16]),
+            digits(&value[17..19]),
+        ),
+        (Some(hour), Some(minute), Some(second)) if hour <= 23 && minute <= 59 && second <= 60
+    )
+}
+
+/// Five groups of 8, 4, 4, 4 and 12 hexadecimal digits separated by dashes.
+fn is_uuid(value: &str) -> bool {
+    let groups = value.split('-').collect::<Vec<_>>();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, length)| {
+            group.len() == length && group.bytes().all(|byte| byte.is_ascii_hexdigit())
+        })
+}
+
+/// The value of a string of ASCII digits.
+fn digits(value: &str) -> Option<u32> {
+    if value.bytes().all(|byte| byte.is_ascii_digit()) {
+        value.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CustomScalarType;
+
+    #[test]
+    fn test_custom_scalar_string_formats() {
+        for (scalar, value, is_valid) in [
+            (CustomScalarType::Timestamp, "2024-01-05T10:30:00Z", true),
+            (
+                CustomScalarType::Timestamp,
+                "2024-01-05T10:30:00.123+02:00",
+                false,
+            ),
+            (
+                CustomScalarType::Timestamp,
+                "2024-01-05T10:30:00.123Z",
+                false,
+            ),
+            (CustomScalarType::Timestamp, "2024-01-05T10:30:00", false),
+            (CustomScalarType::Timestamp, "2024-01-05 10:30:00Z", false),
+            (CustomScalarType::Timestamp, "2024-01-05t10:30:00z", false),
+            (CustomScalarType::Timestamp, "2024-01-05", false),
+            (CustomScalarType::Timestamp, "2024-01-05T25:30:00Z", false),
+            (
+                CustomScalarType::Timestamp,
+                "2024-01-05T10:30:00+02:00",
+                false,
+            ),
+            (CustomScalarType::Date, "2024-01-05", true),
+            (CustomScalarType::Date, "2024-13-05", false),
+            (CustomScalarType::Date, "2024-1-5", false),
+            (CustomScalarType::Date, "2024-01-0é", false),
+            (
+                CustomScalarType::Uuid,
+                "67e55044-10b1-426f-9247-bb680e5fe0c8",
+                true,
+            ),
+            (CustomScalarType::Uuid, "67e55044-10b1-426f-9247", false),
+            (
+                CustomScalarType::Uuid,
+                "67e55044-10b1-426f-9247-bb680e5fe0cg",
+                false,
+            ),
+        ] {
+            assert_eq!(
+                scalar.is_valid_string(value),
+                is_valid,
+                "{} '{value}'",
+                scalar.name()
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_scalar_json_values() {
+        for (scalar, value, is_valid) in [
+            (CustomScalarType::Timestamp, serde_json::Value::Null, true),
+            (
+                CustomScalarType::Timestamp,
+                serde_json::json!("2024-01-05T10:30:00Z"),
+                true,
+            ),
+            (
+                CustomScalarType::Timestamp,
+                serde_json::json!("today"),
+                false,
+            ),
+            (
+                CustomScalarType::Timestamp,
+                serde_json::json!(1_704_450_600),
+                false,
+            ),
+            (
+                CustomScalarType::EpochTimestamp,
+                serde_json::json!(1_704_450_600),
+                true,
+            ),
+            (
+                CustomScalarType::EpochTimestamp,
+                serde_json::json!(1.5),
+                false,
+            ),
+            (
+                CustomScalarType::EpochTimestamp,
+                serde_json::json!("1704450600"),
+                false,
+            ),
+            (CustomScalarType::Uuid, serde_json::json!(true), false),
+        ] {
+            assert_eq!(
+                scalar.is_valid_value(&value),
+                is_valid,
+                "{} {value}",
+                scalar.name()
+            );
+        }
+    }
+}
@@ -6,6 +6,9 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
+use super::entities::Entity;
+use super::key_templates::KeyTemplates;
+
 /// The scalar types supported by the Engine.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -16,6 +19,7 @@ pub struct ScalarTypeTypeName(pub String);
 #[serde(rename_all = "camelCase")]
 pub enum Type {
     ScalarType(models::ScalarTypeName),
+    CompositeType(models::ObjectTypeName),
     ArrayType(Box<Type>),
 }
 
@@ -39,6 +43,8 @@ pub struct ScalarType {
     // pub schema_name: Option<String>, // TODO(PY): see what happens when option is removed
     pub description: Option<String>,
     pub comparison_operators: BTreeMap<models::ComparisonOperatorName, ComparisonOperator>,
+    #[serde(default)]
+    pub aggregate_functions: BTreeMap<models::AggregateFunctionName, AggregateFunction>,
     pub type_representation: Option<TypeRepresentation>,
 }
 
@@ -59,8 +65,29 @@ impl ComparisonOperators {
     }
 }
 
+/// Map of all known composite types.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositeTypes(pub BTreeMap<models::ObjectTypeName, CompositeType>);
+
+impl CompositeTypes {
+    pub fn empty() -> Self {
+        CompositeTypes(BTreeMap::new())
+    }
+}
+
+/// Information about a composite type, the shape of a map attribute.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositeType {
+    pub type_name: String,
+    pub fields: BTreeMap<models::FieldName, FieldInfo>,
+    pub description: Option<String>,
+}
+
 /// Information about a composite type field.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct FieldInfo {
     pub field_name: String,
     pub r#type: Type,
@@ -77,13 +104,30 @@ pub struct ComparisonOperator {
     pub is_infix: bool,
 }
 
-/// Is it a built-in operator, or a custom operator.
+/// Is it a built-in operator, a custom infix operator, or one of the PartiQL functions
+/// DynamoDB offers for conditions.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum OperatorKind {
     Equal,
     In,
     Custom,
+    /// `begins_with(attribute, value)`
+    BeginsWith,
+    /// `contains(attribute, value)`
+    Contains,
+    /// `size(attribute) <operator_name> value`
+    Size,
+    /// `attribute_type(attribute, value)`
+    AttributeType,
+    /// `attribute BETWEEN low AND high`, with the bounds given as a two element array.
+    Between,
+    /// `attribute IS MISSING` when the boolean value is true, `attribute_not_exists` in
+    /// DynamoDB condition expressions.
+    IsMissing,
+    /// `attribute IS NOT MISSING` when the boolean value is true, `attribute_exists` in
+    /// DynamoDB condition expressions.
+    Exists,
 }
 
 /// Mapping from a "table" name to its information.
@@ -117,6 +161,86 @@ pub struct TableInfo {
     pub gsi: GlobalSecondaryIndexes,
 
     pub description: Option<String>,
+
+    /// The attribute holding the item version used for optimistic locking, if any.
+    /// Updates and deletes against the table must then give the version they expect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_attribute: Option<String>,
+
+    /// What to do with queries that DynamoDB would run as a full Scan of the table or index.
+    #[serde(default, skip_serializing_if = "ScanPolicy::is_default")]
+    pub scan_policy: ScanPolicy,
+
+    /// Foreign keys from attributes of the table to the key attributes of another table or of
+    /// one of its indexes. DynamoDB does not know about them, so they are declared by the user.
+    #[serde(default, skip_serializing_if = "ForeignRelations::is_empty")]
+    pub foreign_relations: ForeignRelations,
+
+    /// Templates of attributes whose values encode several values, such as `ORDER#{date}#{id}`,
+    /// by attribute name. Their placeholders are read and compared as fields of their own.
+    #[serde(default, skip_serializing_if = "KeyTemplates::is_empty")]
+    pub key_templates: KeyTemplates,
+
+    /// Attributes left out of the columns by the user. `update` does not add them back when it
+    /// finds them in the sampled items. Key attributes cannot be left out.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub excluded_columns: BTreeSet<models::FieldName>,
+
+    /// Set by `update` when introspection no longer finds the table. The table is kept so that
+    /// the user can check whether it was deleted, and left out of the schema.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+
+    /// The entity this table is read as, for the collections of entities. Set when the
+    /// configuration is loaded, never written.
+    #[serde(skip)]
+    pub entity: Option<Entity>,
+}
+
+impl TableInfo {
+    /// The collection, given the collection of the table, whose key the attributes are: the
+    /// table itself, or `{collection}:{index}` for one of its global secondary indexes. A key is
+    /// a partition key, with or without its sort key. The table comes before its indexes.
+    pub fn collection_keyed_on(
+        &self,
+        collection: &models::CollectionName,
+        attributes: &BTreeSet<&str>,
+    ) -> Option<models::CollectionName> {
+        let is_key = |partition_key: &str, sort_key: Option<&str>| {
+            *attributes == BTreeSet::from([partition_key])
+                || sort_key.is_some_and(|sort_key| {
+                    *attributes == BTreeSet::from([partition_key, sort_key])
+                })
+        };
+        let sort_key = Some(self.sort_key.as_str()).filter(|sort_key| !sort_key.is_empty());
+        if is_key(&self.partition_key, sort_key) {
+            return Some(collection.clone());
+        }
+        self.gsi
+            .0
+            .iter()
+            .find(|(_, index)| is_key(&index.partition_key, index.sort_key.as_deref()))
+            .map(|(index_name, _)| format!("{collection}:{index_name}").into())
+    }
+}
+
+/// How to handle queries which do not fix a partition key, and so scan the whole table or index.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanPolicy {
+    /// Run the scan.
+    #[default]
+    Allow,
+    /// Run the scan, logging a warning.
+    Warn,
+    /// Reject the query.
+    Deny,
+}
+
+impl ScanPolicy {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
 }
 
 /// Can this column contain null values
@@ -166,6 +290,10 @@ pub struct ColumnInfo {
     pub nullable: Nullable,
     #[serde(default)]
     pub description: Option<String>,
+    /// Set by `update` when the column is not found in the sampled items anymore. The column is
+    /// kept, as it may only be missing from the sample.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
 }
 
 /// A mapping from the name of a unique constraint to its value.
@@ -178,16 +306,23 @@ pub struct UniquenessConstraints(pub BTreeMap<String, UniquenessConstraint>);
 pub struct UniquenessConstraint(pub BTreeSet<models::FieldName>);
 
 /// A mapping from the name of a foreign key constraint to its value.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ForeignRelations(pub BTreeMap<String, ForeignRelation>);
 
+impl ForeignRelations {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
 /// A foreign key constraint.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
-
+#[serde(rename_all = "camelCase")]
 pub struct ForeignRelation {
-    pub foreign_schema: Option<String>,
-    pub foreign_table: String,
+    /// The collection of the table the foreign key refers to.
+    pub foreign_table: models::CollectionName,
+    /// The columns of the table, and the columns of the foreign table they refer to.
     pub column_mapping: BTreeMap<models::FieldName, models::FieldName>,
 }
 
@@ -222,10 +357,11 @@ impl AggregateFunctions {
     }
 }
 
+/// An aggregate function the connector computes over the values of a column.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AggregateFunction {
-    pub return_type: models::TypeName,
+    pub return_type: models::ScalarTypeName,
 }
 
 /// Type representation of scalar types, grouped by type.
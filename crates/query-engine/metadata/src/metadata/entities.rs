@@ -0,0 +1,64 @@
+//! Metadata information regarding the entities of single-table designs.
+
+use ndc_models as models;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::database::ColumnInfo;
+use super::key_templates::KeyTemplates;
+
+/// Mapping from an entity name to its information.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Entities(pub BTreeMap<models::CollectionName, EntityInfo>);
+
+impl Entities {
+    pub fn empty() -> Self {
+        Entities(BTreeMap::new())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Information about an entity: the items of a table holding many entity types, published as a
+/// collection of their own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityInfo {
+    /// The collection of the table holding the items of the entity.
+    pub table: models::CollectionName,
+    /// How the items of the entity are told apart from the other items of the table.
+    pub discriminator: Discriminator,
+    /// The attributes of the items of the entity.
+    pub columns: BTreeMap<models::FieldName, ColumnInfo>,
+    /// The prefixes of attribute values, such as `USER#`, by attribute name. They are stripped
+    /// from the values read, and added to the values compared with.
+    #[serde(default)]
+    pub key_prefixes: BTreeMap<String, String>,
+    /// Templates of attributes whose values encode several values, by attribute name.
+    #[serde(default, skip_serializing_if = "KeyTemplates::is_empty")]
+    pub key_templates: KeyTemplates,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// How the items of an entity are told apart from the other items of its table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Discriminator {
+    /// The items holding a value in an attribute, such as `type = 'order'`.
+    AttributeValue { attribute: String, value: String },
+    /// The items whose value of an attribute starts with its key prefix, such as
+    /// `begins_with(SK, 'ORDER#')`.
+    KeyPrefix { attribute: String },
+}
+
+/// The rules of an entity, kept with the table the connector reads it as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entity {
+    pub discriminator: Discriminator,
+    pub key_prefixes: BTreeMap<String, String>,
+}
@@ -0,0 +1,86 @@
+//! The names of the procedures and object types generated from the tables, which native
+//! mutations must not reuse.
+
+use std::collections::BTreeSet;
+
+use ndc_models as models;
+
+use super::database::{TableInfo, TablesInfo};
+
+/// The object type returned by batch write procedures.
+pub const BATCH_WRITE_RESULT_TYPE: &str = "batch_write_result";
+
+/// The object type describing the outcome of a single item in a batch write.
+pub const BATCH_WRITE_ITEM_RESULT_TYPE: &str = "batch_write_item_result";
+
+/// The name of the procedure inserting many items into a table.
+pub fn insert_many_procedure_name(
+    collection_name: &models::CollectionName,
+) -> models::ProcedureName {
+    format!("insert_{collection_name}_many").into()
+}
+
+/// The name of the procedure deleting many items by key from a table.
+pub fn delete_many_procedure_name(
+    collection_name: &models::CollectionName,
+) -> models::ProcedureName {
+    format!("delete_{collection_name}_many").into()
+}
+
+/// The name of the procedure updating a single item by key in a table.
+pub fn update_by_key_procedure_name(
+    collection_name: &models::CollectionName,
+) -> models::ProcedureName {
+    format!("update_{collection_name}_by_key").into()
+}
+
+/// The name of the procedure deleting a single item by key from a table.
+pub fn delete_by_key_procedure_name(
+    collection_name: &models::CollectionName,
+) -> models::ProcedureName {
+    format!("delete_{collection_name}_by_key").into()
+}
+
+/// The name of the object type describing the primary key of a table.
+pub fn key_object_type_name(collection_name: &models::CollectionName) -> models::ObjectTypeName {
+    format!("{collection_name}_key").into()
+}
+
+/// The name of the object type describing the attributes that can be set in an update.
+pub fn update_object_type_name(collection_name: &models::CollectionName) -> models::ObjectTypeName {
+    format!("{collection_name}_update").into()
+}
+
+/// The procedures generated for a table. Tables with a version attribute get no batch delete.
+pub fn generated_procedure_names(
+    collection_name: &models::CollectionName,
+    table: &TableInfo,
+) -> Vec<models::ProcedureName> {
+    let mut names = vec![insert_many_procedure_name(collection_name)];
+    if table.version_attribute.is_none() {
+        names.push(delete_many_procedure_name(collection_name));
+    }
+    names.push(update_by_key_procedure_name(collection_name));
+    names.push(delete_by_key_procedure_name(collection_name));
+    names
+}
+
+/// The object types generated for the procedures of the tables. Entities get no procedures.
+pub fn generated_object_type_names(tables: &TablesInfo) -> BTreeSet<models::ObjectTypeName> {
+    let mut names: BTreeSet<_> = tables
+        .0
+        .iter()
+        .filter(|(_, table)| table.entity.is_none())
+        .flat_map(|(collection_name, _)| {
+            [
+                key_object_type_name(collection_name),
+                update_object_type_name(collection_name),
+            ]
+        })
+        .collect();
+    if !names.is_empty() {
+        names.insert(BATCH_WRITE_RESULT_TYPE.into());
+        names.insert(BATCH_WRITE_ITEM_RESULT_TYPE.into());
+    }
+    names
+}
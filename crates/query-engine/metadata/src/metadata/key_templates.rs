@@ -0,0 +1,140 @@
+//! Metadata information regarding key templates: attributes whose values encode several values,
+//! such as `ORDER#2024-01-05#1234`.
+
+use ndc_models as models;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Mapping from an attribute name to the template of its values.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyTemplates(pub BTreeMap<String, KeyTemplate>);
+
+impl KeyTemplates {
+    pub fn empty() -> Self {
+        KeyTemplates(BTreeMap::new())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Find the attribute and template holding a virtual field.
+    pub fn lookup_field(
+        &self,
+        field_name: &models::FieldName,
+    ) -> Option<(&String, &KeyTemplate, &KeyTemplateField)> {
+        self.0.iter().find_map(|(attribute, key_template)| {
+            key_template
+                .fields
+                .get(field_name)
+                .map(|field| (attribute, key_template, field))
+        })
+    }
+}
+
+/// The template of the values of an attribute, whose placeholders are published as virtual
+/// fields of the collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyTemplate {
+    /// The values of the attribute, with a `{field}` placeholder for each virtual field, such as
+    /// `ORDER#{orderDate}#{orderId}`.
+    pub template: String,
+    /// The virtual fields of the placeholders.
+    pub fields: BTreeMap<models::FieldName, KeyTemplateField>,
+}
+
+/// A virtual field of a key template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyTemplateField {
+    /// The scalar type of the values of the field, `String` or `Number`.
+    pub r#type: models::ScalarTypeName,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl KeyTemplate {
+    /// Split the template into text and the fields of its placeholders.
+    pub fn parts(&self) -> Vec<KeyTemplatePart> {
+        parse_key_template(&self.template)
+    }
+}
+
+/// A part of a key template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyTemplatePart {
+    /// Text of the values.
+    Text(String),
+    /// The name of the field of a `{field}` placeholder.
+    Field(models::FieldName),
+}
+
+/// Split a template into text and the fields of its `{field}` placeholders.
+/// Braces that do not close stay text.
+pub fn parse_key_template(template: &str) -> Vec<KeyTemplatePart> {
+    let mut parts = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start + 1..].find('}') else {
+            break;
+        };
+        if start > 0 {
+            parts.push(KeyTemplatePart::Text(rest[..start].to_string()));
+        }
+        parts.push(KeyTemplatePart::Field(models::FieldName::new(
+            rest[start + 1..start + 1 + end].trim().into(),
+        )));
+        rest = &rest[start + 1 + end + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(KeyTemplatePart::Text(rest.to_string()));
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_key_template, KeyTemplatePart};
+
+    fn text(text: &str) -> KeyTemplatePart {
+        KeyTemplatePart::Text(text.to_string())
+    }
+
+    fn field(name: &str) -> KeyTemplatePart {
+        KeyTemplatePart::Field(name.into())
+    }
+
+    #[test]
+    fn test_parse_placeholders() {
+        assert_eq!(
+            parse_key_template("ORDER#{orderDate}#{ orderId }"),
+            vec![
+                text("ORDER#"),
+                field("orderDate"),
+                text("#"),
+                field("orderId"),
+            ]
+        );
+        assert_eq!(
+            parse_key_template("{userId}#PROFILE"),
+            vec![field("userId"), text("#PROFILE")]
+        );
+    }
+
+    #[test]
+    fn test_parse_without_placeholders() {
+        assert_eq!(parse_key_template("PROFILE"), vec![text("PROFILE")]);
+        assert_eq!(parse_key_template(""), vec![]);
+    }
+
+    #[test]
+    fn test_parse_unclosed_braces_stay_text() {
+        assert_eq!(
+            parse_key_template("ORDER#{orderId}#{date"),
+            vec![text("ORDER#"), field("orderId"), text("#{date")]
+        );
+    }
+}
@@ -1,9 +1,21 @@
 //! Metadata information regarding the database and tracked information.
 
+pub mod custom_scalars;
 pub mod database;
+pub mod entities;
+pub mod generated_mutations;
+pub mod key_templates;
+pub mod native_mutations;
+pub mod native_queries;
 
 // re-export without modules
+pub use custom_scalars::*;
 pub use database::*;
+pub use entities::*;
+pub use generated_mutations::*;
+pub use key_templates::*;
+pub use native_mutations::*;
+pub use native_queries::*;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +25,14 @@ use serde::{Deserialize, Serialize};
 pub struct Metadata {
     pub tables: TablesInfo,
     pub scalar_types: ScalarTypes,
+    #[serde(default)]
+    pub composite_types: CompositeTypes,
+    #[serde(default, skip_serializing_if = "NativeQueries::is_empty")]
+    pub native_queries: NativeQueries,
+    #[serde(default, skip_serializing_if = "NativeMutations::is_empty")]
+    pub native_mutations: NativeMutations,
+    #[serde(default, skip_serializing_if = "Entities::is_empty")]
+    pub entities: Entities,
 }
 
 impl Metadata {
@@ -20,6 +40,10 @@ impl Metadata {
         Metadata {
             tables: TablesInfo::empty(),
             scalar_types: ScalarTypes::empty(),
+            composite_types: CompositeTypes::empty(),
+            native_queries: NativeQueries::empty(),
+            native_mutations: NativeMutations::empty(),
+            entities: Entities::empty(),
         }
     }
 }
@@ -0,0 +1,48 @@
+//! Metadata information regarding native mutations.
+
+use ndc_models as models;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::database::ColumnInfo;
+use super::native_queries::{parse_native_query, NativeQueryPart};
+
+/// Mapping from a native mutation name to its information.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeMutations(pub BTreeMap<models::ProcedureName, NativeMutationInfo>);
+
+impl NativeMutations {
+    pub fn empty() -> Self {
+        NativeMutations(BTreeMap::new())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Information about a native mutation: a PartiQL INSERT, UPDATE or DELETE statement written by
+/// the user, published as a procedure whose arguments are the parameters of the statement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeMutationInfo {
+    /// The PartiQL statement, with `{{argument}}` placeholders for its arguments.
+    pub sql: String,
+    /// The attributes of the item the statement returns with a `RETURNING` clause.
+    #[serde(default)]
+    pub columns: BTreeMap<models::FieldName, ColumnInfo>,
+    /// The arguments of the statement, bound as parameters when it runs.
+    #[serde(default)]
+    pub arguments: BTreeMap<models::ArgumentName, ColumnInfo>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl NativeMutationInfo {
+    /// Split the statement into text and the arguments of its placeholders.
+    pub fn parts(&self) -> Vec<NativeQueryPart> {
+        parse_native_query(&self.sql)
+    }
+}
@@ -0,0 +1,145 @@
+//! Metadata information regarding native queries.
+
+use ndc_models as models;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::database::ColumnInfo;
+
+/// Mapping from a native query name to its information.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeQueries(pub BTreeMap<models::CollectionName, NativeQueryInfo>);
+
+impl NativeQueries {
+    pub fn empty() -> Self {
+        NativeQueries(BTreeMap::new())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Information about a native query: a PartiQL SELECT statement written by the user, published
+/// as a collection whose arguments are the parameters of the statement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeQueryInfo {
+    /// The PartiQL statement, with `{{argument}}` placeholders for its arguments.
+    pub sql: String,
+    /// The attributes of the items the statement returns.
+    pub columns: BTreeMap<models::FieldName, ColumnInfo>,
+    /// The arguments of the statement, bound as parameters when it runs.
+    #[serde(default)]
+    pub arguments: BTreeMap<models::ArgumentName, ColumnInfo>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl NativeQueryInfo {
+    /// Split the statement into text and the arguments of its placeholders.
+    pub fn parts(&self) -> Vec<NativeQueryPart> {
+        parse_native_query(&self.sql)
+    }
+}
+
+/// A part of a native query statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativeQueryPart {
+    /// Text of the statement.
+    Text(String),
+    /// The name of the argument of a `{{argument}}` placeholder.
+    Parameter(String),
+}
+
+/// Split a statement into text and the arguments of its `{{argument}}` placeholders.
+/// Braces that do not close stay text.
+pub fn parse_native_query(sql: &str) -> Vec<NativeQueryPart> {
+    let mut parts = vec![];
+    let mut rest = sql;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        if start > 0 {
+            parts.push(NativeQueryPart::Text(rest[..start].to_string()));
+        }
+        parts.push(NativeQueryPart::Parameter(
+            rest[start + 2..start + 2 + end].trim().to_string(),
+        ));
+        rest = &rest[start + 2 + end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(NativeQueryPart::Text(rest.to_string()));
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_native_query, NativeQueryPart};
+
+    fn text(text: &str) -> NativeQueryPart {
+        NativeQueryPart::Text(text.to_string())
+    }
+
+    fn parameter(name: &str) -> NativeQueryPart {
+        NativeQueryPart::Parameter(name.to_string())
+    }
+
+    #[test]
+    fn test_parse_placeholders() {
+        assert_eq!(
+            parse_native_query("SELECT * FROM users WHERE id = {{id}} AND age > {{ age }}"),
+            vec![
+                text("SELECT * FROM users WHERE id = "),
+                parameter("id"),
+                text(" AND age > "),
+                parameter("age"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_adjacent_placeholders() {
+        assert_eq!(
+            parse_native_query("{{a}}{{b}}"),
+            vec![parameter("a"), parameter("b")]
+        );
+    }
+
+    #[test]
+    fn test_parse_without_placeholders() {
+        assert_eq!(
+            parse_native_query("SELECT * FROM users"),
+            vec![text("SELECT * FROM users")]
+        );
+        assert_eq!(parse_native_query(""), vec![]);
+    }
+
+    #[test]
+    fn test_parse_unclosed_braces_stay_text() {
+        assert_eq!(
+            parse_native_query("SELECT * FROM users WHERE id = {{id"),
+            vec![text("SELECT * FROM users WHERE id = {{id")]
+        );
+        assert_eq!(
+            parse_native_query("WHERE a = {{a}} AND b = {{b"),
+            vec![text("WHERE a = "), parameter("a"), text(" AND b = {{b")]
+        );
+    }
+
+    #[test]
+    fn test_parse_single_braces_stay_text() {
+        assert_eq!(
+            parse_native_query("WHERE address = {'city': {{city}}}"),
+            vec![
+                text("WHERE address = {'city': "),
+                parameter("city"),
+                text("}"),
+            ]
+        );
+    }
+}
@@ -2,6 +2,9 @@
 
 use std::collections::BTreeMap;
 
+use super::execution_plan::AttributeType;
+use query_engine_metadata::metadata::CustomScalarType;
+
 /// An EXPLAIN clause
 #[derive(Debug, Clone, PartialEq)]
 pub enum Explain<'a> {
@@ -139,6 +142,8 @@ pub enum From {
         alias: TableAlias,
         column: ColumnAlias,
     },
+    /// Select from a native query, given as raw PartiQL with its arguments as expressions.
+    NativeQuery { sql: Vec<RawSql>, alias: TableAlias },
 }
 
 /// A JOIN clause
@@ -259,6 +264,12 @@ pub enum Expression {
         operator: BinaryArrayOperator,
         right: Vec<Expression>,
     },
+    /// A BETWEEN clause, inclusive of both bounds
+    Between {
+        expression: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+    },
     /// An unary operation on a scalar expression
     UnaryOperation {
         expression: Box<Expression>,
@@ -273,6 +284,9 @@ pub enum Expression {
     Exists {
         select: Box<Select>,
     },
+    /// The condition of a semi-join of the execution plan, by its index. The executor replaces it
+    /// with the join keys of the matching target items before running the statement.
+    SemiJoin(usize),
     /// A json_build_object function call
     JsonBuildObject(BTreeMap<String, Expression>),
     // SELECT queries can appear in a select list if they return
@@ -298,6 +312,11 @@ pub enum Expression {
         expression: Box<Expression>,
         nested_field: NestedField,
     },
+    /// An element of a list, by its index.
+    NestedElementSelect {
+        expression: Box<Expression>,
+        index: u32,
+    },
     JoinExpressions(Vec<Expression>),
     SafeOffSet {
         offset: i32,
@@ -321,6 +340,8 @@ pub struct NestedField(pub String);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UnaryOperator {
     IsNull,
+    /// The attribute is absent from the item, as opposed to holding NULL.
+    IsMissing,
 }
 
 /// Represents the name of a binary operator.
@@ -341,6 +362,10 @@ pub enum Function {
     JsonbPopulateRecord,
     ArrayAgg,
     Unnest,
+    BeginsWith,
+    Contains,
+    AttributeType,
+    Size,
     Unknown(String),
     SafeOffSet(String),
 }
@@ -365,7 +390,22 @@ pub enum Value {
     Array(Vec<Value>),
     JsonValue(serde_json::Value),
     EmptyJsonArray,
-    Variable(String),
+    /// A variable of the request, bound when the statement runs with each variable set.
+    Variable {
+        name: String,
+        attribute_type: AttributeType,
+        /// The key prefix to add to its string values, when compared with a prefixed attribute.
+        key_prefix: Option<String>,
+        /// The custom scalar type whose format its values must have.
+        custom_scalar: Option<CustomScalarType>,
+    },
+    /// A join key value of a batch of relationship lookups, by position.
+    BatchKey(usize),
+    /// A value bound as a parameter of the given attribute type.
+    Typed {
+        value: serde_json::Value,
+        attribute_type: AttributeType,
+    },
 }
 
 /// Scalar type
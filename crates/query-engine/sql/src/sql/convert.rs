@@ -108,6 +108,18 @@ impl SelectList {
 
 impl Select {
     pub fn to_sql(&self, sql: &mut SQL) {
+        // DynamoDB cannot select from a subquery, so a select from a native query runs the native
+        // query as written, and the executor picks the requested fields from its items.
+        if let Some(From::NativeQuery {
+            sql: native_query, ..
+        }) = &self.from
+        {
+            for item in native_query {
+                item.to_sql(sql);
+            }
+            return;
+        }
+
         self.with.to_sql(sql);
 
         sql.append_syntax("SELECT ");
@@ -304,6 +316,16 @@ impl From {
                 column.to_sql(sql);
                 sql.append_syntax(")");
             }
+            From::NativeQuery {
+                sql: native_query,
+                alias: _,
+            } => {
+                sql.append_syntax("(");
+                for item in native_query {
+                    item.to_sql(sql);
+                }
+                sql.append_syntax(")");
+            }
             From::Unnest {
                 expression,
                 alias,
@@ -430,17 +452,31 @@ impl Expression {
                 {
                     left.to_sql(sql);
                     operator.to_sql(sql);
-                    sql.append_syntax("(");
+                    // PartiQL lists are in square brackets
+                    sql.append_syntax("[");
                     for (index, item) in right.iter().enumerate() {
                         item.to_sql(sql);
                         if index < (right.len() - 1) {
                             sql.append_syntax(", ");
                         }
                     }
-                    sql.append_syntax(")");
+                    sql.append_syntax("]");
                 }
                 sql.append_syntax(")");
             }
+            Expression::Between {
+                expression,
+                low,
+                high,
+            } => {
+                sql.append_syntax("(");
+                expression.to_sql(sql);
+                sql.append_syntax(" BETWEEN ");
+                low.to_sql(sql);
+                sql.append_syntax(" AND ");
+                high.to_sql(sql);
+                sql.append_syntax(")");
+            }
             Expression::UnaryOperation {
                 expression,
                 operator,
@@ -478,6 +514,9 @@ impl Expression {
                 select.to_sql(sql);
                 sql.append_syntax(")");
             }
+            Expression::SemiJoin(index) => {
+                sql.append_syntax(&format!("SEMI_JOIN({index})"));
+            }
             Expression::JsonBuildObject(map) => {
                 sql.append_syntax("JSON_OBJECT");
                 sql.append_syntax("(");
@@ -524,15 +563,19 @@ impl Expression {
                 select.to_sql(sql);
                 sql.append_syntax(")");
             }
+            // PartiQL document paths, such as "address"."city"
             Expression::NestedFieldSelect {
                 expression,
                 nested_field,
             } => {
-                sql.append_syntax("(");
                 expression.to_sql(sql);
-                sql.append_syntax(")");
                 sql.append_syntax(".");
                 nested_field.to_sql(sql);
+            }
+            // and "tags"[0]
+            Expression::NestedElementSelect { expression, index } => {
+                expression.to_sql(sql);
+                sql.append_syntax(format!("[{index}]").as_str());
             } // Expression::JsonQuery(target, path) => {
               //     sql.append_syntax("JSON_QUERY");
               //     sql.append_syntax("(");
@@ -571,6 +614,7 @@ impl UnaryOperator {
     pub fn to_sql(&self, sql: &mut SQL) {
         match self {
             UnaryOperator::IsNull => sql.append_syntax(" IS NULL "),
+            UnaryOperator::IsMissing => sql.append_syntax(" IS MISSING "),
         }
     }
 }
@@ -605,6 +649,10 @@ impl Function {
             Function::JsonbPopulateRecord => sql.append_syntax("jsonb_populate_record"),
             Function::ArrayAgg => sql.append_syntax("ARRAY_AGG"),
             Function::Unnest => sql.append_syntax("unnest"),
+            Function::BeginsWith => sql.append_syntax("begins_with"),
+            Function::Contains => sql.append_syntax("contains"),
+            Function::AttributeType => sql.append_syntax("attribute_type"),
+            Function::Size => sql.append_syntax("size"),
             Function::Unknown(name) => sql.append_syntax(name),
             Function::SafeOffSet(index) => {
                 sql.append_syntax(format!("[SAFE_OFFSET({index})]").as_str());
@@ -632,9 +680,27 @@ impl Value {
             Value::EmptyJsonArray => sql.append_syntax("'[]'"),
             Value::Int8(i) => sql.append_syntax(format!("{i}").as_str()),
             Value::Float8(n) => sql.append_syntax(format!("{n}").as_str()),
-            Value::Character(s) | Value::String(s) => sql.append_syntax(format!("'{s}'").as_str()),
-            // Value::Character(s) | Value::String(s) => sql.append_param(Param::String(s.clone())),
-            Value::Variable(v) => sql.append_param(Param::Variable(v.clone())),
+            // strings are bound, as PartiQL has no escape for the quotes of a string literal
+            Value::Character(s) | Value::String(s) => sql.append_param(Param::String(s.clone())),
+            Value::Variable {
+                name,
+                attribute_type,
+                key_prefix,
+                custom_scalar,
+            } => sql.append_param(Param::Variable {
+                name: name.clone(),
+                attribute_type: *attribute_type,
+                key_prefix: key_prefix.clone(),
+                custom_scalar: *custom_scalar,
+            }),
+            Value::BatchKey(index) => sql.append_param(Param::BatchKey(*index)),
+            Value::Typed {
+                value,
+                attribute_type,
+            } => sql.append_param(Param::Typed {
+                value: value.clone(),
+                attribute_type: *attribute_type,
+            }),
             Value::Bool(true) => sql.append_syntax("true"),
             Value::Bool(false) => sql.append_syntax("false"),
             Value::Null => sql.append_syntax("null"),
@@ -17,7 +17,7 @@ pub struct ExecutionPlan<Query> {
 }
 
 /// The query we want to run with some additional information.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Query {
     /// The root field name of the top-most collection.
     pub root_field: models::CollectionName,
@@ -27,6 +27,194 @@ pub struct Query {
     pub query: sql::ast::Select,
     /// Query limit
     pub limit: Option<u32>,
+    /// How many matched items to skip before the first row.
+    pub offset: Option<u32>,
+    /// An opaque token from a previous page of results, to resume reading from where it ended
+    /// instead of reading and skipping the items before it again.
+    pub continuation_token: Option<String>,
+    /// How DynamoDB will run the query.
+    pub access_pattern: AccessPattern,
+    /// How to build the rowset from the items the query reads.
+    pub rowset: Rowset,
+    /// The nested document paths the statement reads without their top-level attribute.
+    /// DynamoDB may return them as attributes named after their last step.
+    pub document_paths: Vec<Vec<String>>,
+    /// The ordering the executor applies to the items before building the rowset.
+    /// Empty when DynamoDB returns the items in the requested order.
+    pub order_by: Vec<OrderByElement>,
+    /// The semi-joins of the statements of the plan, by the index of their placeholders.
+    /// Only the root query of a plan has them.
+    pub semi_joins: Vec<SemiJoin>,
+}
+
+/// An attribute, or a field nested in maps of an attribute, the executor sorts the items by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderByElement {
+    pub attribute: String,
+    /// The fields of nested maps leading to the value, empty for the attribute itself.
+    pub field_path: Vec<String>,
+    pub direction: sql::ast::OrderByDirection,
+}
+
+/// The rows and aggregates to compute from the items read by a query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rowset {
+    /// The fields of each row, by alias. `None` when no rows were requested.
+    pub fields: Option<Vec<(String, Field)>>,
+    /// The aggregates, by alias. `None` when no aggregates were requested.
+    pub aggregates: Option<Vec<(String, Aggregate)>>,
+}
+
+/// A field of a returned row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Field {
+    /// The value of an attribute, or only some fields of it when nested fields were selected.
+    Column {
+        attribute: String,
+        fields: Option<NestedField>,
+    },
+    /// The value of an attribute of an entity holding a key prefix, with the prefix stripped.
+    PrefixedColumn {
+        attribute: String,
+        key_prefix: String,
+    },
+    /// A virtual field of a key template, split from the value of its attribute. Null when the
+    /// value does not have the shape of the template.
+    KeyTemplateField {
+        attribute: String,
+        template: Vec<KeyTemplatePart>,
+        field: String,
+        /// Whether the field is read as a number.
+        is_number: bool,
+    },
+    /// An opaque token to continue the query after this row.
+    Cursor,
+    /// The rowset of a relationship, read for every row of the query.
+    Relationship(Box<RelationshipQuery>),
+}
+
+/// A part of a key template: text, or the placeholder of a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyTemplatePart {
+    Text(String),
+    Field(String),
+}
+
+/// The query of a relationship field. The executor runs it for the rows of the source query in
+/// batches, reading the target items whose join attributes have the values of the source items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelationshipQuery {
+    /// The query on the target collection, without the join condition. Its limit, offset and
+    /// ordering apply to the target items of each source item.
+    pub query: Query,
+    /// The attributes the source and target items are joined on.
+    pub join: Vec<JoinColumn>,
+}
+
+/// An attribute of the source items, and the attribute of the target items it must be equal to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinColumn {
+    pub source: String,
+    pub target: String,
+    /// A reference to the source attribute in the statement of the source query.
+    pub source_column: sql::ast::Expression,
+    /// A reference to the target attribute in the statement of the query.
+    pub target_column: sql::ast::Expression,
+}
+
+/// An `exists` filter across a relationship. DynamoDB cannot run subqueries, so the executor
+/// first reads the join keys of the target items that satisfy the predicate, and then filters
+/// the source items on them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SemiJoin {
+    /// The statement reading the join attributes of the matching target items.
+    pub query: sql::ast::Select,
+    /// How DynamoDB will run the statement.
+    pub access_pattern: AccessPattern,
+    /// The attributes the source and target items are joined on.
+    pub join: Vec<JoinColumn>,
+}
+
+/// The fields selected from a map or a list value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NestedField {
+    /// The fields of a map, by alias.
+    Object(Vec<(String, Field)>),
+    /// The fields of each element of a list.
+    Array(Box<NestedField>),
+}
+
+/// An aggregate computed by the connector over the items read by a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aggregate {
+    /// The number of items.
+    StarCount,
+    /// The number of items with a non-null value for the attribute.
+    ColumnCount { attribute: String, distinct: bool },
+    /// A function of the non-null values of the attribute.
+    SingleColumn {
+        attribute: String,
+        function: AggregateFunction,
+    },
+}
+
+/// The aggregate functions the connector knows how to compute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Min,
+    Max,
+    Sum,
+    Avg,
+}
+
+impl AggregateFunction {
+    /// Find an aggregate function by the name it is published under.
+    pub fn from_name(name: &str) -> Option<AggregateFunction> {
+        match name {
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            "sum" => Some(AggregateFunction::Sum),
+            "avg" => Some(AggregateFunction::Avg),
+            _ => None,
+        }
+    }
+}
+
+/// How DynamoDB runs a PartiQL SELECT statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessPattern {
+    /// The whole primary key is fixed by equalities, so at most one item is read.
+    KeyLookup { table: String },
+    /// The partition key is fixed, so DynamoDB runs a Query on the table or one of its indexes.
+    Query {
+        table: String,
+        index: Option<String>,
+    },
+    /// Nothing fixes the partition key, so DynamoDB reads the whole table or index.
+    Scan {
+        table: String,
+        index: Option<String>,
+        /// The attributes which would let DynamoDB run a Query instead. Those of another index
+        /// or of the table are followed by the collection to query, as in
+        /// `email (users:by_email)`.
+        suggested_keys: Vec<String>,
+        /// What the executor does before running the statement.
+        policy: ScanPolicy,
+    },
+    /// A native query, run as written by the user.
+    NativeQuery { name: String },
+}
+
+/// What the executor does with a statement DynamoDB runs as a full Scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanPolicy {
+    /// Run the statement.
+    #[default]
+    Allow,
+    /// Run the statement, logging a warning.
+    Warn,
+    /// Fail before the statement is sent to DynamoDB.
+    Deny,
 }
 
 impl Query {
@@ -61,12 +249,120 @@ pub fn explain_to_sql(explain: &sql::ast::Explain) -> sql::string::SQL {
     sql
 }
 
+/// A mutation we want to run, with some additional information.
+#[derive(Debug)]
+pub struct Mutation {
+    /// The name of the procedure being invoked.
+    pub procedure_name: models::ProcedureName,
+    /// The write operation.
+    pub operation: MutationOperation,
+    /// The fields requested from the procedure result.
+    pub fields: Option<models::NestedField>,
+}
+
+/// A write operation against a DynamoDB table.
+#[derive(Debug)]
+pub enum MutationOperation {
+    /// Write a set of items using `BatchWriteItem`.
+    /// The requests are chunked by the executor, and each item reports its own result.
+    BatchWrite {
+        table_name: String,
+        /// The names of the primary key attributes of the table.
+        key_attributes: Vec<String>,
+        requests: Vec<WriteRequest>,
+    },
+    /// Insert items one at a time using `PutItem`, each only if no item has its key yet.
+    /// Used on tables with a version attribute, where a batch write would overwrite existing
+    /// items without checking their version. Each item reports its own result.
+    PutItems {
+        table_name: String,
+        /// The names of the primary key attributes of the table.
+        key_attributes: Vec<String>,
+        items: Vec<BTreeMap<String, TypedValue>>,
+        expression: WriteExpression,
+    },
+    /// Update a single item using `UpdateItem`, returning the updated item.
+    UpdateItem {
+        table_name: String,
+        key: BTreeMap<String, TypedValue>,
+        expression: WriteExpression,
+        version: Option<VersionCheck>,
+    },
+    /// Delete a single item using `DeleteItem`, returning the deleted item.
+    DeleteItem {
+        table_name: String,
+        key: BTreeMap<String, TypedValue>,
+        expression: WriteExpression,
+        version: Option<VersionCheck>,
+    },
+    /// Run a native mutation using `ExecuteStatement`, returning the item of its `RETURNING`
+    /// clause if it has one.
+    ExecuteStatement {
+        /// The PartiQL statement, with a `?` placeholder for each parameter.
+        statement: String,
+        parameters: Vec<TypedValue>,
+    },
+}
+
+/// The expressions of a single item write, with their placeholders.
+#[derive(Debug, Clone, Default)]
+pub struct WriteExpression {
+    pub update_expression: Option<String>,
+    pub condition_expression: Option<String>,
+    /// Maps `#name` placeholders to attribute names.
+    pub attribute_names: BTreeMap<String, String>,
+    /// Maps `:value` placeholders to values.
+    pub attribute_values: BTreeMap<String, TypedValue>,
+}
+
+/// The version an item is expected to have for a write to go through.
+/// The check itself is part of the condition expression; this is used to report conflicts.
+#[derive(Debug, Clone)]
+pub struct VersionCheck {
+    pub attribute: String,
+    pub expected: serde_json::Value,
+}
+
+/// A single put or delete in a batch write.
+#[derive(Debug, Clone)]
+pub enum WriteRequest {
+    Put { item: BTreeMap<String, TypedValue> },
+    Delete { key: BTreeMap<String, TypedValue> },
+}
+
+/// A json value together with the DynamoDB attribute type it should be written as.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedValue {
+    pub value: serde_json::Value,
+    pub attribute_type: AttributeType,
+}
+
+/// The DynamoDB attribute types we know how to write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeType {
+    String,
+    Number,
+    Binary,
+    Boolean,
+    List,
+    Map,
+    /// Infer the attribute type from the json value.
+    Any,
+}
+
 /// A simple query execution plan with only a root field and a query.
+#[allow(clippy::too_many_arguments)]
 pub fn simple_query_execution_plan(
     variables: Option<Vec<BTreeMap<models::VariableName, serde_json::Value>>>,
     root_field: models::CollectionName,
     query: sql::ast::Select,
     limit: Option<u32>,
+    offset: Option<u32>,
+    continuation_token: Option<String>,
+    access_pattern: AccessPattern,
+    rowset: Rowset,
+    document_paths: Vec<Vec<String>>,
+    order_by: Vec<OrderByElement>,
 ) -> ExecutionPlan<Query> {
     ExecutionPlan {
         pre: vec![],
@@ -75,7 +371,23 @@ pub fn simple_query_execution_plan(
             variables,
             query,
             limit,
+            offset,
+            continuation_token,
+            access_pattern,
+            rowset,
+            document_paths,
+            order_by,
+            semi_joins: vec![],
         },
         post: vec![],
     }
 }
+
+/// A mutation execution plan running each of the mutations in order.
+pub fn simple_mutations_execution_plan(mutations: Vec<Mutation>) -> ExecutionPlan<Vec<Mutation>> {
+    ExecutionPlan {
+        pre: vec![],
+        query: mutations,
+        post: vec![],
+    }
+}
@@ -139,11 +139,9 @@ pub enum ResultsKind {
 /// combine them into one Select.
 pub fn select_rowset(select_set: SelectSet, returns_field: &ReturnsFields) -> Select {
     match select_set {
+        // The executor builds the rows and the aggregates from the items the select reads.
         SelectSet::Rows(row_select) => match returns_field {
-            ReturnsFields::FieldsWereRequested => row_select,
-            ReturnsFields::NoFieldsWereRequested => {
-                todo!("not supported yet")
-            }
+            ReturnsFields::FieldsWereRequested | ReturnsFields::NoFieldsWereRequested => row_select,
         },
     }
 }
@@ -171,6 +171,7 @@ pub fn normalize_expr(expr: Expression) -> Expression {
         Expression::Exists { select } => Expression::Exists {
             select: Box::new(normalize_select(*select)),
         },
+        Expression::SemiJoin(index) => Expression::SemiJoin(index),
         // reverse not on literal bool.
         Expression::Not(expr) => match normalize_expr(*expr) {
             Expression::Value(Value::Bool(false)) => Expression::Value(Value::Bool(true)),
@@ -199,6 +200,16 @@ pub fn normalize_expr(expr: Expression) -> Expression {
             right: right.into_iter().map(normalize_expr).collect(),
         },
         // Apply inner
+        Expression::Between {
+            expression,
+            low,
+            high,
+        } => Expression::Between {
+            expression: Box::new(normalize_expr(*expression)),
+            low: Box::new(normalize_expr(*low)),
+            high: Box::new(normalize_expr(*high)),
+        },
+        // Apply inner
         Expression::UnaryOperation {
             expression,
             operator,
@@ -242,6 +253,11 @@ pub fn normalize_expr(expr: Expression) -> Expression {
             expression: Box::new(normalize_expr(*expression)),
             nested_field,
         },
+        // Apply inner
+        Expression::NestedElementSelect { expression, index } => Expression::NestedElementSelect {
+            expression: Box::new(normalize_expr(*expression)),
+            index,
+        },
         // Nothing to do.
         Expression::RowToJson(_)
         | Expression::ColumnReference(_)
@@ -1,5 +1,8 @@
 //! Type definitions of a low-level SQL string representation.
 
+use super::execution_plan::AttributeType;
+use query_engine_metadata::metadata::CustomScalarType;
+
 /// A low-level builder for SQL.
 #[derive(Debug, PartialEq, Eq)]
 pub struct SQL {
@@ -20,8 +23,22 @@ pub enum Param {
     String(String),
     /// A JSON value
     Value(serde_json::Value),
-    /// A variable name to look up in the `variables` field in a `QueryRequest`.
-    Variable(String),
+    /// A variable name to look up in the `variables` field in a `QueryRequest`, bound as the
+    /// attribute type of the value it is compared with.
+    Variable {
+        name: String,
+        attribute_type: AttributeType,
+        key_prefix: Option<String>,
+        custom_scalar: Option<CustomScalarType>,
+    },
+    /// A join key value of a batch of relationship lookups, by position, bound as it was read
+    /// from the source item.
+    BatchKey(usize),
+    /// A JSON value bound as the given attribute type.
+    Typed {
+        value: serde_json::Value,
+        attribute_type: AttributeType,
+    },
 }
 
 /// A DDL statement.
@@ -49,14 +66,11 @@ impl SQL {
         // todo: sanitize
         self.sql.push_str(sql.to_string().as_str());
     }
-    /// Append a parameter to a parameterized query. Will be represented as $1, $2, and so on,
-    /// in the sql query text, and will be inserted to the `params` vector, so we can
-    /// bind them later when we run the query.
+    /// Append a parameter to a parameterized query. Will be represented as a `?` placeholder
+    /// in the PartiQL statement, and will be inserted to the `params` vector, so we can
+    /// bind them by position when we run the query.
     pub fn append_param(&mut self, param: Param) {
-        // we want the postgres param to start from 1
-        // so we first push the param and then check the length of the vector.
         self.params.push(param);
-        self.sql
-            .push_str(format!("@param{}", self.params.len()).as_str());
+        self.sql.push('?');
     }
 }
@@ -9,9 +9,16 @@ pub enum Error {
     CollectionNotFound(models::CollectionName),
     InvalidCollectionName(String),
     ScalarTypeNotFound(models::ScalarTypeName),
+    CompositeTypeNotFound(models::ObjectTypeName),
+    FieldNotFoundInCompositeType(models::FieldName, models::ObjectTypeName),
     ProcedureNotFound(models::ProcedureName),
     ColumnNotFoundInCollection(models::FieldName, models::CollectionName),
     RelationshipNotFound(models::RelationshipName),
+    RelationshipNotOnPartitionKey {
+        relationship: models::RelationshipName,
+        target_collection: models::CollectionName,
+        partition_key: String,
+    },
     ArgumentNotFound(models::ArgumentName),
     OperatorNotFound {
         operator_name: models::ComparisonOperatorName,
@@ -20,6 +27,13 @@ pub enum Error {
     NonScalarTypeUsedInOperator {
         r#type: database::Type,
     },
+    AggregateFunctionNotFound {
+        function_name: models::AggregateFunctionName,
+        type_name: models::ScalarTypeName,
+    },
+    NonScalarTypeUsedInAggregate {
+        r#type: database::Type,
+    },
     RelationshipArgumentWasOverriden(models::ArgumentName),
     EmptyPathForOrderByAggregate,
     MissingAggregateForArrayRelationOrdering,
@@ -84,6 +98,13 @@ impl std::fmt::Display for Error {
             Error::ScalarTypeNotFound(scalar_type) => {
                 write!(f, "Scalar Type '{scalar_type}' not found.")
             }
+            Error::CompositeTypeNotFound(type_name) => {
+                write!(f, "Composite Type '{type_name}' not found.")
+            }
+            Error::FieldNotFoundInCompositeType(field_name, type_name) => write!(
+                f,
+                "Field '{field_name}' not found in composite type '{type_name}'."
+            ),
             Error::ProcedureNotFound(procedure_name) => {
                 write!(f, "Procedure '{procedure_name}' not found.")
             }
@@ -94,6 +115,14 @@ impl std::fmt::Display for Error {
             Error::RelationshipNotFound(relationship_name) => {
                 write!(f, "Relationship '{relationship_name}' not found.")
             }
+            Error::RelationshipNotOnPartitionKey {
+                relationship,
+                target_collection,
+                partition_key,
+            } => write!(
+                f,
+                "Relationship '{relationship}' does not join on the partition key '{partition_key}' of '{target_collection}', so every batch of its join keys would scan the whole collection. Map a column to '{partition_key}', or target an index partitioned on a joined column."
+            ),
             Error::ArgumentNotFound(argument) => {
                 write!(f, "Argument '{argument}' not found.")
             }
@@ -106,6 +135,15 @@ impl std::fmt::Display for Error {
                     "Operator '{operator_name}' not found in type {type_name:?}."
                 )
             }
+            Error::AggregateFunctionNotFound {
+                function_name,
+                type_name,
+            } => {
+                write!(
+                    f,
+                    "Aggregate function '{function_name}' not found in type {type_name:?}."
+                )
+            }
             Error::RelationshipArgumentWasOverriden(key) => {
                 write!(f, "The relationship argument '{key}' was defined as part of the relationship, but was overriden.")
             }
@@ -179,6 +217,9 @@ impl std::fmt::Display for Error {
             Error::NonScalarTypeUsedInOperator { r#type } => {
                 write!(f, "Non-scalar-type used in operator: {type:?}")
             }
+            Error::NonScalarTypeUsedInAggregate { r#type } => {
+                write!(f, "Non-scalar-type used in aggregate: {type:?}")
+            }
             Error::NestedArrayTypesNotSupported => {
                 write!(f, "Encountered a nested array type.")
             }
@@ -1,15 +1,21 @@
 //! Helpers for processing requests and building SQL.
 
+use std::collections::BTreeMap;
+
 use ndc_models::{self as models};
 
 use super::error::Error;
 use query_engine_metadata::metadata;
 use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan;
 
 #[derive(Debug)]
 /// Static information from the query and metadata.
 pub struct Env<'request> {
     pub(crate) metadata: &'request metadata::Metadata,
+    relationships: &'request BTreeMap<models::RelationshipName, models::Relationship>,
+    /// Whether the request comes with variable sets to run it with.
+    has_variables: bool,
 }
 
 #[derive(Debug)]
@@ -17,6 +23,9 @@ pub struct Env<'request> {
 pub struct State {
     // native_queries: NativeQueries,
     global_table_index: TableAliasIndex,
+    /// The semi-joins of `exists` filters across relationships, by the index of their
+    /// placeholders.
+    semi_joins: Vec<execution_plan::SemiJoin>,
 }
 
 #[derive(Debug)]
@@ -61,6 +70,10 @@ pub enum CollectionInfo<'env> {
         name: &'env models::CollectionName,
         info: &'env metadata::TableInfo,
     },
+    NativeQuery {
+        name: &'env models::CollectionName,
+        info: &'env metadata::NativeQueryInfo,
+    },
 }
 
 #[derive(Debug)]
@@ -70,12 +83,17 @@ pub enum FieldsInfo<'env> {
         name: &'env models::CollectionName,
         info: &'env metadata::TableInfo,
     },
+    NativeQuery {
+        name: &'env models::CollectionName,
+        info: &'env metadata::NativeQueryInfo,
+    },
 }
 
 impl<'a> From<&'a CollectionInfo<'a>> for FieldsInfo<'a> {
     fn from(value: &'a CollectionInfo<'a>) -> Self {
         match value {
             CollectionInfo::Table { name, info } => FieldsInfo::Table { name, info },
+            CollectionInfo::NativeQuery { name, info } => FieldsInfo::NativeQuery { name, info },
         }
     }
 }
@@ -92,15 +110,46 @@ impl<'request> Env<'request> {
         F: FnOnce(Env) -> R,
     {
         let temp_metadata = metadata::Metadata::empty();
+        let temp_relationships = BTreeMap::new();
         let temp_env = Env {
             metadata: &temp_metadata,
+            relationships: &temp_relationships,
+            has_variables: false,
         };
         f(temp_env)
     }
 
-    /// Create a new Env by supplying the metadata and relationships.
-    pub fn new(metadata: &'request metadata::Metadata) -> Self {
-        Env { metadata }
+    /// Create a new Env by supplying the metadata, the relationships of the request and whether
+    /// it has variables.
+    pub fn new(
+        metadata: &'request metadata::Metadata,
+        relationships: &'request BTreeMap<models::RelationshipName, models::Relationship>,
+        has_variables: bool,
+    ) -> Self {
+        Env {
+            metadata,
+            relationships,
+            has_variables,
+        }
+    }
+
+    /// Lookup a relationship of the request.
+    pub fn lookup_relationship(
+        &self,
+        name: &models::RelationshipName,
+    ) -> Result<&'request models::Relationship, Error> {
+        self.relationships
+            .get(name)
+            .ok_or_else(|| Error::RelationshipNotFound(name.clone()))
+    }
+
+    /// Variables can only be referenced by requests that come with variable sets.
+    pub fn check_variables_available(&self) -> Result<(), Error> {
+        if self.has_variables {
+            Ok(())
+        } else {
+            Err(Error::UnexpectedVariable)
+        }
     }
 
     /// Lookup a metadata object that may contain fields. This may be any of Tables, Native
@@ -113,15 +162,23 @@ impl<'request> Env<'request> {
     ) -> Result<FieldsInfo<'request>, Error> {
         // Lookup the fields of a type name in a specific order:
         // tables, then composite types, then native queries.
-        let info = self
-            .metadata
-            .tables
-            .0
-            .get(type_name)
-            .map(|t| FieldsInfo::Table {
-                name: type_name,
-                info: t,
-            });
+        let info =
+            self.metadata
+                .tables
+                .0
+                .get(type_name)
+                .map(|t| FieldsInfo::Table {
+                    name: type_name,
+                    info: t,
+                })
+                .or_else(|| {
+                    self.metadata.native_queries.0.get(type_name).map(|nq| {
+                        FieldsInfo::NativeQuery {
+                            name: type_name,
+                            info: nq,
+                        }
+                    })
+                });
 
         info.ok_or(Error::CollectionNotFound(type_name.as_str().into()))
     }
@@ -144,7 +201,15 @@ impl<'request> Env<'request> {
         if let Some(table) = table {
             Ok(table)
         } else {
-            Err(Error::CollectionNotFound(collection_name.clone()))
+            self.metadata
+                .native_queries
+                .0
+                .get(collection_name)
+                .map(|nq| CollectionInfo::NativeQuery {
+                    name: collection_name,
+                    info: nq,
+                })
+                .ok_or_else(|| Error::CollectionNotFound(collection_name.clone()))
         }
     }
 
@@ -165,6 +230,23 @@ impl<'request> Env<'request> {
             })
     }
 
+    /// Looks up an aggregate function of a scalar type in the metadata.
+    pub fn lookup_aggregate_function(
+        &self,
+        scalar_type: &models::ScalarTypeName,
+        name: &models::AggregateFunctionName,
+    ) -> Result<&'request metadata::AggregateFunction, Error> {
+        self.metadata
+            .scalar_types
+            .0
+            .get(scalar_type)
+            .and_then(|t| t.aggregate_functions.get(name))
+            .ok_or(Error::AggregateFunctionNotFound {
+                function_name: name.clone(),
+                type_name: scalar_type.clone(),
+            })
+    }
+
     /// Lookup type representation of a type.
     pub fn lookup_type_representation(
         &self,
@@ -177,6 +259,18 @@ impl<'request> Env<'request> {
             .and_then(|t| t.type_representation.as_ref())
     }
 
+    /// Lookup a composite type, the shape of a map attribute, by its name in the ndc schema.
+    pub fn lookup_composite_type(
+        &self,
+        type_name: &models::ObjectTypeName,
+    ) -> Result<&'request metadata::CompositeType, Error> {
+        self.metadata
+            .composite_types
+            .0
+            .get(type_name)
+            .ok_or_else(|| Error::CompositeTypeNotFound(type_name.clone()))
+    }
+
     /// Lookup a scalar type by its name in the ndc schema.
     pub(crate) fn lookup_scalar_type(
         &self,
@@ -204,16 +298,16 @@ impl FieldsInfo<'_> {
                 .ok_or_else(|| {
                     Error::ColumnNotFoundInCollection(column_name.clone(), (*name).clone())
                 }),
-            // FieldsInfo::NativeQuery { name, info } => info
-            //     .columns
-            //     .get(column_name)
-            //     .map(|column_info| ColumnInfo {
-            //         name: sql::ast::ColumnName(column_info.name.clone()),
-            //         r#type: column_info.r#type.clone(),
-            //     })
-            //     .ok_or_else(|| {
-            //         Error::ColumnNotFoundInCollection(column_name.clone(), name.as_str().into())
-            //     }),
+            FieldsInfo::NativeQuery { name, info } => info
+                .columns
+                .get(column_name)
+                .map(|column_info| ColumnInfo {
+                    name: sql::ast::ColumnName(column_info.name.clone()),
+                    r#type: column_info.r#type.clone(),
+                })
+                .ok_or_else(|| {
+                    Error::ColumnNotFoundInCollection(column_name.clone(), (*name).clone())
+                }),
         }
     }
 }
@@ -230,6 +324,7 @@ impl Default for State {
         State {
             // native_queries: NativeQueries::new(),
             global_table_index: TableAliasIndex(0),
+            semi_joins: vec![],
         }
     }
 }
@@ -274,6 +369,19 @@ impl State {
     ) -> sql::ast::TableAlias {
         self.make_table_alias(format!("BOOLEXP_{source_table_name}"))
     }
+
+    // semi-joins
+
+    /// Add the semi-join of an `exists` filter, and get the placeholder of its condition.
+    pub fn add_semi_join(&mut self, semi_join: execution_plan::SemiJoin) -> sql::ast::Expression {
+        self.semi_joins.push(semi_join);
+        sql::ast::Expression::SemiJoin(self.semi_joins.len() - 1)
+    }
+
+    /// Take the semi-joins added while translating the request.
+    pub fn take_semi_joins(&mut self) -> Vec<execution_plan::SemiJoin> {
+        std::mem::take(&mut self.semi_joins)
+    }
 }
 
 impl TableAliasIndex {
@@ -307,15 +415,55 @@ impl From<&Option<Vec<models::FieldName>>> for FieldPath {
     }
 }
 
-/// Fold an expression inside of a chain of field path accessors.
-pub fn wrap_in_field_path(
+/// Fold an expression of the given type inside of a chain of document path accessors, checking
+/// each step against the type. Fields select the attributes of maps, and fields which are list
+/// indices, such as `0`, select the elements of lists.
+///
+/// Returns the expression and the type at the end of the path.
+pub fn translate_field_path(
+    env: &Env,
     field_path: &FieldPath,
     expression: sql::ast::Expression,
-) -> sql::ast::Expression {
-    field_path.0.iter().fold(expression, |expression, field| {
-        sql::ast::Expression::NestedFieldSelect {
-            expression: Box::new(expression),
-            nested_field: sql::ast::NestedField(field.clone().into()),
-        }
-    })
+    r#type: &metadata::Type,
+) -> Result<(sql::ast::Expression, metadata::Type), Error> {
+    field_path.0.iter().try_fold(
+        (expression, r#type.clone()),
+        |(expression, r#type), field| match &r#type {
+            metadata::Type::CompositeType(type_name) => {
+                let field_info = env
+                    .lookup_composite_type(type_name)?
+                    .fields
+                    .get(field)
+                    .ok_or_else(|| {
+                        Error::FieldNotFoundInCompositeType(field.clone(), type_name.clone())
+                    })?;
+                Ok((
+                    sql::ast::Expression::NestedFieldSelect {
+                        expression: Box::new(expression),
+                        nested_field: sql::ast::NestedField(field_info.field_name.clone()),
+                    },
+                    field_info.r#type.clone(),
+                ))
+            }
+            metadata::Type::ArrayType(element_type) => {
+                let index = field.as_str().parse::<u32>().map_err(|_| {
+                    Error::NestedFieldNotOfCompositeType {
+                        field_name: field.clone(),
+                        actual_type: r#type.clone(),
+                    }
+                })?;
+                Ok((
+                    sql::ast::Expression::NestedElementSelect {
+                        expression: Box::new(expression),
+                        index,
+                    },
+                    (**element_type).clone(),
+                ))
+            }
+            metadata::Type::ScalarType(_) => Err(Error::NestedFieldNotOfCompositeType {
+                field_name: field.clone(),
+                actual_type: r#type.clone(),
+            }),
+        },
+    )
 }
@@ -2,4 +2,8 @@
 
 pub mod error;
 pub mod helpers;
+pub mod mutation;
 pub mod query;
+
+#[cfg(test)]
+mod test_helpers;
@@ -0,0 +1,171 @@
+//! Given the tables in the metadata, generate a set of standard mutation procedures.
+
+use std::collections::BTreeMap;
+
+use ndc_models as models;
+use query_engine_metadata::metadata;
+
+pub use metadata::{
+    key_object_type_name, update_object_type_name, BATCH_WRITE_ITEM_RESULT_TYPE,
+    BATCH_WRITE_RESULT_TYPE,
+};
+
+/// The name of the argument holding the items to insert in a batch insert.
+pub const OBJECTS_ARGUMENT: &str = "objects";
+
+/// The name of the argument holding the keys to delete in a batch delete.
+pub const KEYS_ARGUMENT: &str = "keys";
+
+/// The name of the argument holding the key of the item to update or delete.
+pub const KEY_ARGUMENT: &str = "key";
+
+/// The name of the argument holding the attributes to set in an update.
+pub const SET_ARGUMENT: &str = "set";
+
+/// The name of the argument holding the version the caller expects the item to have, on tables
+/// with a version attribute.
+pub const EXPECTED_VERSION_ARGUMENT: &str = "expected_version";
+
+/// A procedure generated from a table.
+#[derive(Debug, Clone)]
+pub enum Mutation<'a> {
+    /// Put many items using `BatchWriteItem`, or using a conditional `PutItem` per item on tables
+    /// with a version attribute.
+    InsertMany(TableMutation<'a>),
+    /// Delete many items by key using `BatchWriteItem`.
+    DeleteMany(TableMutation<'a>),
+    /// Update the attributes of a single item using `UpdateItem`.
+    UpdateByKey(TableMutation<'a>),
+    /// Delete a single item using `DeleteItem`.
+    DeleteByKey(TableMutation<'a>),
+}
+
+/// The table a generated procedure operates on.
+#[derive(Debug, Clone)]
+pub struct TableMutation<'a> {
+    pub collection_name: &'a models::CollectionName,
+    pub table: &'a metadata::TableInfo,
+    pub description: String,
+}
+
+impl<'a> Mutation<'a> {
+    /// The table this procedure operates on.
+    pub fn table_mutation(&self) -> &TableMutation<'a> {
+        match self {
+            Mutation::InsertMany(table_mutation)
+            | Mutation::DeleteMany(table_mutation)
+            | Mutation::UpdateByKey(table_mutation)
+            | Mutation::DeleteByKey(table_mutation) => table_mutation,
+        }
+    }
+}
+
+/// Generate the procedures for every table in the metadata.
+///
+/// Tables with a version attribute do not get a batch delete, as `BatchWriteItem` cannot check
+/// the version of the items it deletes. Entities get no procedures, as their items would need
+/// their key prefixes and discriminator written; native mutations can write them instead.
+pub fn generate(tables: &metadata::TablesInfo) -> BTreeMap<models::ProcedureName, Mutation> {
+    let mut mutations = BTreeMap::new();
+
+    for (collection_name, table) in tables.0.iter().filter(|(_, table)| table.entity.is_none()) {
+        let insert_description = if table.version_attribute.is_none() {
+            format!("Insert multiple items into the '{collection_name}' table using BatchWriteItem")
+        } else {
+            format!(
+                "Insert multiple items into the '{collection_name}' table using PutItem, each only if no item has its key yet"
+            )
+        };
+        mutations.insert(
+            metadata::insert_many_procedure_name(collection_name),
+            Mutation::InsertMany(TableMutation {
+                collection_name,
+                table,
+                description: insert_description,
+            }),
+        );
+        if table.version_attribute.is_none() {
+            mutations.insert(
+                metadata::delete_many_procedure_name(collection_name),
+                Mutation::DeleteMany(TableMutation {
+                    collection_name,
+                    table,
+                    description: format!(
+                        "Delete multiple items by key from the '{collection_name}' table using BatchWriteItem"
+                    ),
+                }),
+            );
+        }
+        mutations.insert(
+            metadata::update_by_key_procedure_name(collection_name),
+            Mutation::UpdateByKey(TableMutation {
+                collection_name,
+                table,
+                description: format!(
+                    "Update a single item by key in the '{collection_name}' table using UpdateItem"
+                ),
+            }),
+        );
+        mutations.insert(
+            metadata::delete_by_key_procedure_name(collection_name),
+            Mutation::DeleteByKey(TableMutation {
+                collection_name,
+                table,
+                description: format!(
+                    "Delete a single item by key from the '{collection_name}' table using DeleteItem"
+                ),
+            }),
+        );
+    }
+
+    mutations
+}
+
+/// The columns that can be set in an update: everything but the key and version attributes.
+pub fn updatable_columns(
+    table: &metadata::TableInfo,
+) -> impl Iterator<Item = (&models::FieldName, &metadata::ColumnInfo)> {
+    table.columns.iter().filter(|(_, column_info)| {
+        column_info.name != table.partition_key
+            && column_info.name != table.sort_key
+            && Some(&column_info.name) != table.version_attribute.as_ref()
+    })
+}
+
+/// The key attributes of a table, paired with the field name they are exposed as.
+pub fn key_columns(table: &metadata::TableInfo) -> Vec<(models::FieldName, &metadata::ColumnInfo)> {
+    [&table.partition_key, &table.sort_key]
+        .into_iter()
+        .filter_map(|attribute_name| {
+            table
+                .columns
+                .iter()
+                .find(|(_, column_info)| &column_info.name == attribute_name)
+                .map(|(field_name, column_info)| (field_name.clone(), column_info))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate;
+    use crate::translation::test_helpers::entity_metadata;
+
+    #[test]
+    fn test_entities_get_no_procedures() {
+        let metadata = entity_metadata();
+        let procedures = generate(&metadata.tables);
+
+        assert!(procedures.contains_key("update_app_by_key"));
+        for entity in ["customer", "order"] {
+            for name in [
+                format!("insert_{entity}_many"),
+                format!("delete_{entity}_many"),
+                format!("update_{entity}_by_key"),
+                format!("delete_{entity}_by_key"),
+            ] {
+                assert!(!procedures.contains_key(name.as_str()), "{name}");
+            }
+        }
+    }
+}
@@ -0,0 +1,7 @@
+//! Translate an incoming `MutationRequest`.
+
+pub mod generate;
+pub mod native_mutations;
+pub mod translate;
+
+pub use translate::translate;
@@ -0,0 +1,161 @@
+//! Translate native mutations: PartiQL writes written by the user, run with their arguments
+//! bound as parameters.
+
+use std::collections::BTreeMap;
+
+use ndc_models as models;
+use query_engine_metadata::metadata;
+use query_engine_sql::sql::execution_plan::MutationOperation;
+
+use super::translate::translate_attribute_value;
+use crate::translation::error::Error;
+
+/// Translate a native mutation to a statement with a `?` parameter for each of its placeholders.
+/// Every argument must be given, except nullable ones which default to null.
+pub fn translate(
+    native_mutation: &metadata::NativeMutationInfo,
+    arguments: &BTreeMap<models::ArgumentName, serde_json::Value>,
+) -> Result<MutationOperation, Error> {
+    if let Some(name) = arguments
+        .keys()
+        .find(|name| !native_mutation.arguments.contains_key(*name))
+    {
+        return Err(Error::ArgumentNotFound(name.clone()));
+    }
+
+    let mut statement = String::new();
+    let mut parameters = vec![];
+    for part in native_mutation.parts() {
+        match part {
+            metadata::NativeQueryPart::Text(text) => statement.push_str(&text),
+            metadata::NativeQueryPart::Parameter(name) => {
+                let name = models::ArgumentName::new(name.into());
+                let argument_info = native_mutation
+                    .arguments
+                    .get(&name)
+                    .ok_or_else(|| Error::ArgumentNotFound(name.clone()))?;
+                let value = match arguments.get(&name) {
+                    Some(value) => value,
+                    None if argument_info.nullable == metadata::Nullable::Nullable => {
+                        &serde_json::Value::Null
+                    }
+                    None => return Err(Error::ArgumentNotFound(name)),
+                };
+                statement.push('?');
+                parameters.push(translate_attribute_value(&argument_info.r#type, value)?);
+            }
+        }
+    }
+
+    Ok(MutationOperation::ExecuteStatement {
+        statement,
+        parameters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ndc_models as models;
+    use query_engine_metadata::metadata;
+    use query_engine_sql::sql::execution_plan::{AttributeType, MutationOperation, TypedValue};
+
+    use super::translate;
+    use crate::translation::error::Error;
+
+    fn native_mutation() -> metadata::NativeMutationInfo {
+        serde_json::from_value(serde_json::json!({
+            "sql": "UPDATE users SET name = {{name}}, age = {{ age }} WHERE id = {{id}}",
+            "arguments": {
+                "id": { "name": "id", "type": { "scalarType": "String" }, "nullable": "nonNullable" },
+                "name": { "name": "name", "type": { "scalarType": "String" }, "nullable": "nonNullable" },
+                "age": { "name": "age", "type": { "scalarType": "Number" } },
+            },
+        }))
+        .unwrap()
+    }
+
+    fn arguments(
+        arguments: serde_json::Value,
+    ) -> BTreeMap<models::ArgumentName, serde_json::Value> {
+        serde_json::from_value(arguments).unwrap()
+    }
+
+    #[test]
+    fn test_arguments_are_bound_in_placeholder_order() {
+        let operation = translate(
+            &native_mutation(),
+            &arguments(serde_json::json!({ "id": "u1", "name": "Ada", "age": 36 })),
+        )
+        .unwrap();
+        let MutationOperation::ExecuteStatement {
+            statement,
+            parameters,
+        } = operation
+        else {
+            panic!("expected a statement, got {operation:?}");
+        };
+        assert_eq!(statement, "UPDATE users SET name = ?, age = ? WHERE id = ?");
+        assert_eq!(
+            parameters,
+            vec![
+                TypedValue {
+                    value: serde_json::json!("Ada"),
+                    attribute_type: AttributeType::String,
+                },
+                TypedValue {
+                    value: serde_json::json!(36),
+                    attribute_type: AttributeType::Number,
+                },
+                TypedValue {
+                    value: serde_json::json!("u1"),
+                    attribute_type: AttributeType::String,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nullable_arguments_default_to_null() {
+        let operation = translate(
+            &native_mutation(),
+            &arguments(serde_json::json!({ "id": "u1", "name": "Ada" })),
+        )
+        .unwrap();
+        let MutationOperation::ExecuteStatement { parameters, .. } = operation else {
+            panic!("expected a statement, got {operation:?}");
+        };
+        assert_eq!(parameters[1].value, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_missing_and_unknown_arguments() {
+        let missing = translate(
+            &native_mutation(),
+            &arguments(serde_json::json!({ "id": "u1" })),
+        );
+        assert!(
+            matches!(missing, Err(Error::ArgumentNotFound(ref name)) if name.as_str() == "name"),
+            "{missing:?}"
+        );
+
+        let unknown = translate(
+            &native_mutation(),
+            &arguments(serde_json::json!({ "id": "u1", "name": "Ada", "email": "a@b.c" })),
+        );
+        assert!(
+            matches!(unknown, Err(Error::ArgumentNotFound(ref name)) if name.as_str() == "email"),
+            "{unknown:?}"
+        );
+    }
+
+    #[test]
+    fn test_argument_of_the_wrong_type() {
+        let result = translate(
+            &native_mutation(),
+            &arguments(serde_json::json!({ "id": "u1", "name": "Ada", "age": "old" })),
+        );
+        assert!(matches!(result, Err(Error::TypeMismatch(..))), "{result:?}");
+    }
+}
@@ -0,0 +1,763 @@
+//! Translate a procedure invocation into a write against the database.
+
+use std::collections::BTreeMap;
+
+use ndc_models as models;
+
+use super::generate;
+use super::native_mutations;
+use crate::translation::error::Error;
+use crate::translation::query::key_templates;
+use crate::translation::query::values;
+use query_engine_metadata::metadata;
+use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan::{
+    AttributeType, TypedValue, VersionCheck, WriteExpression, WriteRequest,
+};
+
+/// Translate the incoming MutationRequest to an ExecutionPlan to be run against the database.
+pub fn translate(
+    metadata: &metadata::Metadata,
+    mutation_request: models::MutationRequest,
+) -> Result<sql::execution_plan::ExecutionPlan<Vec<sql::execution_plan::Mutation>>, Error> {
+    let mutations = generate::generate(&metadata.tables);
+
+    let plans = mutation_request
+        .operations
+        .into_iter()
+        .map(|operation| match operation {
+            models::MutationOperation::Procedure {
+                name,
+                arguments,
+                fields,
+            } => {
+                // native mutations replace the generated procedures of the same name
+                let operation = match metadata.native_mutations.0.get(&name) {
+                    Some(native_mutation) => {
+                        native_mutations::translate(native_mutation, &arguments)?
+                    }
+                    None => {
+                        let mutation = mutations
+                            .get(&name)
+                            .ok_or_else(|| Error::ProcedureNotFound(name.clone()))?;
+                        translate_mutation(mutation, &arguments)?
+                    }
+                };
+                Ok(sql::execution_plan::Mutation {
+                    procedure_name: name,
+                    operation,
+                    fields,
+                })
+            }
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok(sql::execution_plan::simple_mutations_execution_plan(plans))
+}
+
+/// Translate a single generated procedure and its arguments.
+fn translate_mutation(
+    mutation: &generate::Mutation,
+    arguments: &BTreeMap<models::ArgumentName, serde_json::Value>,
+) -> Result<sql::execution_plan::MutationOperation, Error> {
+    let generate::TableMutation {
+        collection_name,
+        table,
+        ..
+    } = mutation.table_mutation();
+
+    let table_name = table.table_name.clone();
+
+    match mutation {
+        generate::Mutation::InsertMany(_) => {
+            let objects = get_array_argument(arguments, generate::OBJECTS_ARGUMENT)?;
+            match &table.version_attribute {
+                None => {
+                    let requests = objects
+                        .iter()
+                        .map(|object| {
+                            let item = translate_item(collection_name, table, object, "insert")?;
+                            Ok(WriteRequest::Put { item })
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    Ok(sql::execution_plan::MutationOperation::BatchWrite {
+                        table_name,
+                        key_attributes: key_attributes(table),
+                        requests,
+                    })
+                }
+                Some(version_attribute) => {
+                    let items = objects
+                        .iter()
+                        .map(|object| {
+                            let mut item = translate_item(collection_name, table, object, "insert")?;
+                            // new items always start at the first version
+                            if item.contains_key(version_attribute) {
+                                return Err(Error::UnexpectedStructure(format!(
+                                    "value in 'insert' procedure, the version attribute '{version_attribute}' is set by the connector"
+                                )));
+                            }
+                            item.insert(
+                                version_attribute.clone(),
+                                TypedValue {
+                                    value: serde_json::Value::from(1),
+                                    attribute_type: AttributeType::Number,
+                                },
+                            );
+                            Ok(item)
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    // never overwrite an item, whatever its version
+                    let mut expression = WriteExpression::default();
+                    expression
+                        .attribute_names
+                        .insert("#pk".to_string(), table.partition_key.clone());
+                    expression.condition_expression = Some("attribute_not_exists(#pk)".to_string());
+                    Ok(sql::execution_plan::MutationOperation::PutItems {
+                        table_name,
+                        key_attributes: key_attributes(table),
+                        items,
+                        expression,
+                    })
+                }
+            }
+        }
+        generate::Mutation::DeleteMany(_) => {
+            let requests = get_array_argument(arguments, generate::KEYS_ARGUMENT)?
+                .iter()
+                .map(|object| {
+                    let key = translate_key(collection_name, table, object, "delete")?;
+                    Ok(WriteRequest::Delete { key })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok(sql::execution_plan::MutationOperation::BatchWrite {
+                table_name,
+                key_attributes: key_attributes(table),
+                requests,
+            })
+        }
+        generate::Mutation::UpdateByKey(_) => {
+            let key = translate_key(
+                collection_name,
+                table,
+                get_argument(arguments, generate::KEY_ARGUMENT)?,
+                "update",
+            )?;
+            let version = translate_version_check(table, arguments)?;
+
+            let serde_json::Value::Object(fields) =
+                get_argument(arguments, generate::SET_ARGUMENT)?
+            else {
+                return Err(Error::UnexpectedStructure(format!(
+                    "value for argument '{}', expected an object",
+                    generate::SET_ARGUMENT
+                )));
+            };
+
+            let mut expression = WriteExpression::default();
+            let mut assignments = vec![];
+            for (index, (field_name, value)) in fields.iter().enumerate() {
+                let field_name: models::FieldName = field_name.as_str().into();
+                let (_, column_info) = generate::updatable_columns(table)
+                    .find(|(name, _)| **name == field_name)
+                    .ok_or_else(|| {
+                        Error::ColumnNotFoundInCollection(
+                            field_name.clone(),
+                            collection_name.clone(),
+                        )
+                    })?;
+                let name = format!("#a{index}");
+                let placeholder = format!(":a{index}");
+                assignments.push(format!("{name} = {placeholder}"));
+                expression
+                    .attribute_names
+                    .insert(name, column_info.name.clone());
+                expression.attribute_values.insert(
+                    placeholder,
+                    translate_attribute_value(&column_info.r#type, value)?,
+                );
+            }
+
+            match &version {
+                Some(version) => {
+                    assignments.push("#v = #v + :one".to_string());
+                    expression.attribute_values.insert(
+                        ":one".to_string(),
+                        TypedValue {
+                            value: serde_json::Value::from(1),
+                            attribute_type: AttributeType::Number,
+                        },
+                    );
+                    add_version_condition(&mut expression, version);
+                }
+                None => {
+                    if assignments.is_empty() {
+                        return Err(Error::UnexpectedStructure(format!(
+                            "value for argument '{}', expected at least one attribute to set",
+                            generate::SET_ARGUMENT
+                        )));
+                    }
+                    // only update items that exist
+                    expression
+                        .attribute_names
+                        .insert("#pk".to_string(), table.partition_key.clone());
+                    expression.condition_expression = Some("attribute_exists(#pk)".to_string());
+                }
+            }
+            expression.update_expression = Some(format!("SET {}", assignments.join(", ")));
+
+            Ok(sql::execution_plan::MutationOperation::UpdateItem {
+                table_name,
+                key,
+                expression,
+                version,
+            })
+        }
+        generate::Mutation::DeleteByKey(_) => {
+            let key = translate_key(
+                collection_name,
+                table,
+                get_argument(arguments, generate::KEY_ARGUMENT)?,
+                "delete",
+            )?;
+            let version = translate_version_check(table, arguments)?;
+            let mut expression = WriteExpression::default();
+            if let Some(version) = &version {
+                add_version_condition(&mut expression, version);
+            }
+            Ok(sql::execution_plan::MutationOperation::DeleteItem {
+                table_name,
+                key,
+                expression,
+                version,
+            })
+        }
+    }
+}
+
+/// Read the version the caller expects, on tables with a version attribute.
+fn translate_version_check(
+    table: &metadata::TableInfo,
+    arguments: &BTreeMap<models::ArgumentName, serde_json::Value>,
+) -> Result<Option<VersionCheck>, Error> {
+    let Some(version_attribute) = &table.version_attribute else {
+        return Ok(None);
+    };
+    let expected = get_argument(arguments, generate::EXPECTED_VERSION_ARGUMENT)?;
+    if !expected.is_number() {
+        return Err(Error::TypeMismatch(expected.clone(), "Number".into()));
+    }
+    Ok(Some(VersionCheck {
+        attribute: version_attribute.clone(),
+        expected: expected.clone(),
+    }))
+}
+
+/// Only let the write through when the stored version is the expected one.
+fn add_version_condition(expression: &mut WriteExpression, version: &VersionCheck) {
+    expression
+        .attribute_names
+        .insert("#v".to_string(), version.attribute.clone());
+    expression.attribute_values.insert(
+        ":expected_version".to_string(),
+        TypedValue {
+            value: version.expected.clone(),
+            attribute_type: AttributeType::Number,
+        },
+    );
+    expression.condition_expression = Some("#v = :expected_version".to_string());
+}
+
+/// The names of the primary key attributes of a table.
+fn key_attributes(table: &metadata::TableInfo) -> Vec<String> {
+    generate::key_columns(table)
+        .into_iter()
+        .map(|(_, column_info)| column_info.name.clone())
+        .collect()
+}
+
+/// Fetch an argument.
+fn get_argument<'a>(
+    arguments: &'a BTreeMap<models::ArgumentName, serde_json::Value>,
+    name: &str,
+) -> Result<&'a serde_json::Value, Error> {
+    let argument_name: models::ArgumentName = name.into();
+    arguments
+        .get(&argument_name)
+        .ok_or(Error::ArgumentNotFound(argument_name))
+}
+
+/// Fetch an argument that is expected to be an array.
+fn get_array_argument<'a>(
+    arguments: &'a BTreeMap<models::ArgumentName, serde_json::Value>,
+    name: &str,
+) -> Result<&'a Vec<serde_json::Value>, Error> {
+    let argument_name: models::ArgumentName = name.into();
+    match arguments.get(&argument_name) {
+        None => Err(Error::ArgumentNotFound(argument_name)),
+        Some(serde_json::Value::Array(values)) => Ok(values),
+        Some(_) => Err(Error::UnexpectedStructure(format!(
+            "value for argument '{name}', expected an array"
+        ))),
+    }
+}
+
+/// Translate a json object into the key attributes of an item.
+fn translate_key(
+    collection_name: &models::CollectionName,
+    table: &metadata::TableInfo,
+    object: &serde_json::Value,
+    operation: &str,
+) -> Result<BTreeMap<String, TypedValue>, Error> {
+    let item = translate_item(collection_name, table, object, operation)?;
+    Ok(generate::key_columns(table)
+        .into_iter()
+        .filter_map(|(_, column_info)| {
+            item.get(&column_info.name)
+                .map(|value| (column_info.name.clone(), value.clone()))
+        })
+        .collect())
+}
+
+/// Translate a json object into the attributes of an item, checking every field against the
+/// table columns and making sure the key attributes are present. Attributes with a key template
+/// may be given by their virtual fields instead.
+fn translate_item(
+    collection_name: &models::CollectionName,
+    table: &metadata::TableInfo,
+    object: &serde_json::Value,
+    operation: &str,
+) -> Result<BTreeMap<String, TypedValue>, Error> {
+    let serde_json::Value::Object(fields) = object else {
+        return Err(Error::UnexpectedStructure(format!(
+            "value in '{operation}' procedure, expected an object"
+        )));
+    };
+
+    let mut item = BTreeMap::new();
+    // the values of the virtual fields of key templates, by attribute
+    let mut virtual_fields: BTreeMap<&String, (&metadata::KeyTemplate, BTreeMap<_, _>)> =
+        BTreeMap::new();
+    for (field_name, value) in fields {
+        let field_name: models::FieldName = field_name.as_str().into();
+        if let Some(column_info) = table.columns.get(&field_name) {
+            item.insert(
+                column_info.name.clone(),
+                translate_attribute_value(&column_info.r#type, value)?,
+            );
+        } else if let Some((attribute, key_template, field)) =
+            table.key_templates.lookup_field(&field_name)
+        {
+            let text = key_templates::field_text(field, value)?;
+            virtual_fields
+                .entry(attribute)
+                .or_insert_with(|| (key_template, BTreeMap::new()))
+                .1
+                .insert(field_name, text);
+        } else {
+            return Err(Error::ColumnNotFoundInCollection(
+                field_name,
+                collection_name.clone(),
+            ));
+        }
+    }
+
+    // attributes given by their virtual fields are built from all of them
+    for (attribute, (key_template, values)) in virtual_fields {
+        if let Some(field_name) = key_template
+            .fields
+            .keys()
+            .find(|field_name| !values.contains_key(*field_name))
+        {
+            return Err(Error::MissingColumnInMutation {
+                collection: collection_name.clone(),
+                column_name: field_name.clone(),
+                operation: operation.to_string(),
+            });
+        }
+        let (value, _) = key_templates::fill_template(key_template, &values);
+        let value = TypedValue {
+            value: serde_json::Value::String(value),
+            attribute_type: AttributeType::String,
+        };
+        if item
+            .get(attribute)
+            .is_some_and(|other| !other.value.is_null() && other != &value)
+        {
+            return Err(Error::UnexpectedStructure(format!(
+                "value in '{operation}' procedure, the attribute '{attribute}' does not match its key template fields"
+            )));
+        }
+        item.insert(attribute.clone(), value);
+    }
+
+    for (field_name, column_info) in generate::key_columns(table) {
+        let is_present = matches!(item.get(&column_info.name), Some(typed_value) if !typed_value.value.is_null());
+        if !is_present {
+            return Err(Error::MissingColumnInMutation {
+                collection: collection_name.clone(),
+                column_name: field_name,
+                operation: operation.to_string(),
+            });
+        }
+    }
+
+    Ok(item)
+}
+
+/// Check that a json value fits the column type, and tag it with the attribute type it should be
+/// written as.
+pub fn translate_attribute_value(
+    typ: &metadata::Type,
+    value: &serde_json::Value,
+) -> Result<TypedValue, Error> {
+    values::check_custom_scalar_value(typ, value)?;
+    let attribute_type = attribute_type(typ);
+    let valid = matches!(
+        (value, attribute_type),
+        (_, AttributeType::Any)
+            | (serde_json::Value::Null, _)
+            | (
+                serde_json::Value::String(_),
+                AttributeType::String | AttributeType::Binary
+            )
+            | (serde_json::Value::Number(_), AttributeType::Number)
+            | (serde_json::Value::Bool(_), AttributeType::Boolean)
+            | (serde_json::Value::Array(_), AttributeType::List)
+            | (serde_json::Value::Object(_), AttributeType::Map)
+    );
+    if valid {
+        Ok(TypedValue {
+            value: value.clone(),
+            attribute_type,
+        })
+    } else {
+        Err(Error::TypeMismatch(value.clone(), scalar_type_name(typ)))
+    }
+}
+
+/// The DynamoDB attribute type of a column type.
+pub fn attribute_type(typ: &metadata::Type) -> AttributeType {
+    match typ {
+        metadata::Type::ArrayType(_) => AttributeType::List,
+        metadata::Type::CompositeType(_) => AttributeType::Map,
+        metadata::Type::ScalarType(scalar_type) => match scalar_type.as_str() {
+            "String" => AttributeType::String,
+            "Number" => AttributeType::Number,
+            "Binary" => AttributeType::Binary,
+            "Boolean" => AttributeType::Boolean,
+            "List" => AttributeType::List,
+            "Map" => AttributeType::Map,
+            // custom scalar types are held in attributes of their storage type
+            name => metadata::CustomScalarType::from_name(name).map_or(
+                AttributeType::Any,
+                |custom_scalar| {
+                    attribute_type(&metadata::Type::ScalarType(
+                        custom_scalar.storage_type().into(),
+                    ))
+                },
+            ),
+        },
+    }
+}
+
+/// The scalar type name to report in errors.
+fn scalar_type_name(typ: &metadata::Type) -> models::ScalarTypeName {
+    match typ {
+        metadata::Type::ScalarType(scalar_type) => scalar_type.clone(),
+        metadata::Type::ArrayType(_) => "List".into(),
+        metadata::Type::CompositeType(_) => "Map".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use query_engine_sql::sql::execution_plan::MutationOperation;
+
+    fn users_table() -> metadata::TableInfo {
+        serde_json::from_value(serde_json::json!({
+            "tableName": "users",
+            "columns": {
+                "id": { "name": "id", "type": { "scalarType": "String" } },
+                "created": { "name": "created", "type": { "scalarType": "Number" } },
+                "name": { "name": "name", "type": { "scalarType": "String" } },
+            },
+            "partitionKey": "id",
+            "sortKey": "created",
+            "gsi": {},
+        }))
+        .unwrap()
+    }
+
+    fn versioned_users_table() -> metadata::TableInfo {
+        let mut table = users_table();
+        table.columns.insert(
+            "version".into(),
+            serde_json::from_value(serde_json::json!({
+                "name": "version",
+                "type": { "scalarType": "Number" },
+            }))
+            .unwrap(),
+        );
+        table.version_attribute = Some("version".to_string());
+        table
+    }
+
+    fn translate_procedure(
+        table: metadata::TableInfo,
+        procedure: &str,
+        arguments: serde_json::Value,
+    ) -> Result<MutationOperation, Error> {
+        let tables = metadata::TablesInfo(BTreeMap::from([("users".into(), table)]));
+        let mutations = generate::generate(&tables);
+        let mutation = mutations
+            .get(&models::ProcedureName::from(procedure))
+            .unwrap_or_else(|| panic!("no procedure '{procedure}'"));
+        translate_mutation(mutation, &serde_json::from_value(arguments).unwrap())
+    }
+
+    #[test]
+    fn test_insert_many_puts_every_object() {
+        let operation = translate_procedure(
+            users_table(),
+            "insert_users_many",
+            serde_json::json!({
+                "objects": [
+                    { "id": "a", "created": 1, "name": "Alice" },
+                    { "id": "b", "created": 2 },
+                ]
+            }),
+        )
+        .unwrap();
+
+        let MutationOperation::BatchWrite {
+            table_name,
+            key_attributes,
+            requests,
+        } = operation
+        else {
+            panic!("expected a batch write, got {operation:?}");
+        };
+        assert_eq!(table_name, "users");
+        assert_eq!(key_attributes, vec!["id", "created"]);
+        let items = requests
+            .into_iter()
+            .map(|request| match request {
+                WriteRequest::Put { item } => item
+                    .into_iter()
+                    .map(|(name, typed_value)| (name, typed_value.value))
+                    .collect::<BTreeMap<_, _>>(),
+                WriteRequest::Delete { .. } => panic!("expected a put request"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            items,
+            vec![
+                BTreeMap::from([
+                    ("created".to_string(), serde_json::json!(1)),
+                    ("id".to_string(), serde_json::json!("a")),
+                    ("name".to_string(), serde_json::json!("Alice")),
+                ]),
+                BTreeMap::from([
+                    ("created".to_string(), serde_json::json!(2)),
+                    ("id".to_string(), serde_json::json!("b")),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_many_requires_the_key_attributes() {
+        let result = translate_procedure(
+            users_table(),
+            "insert_users_many",
+            serde_json::json!({ "objects": [{ "id": "a", "name": "Alice" }] }),
+        );
+        assert!(
+            matches!(
+                result,
+                Err(Error::MissingColumnInMutation { ref column_name, .. })
+                    if column_name.as_str() == "created"
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_insert_many_rejects_unknown_fields() {
+        let result = translate_procedure(
+            users_table(),
+            "insert_users_many",
+            serde_json::json!({ "objects": [{ "id": "a", "created": 1, "age": 42 }] }),
+        );
+        assert!(
+            matches!(result, Err(Error::ColumnNotFoundInCollection(ref field, _)) if field.as_str() == "age"),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_delete_many_deletes_by_key_only() {
+        let operation = translate_procedure(
+            users_table(),
+            "delete_users_many",
+            serde_json::json!({ "keys": [{ "id": "a", "created": 1 }] }),
+        )
+        .unwrap();
+
+        let MutationOperation::BatchWrite { requests, .. } = operation else {
+            panic!("expected a batch write, got {operation:?}");
+        };
+        let [WriteRequest::Delete { key }] = requests.as_slice() else {
+            panic!("expected a single delete request, got {requests:?}");
+        };
+        assert_eq!(
+            key.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec!["created", "id"],
+            "the key should only hold the key attributes"
+        );
+    }
+
+    #[test]
+    fn test_batch_procedures_expect_an_array() {
+        let result = translate_procedure(
+            users_table(),
+            "delete_users_many",
+            serde_json::json!({ "keys": { "id": "a", "created": 1 } }),
+        );
+        assert!(
+            matches!(result, Err(Error::UnexpectedStructure(_))),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_insert_many_on_versioned_tables_puts_new_items_at_the_first_version() {
+        let operation = translate_procedure(
+            versioned_users_table(),
+            "insert_users_many",
+            serde_json::json!({ "objects": [{ "id": "a", "created": 1 }] }),
+        )
+        .unwrap();
+
+        let MutationOperation::PutItems {
+            items, expression, ..
+        } = operation
+        else {
+            panic!("expected conditional puts, got {operation:?}");
+        };
+        let [item] = items.as_slice() else {
+            panic!("expected a single item, got {items:?}");
+        };
+        assert_eq!(item["version"].value, serde_json::json!(1));
+        assert_eq!(
+            expression.condition_expression.as_deref(),
+            Some("attribute_not_exists(#pk)")
+        );
+        assert_eq!(expression.attribute_names["#pk"], "id");
+    }
+
+    #[test]
+    fn test_insert_many_on_versioned_tables_rejects_a_version() {
+        let result = translate_procedure(
+            versioned_users_table(),
+            "insert_users_many",
+            serde_json::json!({ "objects": [{ "id": "a", "created": 1, "version": 7 }] }),
+        );
+        assert!(
+            matches!(result, Err(Error::UnexpectedStructure(_))),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_update_on_versioned_tables_checks_and_increments_the_version() {
+        let operation = translate_procedure(
+            versioned_users_table(),
+            "update_users_by_key",
+            serde_json::json!({
+                "key": { "id": "a", "created": 1 },
+                "set": { "name": "Alice" },
+                "expected_version": 3,
+            }),
+        )
+        .unwrap();
+
+        let MutationOperation::UpdateItem {
+            expression,
+            version,
+            ..
+        } = operation
+        else {
+            panic!("expected an update, got {operation:?}");
+        };
+        assert_eq!(
+            expression.update_expression.as_deref(),
+            Some("SET #a0 = :a0, #v = #v + :one")
+        );
+        assert_eq!(
+            expression.condition_expression.as_deref(),
+            Some("#v = :expected_version")
+        );
+        assert_eq!(
+            expression.attribute_values[":expected_version"].value,
+            serde_json::json!(3)
+        );
+        let version = version.expect("the version should be checked");
+        assert_eq!(version.attribute, "version");
+        assert_eq!(version.expected, serde_json::json!(3));
+    }
+
+    #[test]
+    fn test_update_on_versioned_tables_requires_the_expected_version() {
+        let result = translate_procedure(
+            versioned_users_table(),
+            "update_users_by_key",
+            serde_json::json!({
+                "key": { "id": "a", "created": 1 },
+                "set": { "name": "Alice" },
+            }),
+        );
+        assert!(
+            matches!(result, Err(Error::ArgumentNotFound(ref name)) if name.as_str() == "expected_version"),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_delete_on_versioned_tables_checks_the_version() {
+        let operation = translate_procedure(
+            versioned_users_table(),
+            "delete_users_by_key",
+            serde_json::json!({
+                "key": { "id": "a", "created": 1 },
+                "expected_version": 3,
+            }),
+        )
+        .unwrap();
+
+        let MutationOperation::DeleteItem {
+            expression,
+            version,
+            ..
+        } = operation
+        else {
+            panic!("expected a delete, got {operation:?}");
+        };
+        assert_eq!(
+            expression.condition_expression.as_deref(),
+            Some("#v = :expected_version")
+        );
+        assert!(version.is_some());
+    }
+
+    #[test]
+    fn test_versioned_tables_have_no_batch_delete() {
+        let tables =
+            metadata::TablesInfo(BTreeMap::from([("users".into(), versioned_users_table())]));
+        let mutations = generate::generate(&tables);
+        assert!(!mutations.contains_key(&models::ProcedureName::from("delete_users_many")));
+        assert!(mutations.contains_key(&models::ProcedureName::from("insert_users_many")));
+    }
+}
@@ -0,0 +1,364 @@
+//! Work out how DynamoDB will run a SELECT statement from its WHERE clause.
+//!
+//! DynamoDB runs a PartiQL SELECT as a Query when the WHERE clause fixes the partition key of the
+//! table or index with an equality or an IN condition, and as a full Scan otherwise.
+
+use std::collections::BTreeSet;
+
+use ndc_models as models;
+use query_engine_metadata::metadata;
+use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan::{AccessPattern, ScanPolicy};
+
+/// Find the access pattern of a select over a collection of a table, or one of its global
+/// secondary indexes. Scans carry the scan policy of the table, which the executor enforces, so
+/// that explaining a denied query still shows the scan.
+pub fn analyse(
+    collection: &models::CollectionName,
+    table: &metadata::TableInfo,
+    gsi: Option<&sql::ast::Gsi>,
+    where_: &sql::ast::Where,
+) -> AccessPattern {
+    let table_name = table.table_name.clone();
+    let index = gsi.map(|sql::ast::Gsi(name)| name.clone());
+
+    let mut conditions = KeyConditions::default();
+    conditions.collect(&where_.0);
+
+    match key_attributes(table, gsi) {
+        Some((partition_key, sort_key)) if conditions.equalities.contains(partition_key) => {
+            // Items are only unique on the primary key of the table, not on index keys.
+            let is_key_lookup = index.is_none()
+                && sort_key.map_or(true, |sort_key| {
+                    sort_key.is_empty() || conditions.equalities.contains(sort_key)
+                });
+            if is_key_lookup {
+                AccessPattern::KeyLookup { table: table_name }
+            } else {
+                AccessPattern::Query {
+                    table: table_name,
+                    index,
+                }
+            }
+        }
+        Some((partition_key, _)) if conditions.in_lists.contains(partition_key) => {
+            AccessPattern::Query {
+                table: table_name,
+                index,
+            }
+        }
+        _ => AccessPattern::Scan {
+            table: table_name,
+            index,
+            suggested_keys: suggested_keys(collection, table, gsi),
+            policy: match table.scan_policy {
+                metadata::ScanPolicy::Allow => ScanPolicy::Allow,
+                metadata::ScanPolicy::Warn => ScanPolicy::Warn,
+                metadata::ScanPolicy::Deny => ScanPolicy::Deny,
+            },
+        },
+    }
+}
+
+/// The sort key DynamoDB can order the results of a select by, if any.
+/// DynamoDB only orders items within a single partition, so the WHERE clause must fix the
+/// partition key of the table or index with an equality.
+pub fn native_order_key(
+    table: &metadata::TableInfo,
+    gsi: Option<&sql::ast::Gsi>,
+    where_: &sql::ast::Where,
+) -> Option<String> {
+    let mut conditions = KeyConditions::default();
+    conditions.collect(&where_.0);
+
+    match key_attributes(table, gsi) {
+        Some((partition_key, Some(sort_key)))
+            if !sort_key.is_empty() && conditions.equalities.contains(partition_key) =>
+        {
+            Some(sort_key.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// The partition key of a table, or one of its global secondary indexes.
+pub fn partition_key<'a>(
+    table: &'a metadata::TableInfo,
+    gsi: Option<&sql::ast::Gsi>,
+) -> Option<&'a str> {
+    key_attributes(table, gsi).map(|(partition_key, _)| partition_key)
+}
+
+/// The partition and sort key of a table, or one of its global secondary indexes.
+fn key_attributes<'a>(
+    table: &'a metadata::TableInfo,
+    gsi: Option<&sql::ast::Gsi>,
+) -> Option<(&'a str, Option<&'a str>)> {
+    match gsi {
+        None => Some((table.partition_key.as_str(), Some(table.sort_key.as_str()))),
+        Some(sql::ast::Gsi(name)) => table
+            .gsi
+            .0
+            .get(name)
+            .map(|gsi| (gsi.partition_key.as_str(), gsi.sort_key.as_deref())),
+    }
+}
+
+/// The partition keys which would turn a Scan of a table or index into a Query: that of the
+/// table or index itself, then those of the table and its other indexes, followed by the
+/// collection to query instead.
+fn suggested_keys(
+    collection: &models::CollectionName,
+    table: &metadata::TableInfo,
+    gsi: Option<&sql::ast::Gsi>,
+) -> Vec<String> {
+    let mut keys = vec![];
+    if let Some((partition_key, _)) = key_attributes(table, gsi) {
+        keys.push(partition_key.to_string());
+    }
+    if gsi.is_some() {
+        keys.push(format!("{} ({collection})", table.partition_key));
+    }
+    for (index, gsi_info) in &table.gsi.0 {
+        if gsi.map_or(true, |sql::ast::Gsi(name)| name != index) {
+            keys.push(format!("{} ({collection}:{index})", gsi_info.partition_key));
+        }
+    }
+    keys
+}
+
+/// The attributes a conjunction of conditions restricts to known values.
+#[derive(Default)]
+struct KeyConditions {
+    /// Attributes compared with `=` to a value.
+    equalities: BTreeSet<String>,
+    /// Attributes restricted with `IN` to a list of values.
+    in_lists: BTreeSet<String>,
+}
+
+impl KeyConditions {
+    /// Walk the top-level conjunction of an expression.
+    /// Anything under an OR or a NOT could match other items, so it does not count.
+    fn collect(&mut self, expression: &sql::ast::Expression) {
+        match expression {
+            sql::ast::Expression::And { left, right } => {
+                self.collect(left);
+                self.collect(right);
+            }
+            sql::ast::Expression::BinaryOperation {
+                left,
+                operator: sql::ast::BinaryOperator(operator),
+                right,
+            } if operator == "=" => {
+                if let (Some(column), false) = (column_name(left), is_column(right)) {
+                    self.equalities.insert(column);
+                } else if let (Some(column), false) = (column_name(right), is_column(left)) {
+                    self.equalities.insert(column);
+                }
+            }
+            sql::ast::Expression::BinaryArrayOperation {
+                left,
+                operator: sql::ast::BinaryArrayOperator::In,
+                right,
+            } => {
+                if let (Some(column), false) = (column_name(left), right.iter().any(is_column)) {
+                    self.in_lists.insert(column);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The name of a top-level attribute referenced by an expression.
+fn column_name(expression: &sql::ast::Expression) -> Option<String> {
+    match expression {
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
+            name: sql::ast::ColumnName(name),
+            ..
+        }) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Whether the expression depends on an attribute of the item.
+fn is_column(expression: &sql::ast::Expression) -> bool {
+    matches!(
+        expression,
+        sql::ast::Expression::ColumnReference(_)
+            | sql::ast::Expression::NestedFieldSelect { .. }
+            | sql::ast::Expression::NestedElementSelect { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::test_helpers;
+
+    fn users() -> metadata::TableInfo {
+        test_helpers::metadata().tables.0[&models::CollectionName::from("users")].clone()
+    }
+
+    fn column(name: &str) -> sql::ast::Expression {
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
+            table: sql::ast::TableReference::DBTable {
+                table: sql::ast::TableName("users".to_string()),
+                gsi: None,
+            },
+            name: sql::ast::ColumnName(name.to_string()),
+        })
+    }
+
+    fn value(value: &str) -> sql::ast::Expression {
+        sql::ast::Expression::Value(sql::ast::Value::String(value.to_string()))
+    }
+
+    fn equals(left: sql::ast::Expression, right: sql::ast::Expression) -> sql::ast::Expression {
+        sql::ast::Expression::BinaryOperation {
+            left: Box::new(left),
+            operator: sql::ast::BinaryOperator("=".to_string()),
+            right: Box::new(right),
+        }
+    }
+
+    fn and(left: sql::ast::Expression, right: sql::ast::Expression) -> sql::ast::Expression {
+        sql::ast::Expression::And {
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn analyse_users(gsi: Option<&str>, condition: sql::ast::Expression) -> AccessPattern {
+        let gsi = gsi.map(|name| sql::ast::Gsi(name.to_string()));
+        analyse(
+            &"users".into(),
+            &users(),
+            gsi.as_ref(),
+            &sql::ast::Where(condition),
+        )
+    }
+
+    #[test]
+    fn test_equalities_on_the_primary_key_are_a_key_lookup() {
+        assert_eq!(
+            analyse_users(
+                None,
+                and(
+                    equals(column("id"), value("a")),
+                    equals(value("1"), column("created"))
+                )
+            ),
+            AccessPattern::KeyLookup {
+                table: "users".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_equality_or_in_list_on_the_partition_key_is_a_query() {
+        let query = AccessPattern::Query {
+            table: "users".to_string(),
+            index: None,
+        };
+        assert_eq!(analyse_users(None, equals(column("id"), value("a"))), query);
+        assert_eq!(
+            analyse_users(
+                None,
+                sql::ast::Expression::BinaryArrayOperation {
+                    left: Box::new(column("id")),
+                    operator: sql::ast::BinaryArrayOperator::In,
+                    right: vec![value("a"), value("b")],
+                }
+            ),
+            query
+        );
+    }
+
+    #[test]
+    fn test_equality_on_an_index_partition_key_is_a_query_on_the_index() {
+        assert_eq!(
+            analyse_users(Some("by_email"), equals(column("email"), value("a"))),
+            AccessPattern::Query {
+                table: "users".to_string(),
+                index: Some("by_email".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_conditions_that_may_match_other_items_are_a_scan() {
+        let alternatives = sql::ast::Expression::Or {
+            left: Box::new(equals(column("id"), value("a"))),
+            right: Box::new(equals(column("name"), value("a"))),
+        };
+        let with_another_attribute = equals(column("id"), column("name"));
+        let negated = sql::ast::Expression::Not(Box::new(equals(column("id"), value("a"))));
+        for condition in [alternatives, with_another_attribute, negated] {
+            assert!(
+                matches!(
+                    analyse_users(None, condition.clone()),
+                    AccessPattern::Scan { .. }
+                ),
+                "{condition:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_scans_suggest_keys_with_the_collection_to_query() {
+        let AccessPattern::Scan { suggested_keys, .. } =
+            analyse_users(None, equals(column("name"), value("a")))
+        else {
+            panic!("expected a scan");
+        };
+        assert_eq!(suggested_keys, vec!["id", "email (users:by_email)"]);
+
+        let AccessPattern::Scan { suggested_keys, .. } =
+            analyse_users(Some("by_email"), equals(column("name"), value("a")))
+        else {
+            panic!("expected a scan");
+        };
+        assert_eq!(suggested_keys, vec!["email", "id (users)"]);
+    }
+
+    #[test]
+    fn test_scans_carry_the_scan_policy_of_the_table() {
+        let mut table = users();
+        table.scan_policy = metadata::ScanPolicy::Deny;
+        let access_pattern = analyse(
+            &"users".into(),
+            &table,
+            None,
+            &sql::ast::Where(equals(column("name"), value("a"))),
+        );
+        assert!(matches!(
+            access_pattern,
+            AccessPattern::Scan {
+                policy: ScanPolicy::Deny,
+                ..
+            }
+        ));
+    }
+
+    // The scan policy is enforced when the query runs, so a denied query can still be explained.
+    #[test]
+    fn test_denied_scans_translate() {
+        let mut denying = test_helpers::metadata();
+        if let Some(table) = denying
+            .tables
+            .0
+            .get_mut(&models::CollectionName::from("users"))
+        {
+            table.scan_policy = metadata::ScanPolicy::Deny;
+        }
+        let request = test_helpers::query_request(
+            "users",
+            serde_json::json!({
+                "fields": { "id": { "type": "column", "column": "id" } },
+                "predicate": test_helpers::compare("name", "_eq", serde_json::json!("Alice")),
+            }),
+        );
+        assert!(test_helpers::translate(&denying, request).is_ok());
+    }
+}
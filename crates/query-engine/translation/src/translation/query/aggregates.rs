@@ -0,0 +1,112 @@
+//! Translate the 'aggregates' part of a query.
+//!
+//! DynamoDB has no aggregate functions, so the executor computes them over the items read by the
+//! query. Here we check that the aggregates are supported and find the attributes they read.
+
+use indexmap::IndexMap;
+
+use ndc_models as models;
+
+use crate::translation::error::Error;
+use crate::translation::helpers::{Env, TableNameAndReference};
+use query_engine_metadata::metadata::Type;
+use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan::{Aggregate, AggregateFunction};
+
+/// Translate the requested aggregates to the aggregates the executor computes, by alias.
+pub(crate) fn translate_aggregates(
+    env: &Env,
+    current_table: &TableNameAndReference,
+    aggregates: &IndexMap<models::FieldName, models::Aggregate>,
+) -> Result<Vec<(String, Aggregate)>, Error> {
+    let fields_info = env.lookup_fields_info(&current_table.name)?;
+
+    aggregates
+        .iter()
+        .map(|(alias, aggregate)| {
+            let aggregate = match aggregate {
+                models::Aggregate::StarCount {} => Aggregate::StarCount,
+                models::Aggregate::ColumnCount {
+                    column,
+                    field_path,
+                    distinct,
+                } => {
+                    check_field_path(field_path.as_deref())?;
+                    let column_info = fields_info.lookup_column(column)?;
+                    Aggregate::ColumnCount {
+                        attribute: column_info.name.0,
+                        distinct: *distinct,
+                    }
+                }
+                models::Aggregate::SingleColumn {
+                    column,
+                    field_path,
+                    function,
+                } => {
+                    check_field_path(field_path.as_deref())?;
+                    let column_info = fields_info.lookup_column(column)?;
+                    let function = match &column_info.r#type {
+                        Type::ScalarType(scalar_type) => {
+                            translate_aggregate_function(env, scalar_type, function)
+                        }
+                        Type::CompositeType(_) | Type::ArrayType(_) => {
+                            Err(Error::NonScalarTypeUsedInAggregate {
+                                r#type: column_info.r#type.clone(),
+                            })
+                        }
+                    }?;
+                    Aggregate::SingleColumn {
+                        attribute: column_info.name.0,
+                        function,
+                    }
+                }
+            };
+            Ok((alias.to_string(), aggregate))
+        })
+        .collect()
+}
+
+/// The attributes the aggregates read, so that the query selects them.
+pub(crate) fn aggregated_columns(
+    current_table: &TableNameAndReference,
+    aggregates: &[(String, Aggregate)],
+) -> Vec<(sql::ast::ColumnAlias, sql::ast::Expression)> {
+    let mut columns: Vec<(sql::ast::ColumnAlias, sql::ast::Expression)> = vec![];
+    for (_, aggregate) in aggregates {
+        match aggregate {
+            Aggregate::StarCount => {}
+            Aggregate::ColumnCount { attribute, .. }
+            | Aggregate::SingleColumn { attribute, .. } => {
+                if !columns.iter().any(|(alias, _)| &alias.name == attribute) {
+                    columns.push(sql::helpers::make_column(
+                        current_table.reference.clone(),
+                        sql::ast::ColumnName(attribute.clone()),
+                        sql::helpers::make_column_alias(attribute.clone()),
+                    ));
+                }
+            }
+        }
+    }
+    columns
+}
+
+/// Find an aggregate function of a scalar type.
+fn translate_aggregate_function(
+    env: &Env,
+    scalar_type: &models::ScalarTypeName,
+    function: &models::AggregateFunctionName,
+) -> Result<AggregateFunction, Error> {
+    env.lookup_aggregate_function(scalar_type, function)?;
+    AggregateFunction::from_name(function.as_str())
+        .ok_or_else(|| Error::NotImplementedYet(format!("the aggregate function '{function}'")))
+}
+
+/// Aggregates over nested fields are not supported.
+fn check_field_path(field_path: Option<&[models::FieldName]>) -> Result<(), Error> {
+    match field_path {
+        Some(path) if !path.is_empty() => Err(Error::NotImplementedYet(
+            "aggregates over nested fields".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,248 @@
+//! Entities of single-table designs: collections over the items of a table that are told apart
+//! by a discriminator, and whose attributes may hold values with a key prefix such as `USER#`.
+
+use ndc_models as models;
+use query_engine_metadata::metadata;
+use query_engine_sql::sql;
+
+use crate::translation::error::Error;
+use crate::translation::helpers::{CollectionInfo, Env, FieldsInfo, TableNameAndReference};
+
+/// The condition selecting the items of an entity from its table, if the collection is one.
+pub fn discriminator_condition(
+    env: &Env,
+    table: &TableNameAndReference,
+) -> Result<Option<sql::ast::Expression>, Error> {
+    let CollectionInfo::Table { info, .. } = env.lookup_collection(&table.name)? else {
+        return Ok(None);
+    };
+    let Some(entity) = &info.entity else {
+        return Ok(None);
+    };
+    let attribute = |name: &str| {
+        Box::new(sql::ast::Expression::ColumnReference(
+            sql::ast::ColumnReference::TableColumn {
+                table: table.reference.clone(),
+                name: sql::ast::ColumnName(name.to_string()),
+            },
+        ))
+    };
+    let condition = match &entity.discriminator {
+        metadata::Discriminator::AttributeValue {
+            attribute: name,
+            value,
+        } => sql::ast::Expression::BinaryOperation {
+            left: attribute(name),
+            operator: sql::ast::BinaryOperator("=".to_string()),
+            right: Box::new(sql::ast::Expression::Value(sql::ast::Value::String(
+                value.clone(),
+            ))),
+        },
+        metadata::Discriminator::KeyPrefix { attribute: name } => {
+            let key_prefix = entity.key_prefixes.get(name).ok_or_else(|| {
+                Error::InternalError(format!("no key prefix for the attribute '{name}'"))
+            })?;
+            sql::ast::Expression::FunctionCall {
+                function: sql::ast::Function::BeginsWith,
+                args: vec![
+                    *attribute(name),
+                    sql::ast::Expression::Value(sql::ast::Value::String(key_prefix.clone())),
+                ],
+            }
+        }
+    };
+    Ok(Some(condition))
+}
+
+/// The key prefix of an attribute of a collection, if the collection is an entity with one.
+pub fn key_prefix(fields_info: &FieldsInfo, attribute: &str) -> Option<String> {
+    match fields_info {
+        FieldsInfo::Table { info, .. } => info
+            .entity
+            .as_ref()
+            .and_then(|entity| entity.key_prefixes.get(attribute))
+            .cloned(),
+        FieldsInfo::NativeQuery { .. } => None,
+    }
+}
+
+/// The key prefix of the values compared with a column read whole, if it has one.
+pub fn comparison_key_prefix(
+    env: &Env,
+    table: &TableNameAndReference,
+    name: &models::FieldName,
+    field_path: Option<&Vec<models::FieldName>>,
+) -> Result<Option<String>, Error> {
+    if field_path.is_some_and(|field_path| !field_path.is_empty()) {
+        return Ok(None);
+    }
+    let fields_info = env.lookup_fields_info(&table.name)?;
+    let attribute = fields_info.lookup_column(name)?.name;
+    Ok(key_prefix(&fields_info, &attribute.0))
+}
+
+/// Add a key prefix to a value compared with a prefixed attribute.
+/// Values only known when the statement runs get their prefix when they are bound.
+pub fn add_key_prefix(key_prefix: &str, expression: sql::ast::Expression) -> sql::ast::Expression {
+    match expression {
+        sql::ast::Expression::Value(sql::ast::Value::String(value)) => {
+            sql::ast::Expression::Value(sql::ast::Value::String(format!("{key_prefix}{value}")))
+        }
+        sql::ast::Expression::Value(sql::ast::Value::Variable {
+            name,
+            attribute_type,
+            key_prefix: _,
+            custom_scalar,
+        }) => sql::ast::Expression::Value(sql::ast::Value::Variable {
+            name,
+            attribute_type,
+            key_prefix: Some(key_prefix.to_string()),
+            custom_scalar,
+        }),
+        expression => expression,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use query_engine_sql::sql::execution_plan::{AttributeType, Field};
+    use query_engine_sql::sql::string::{Param, SQL};
+
+    use crate::translation::test_helpers::{compare, entity_metadata, query_request, translate};
+
+    fn entity_sql(collection: &str, query: serde_json::Value) -> SQL {
+        translate(&entity_metadata(), query_request(collection, query))
+            .unwrap()
+            .query
+            .query_sql()
+    }
+
+    fn string(s: &str) -> Param {
+        Param::String(s.to_string())
+    }
+
+    #[test]
+    fn test_entities_read_the_items_of_their_discriminator() {
+        let fields = json!({ "name": { "type": "column", "column": "name" } });
+        let sql = entity_sql("customer", json!({ "fields": fields }));
+        assert!(sql.sql.contains("FROM app"), "{}", sql.sql);
+        assert!(sql.sql.contains("(type = ?)"), "{}", sql.sql);
+        assert_eq!(sql.params, [string("customer")]);
+
+        let fields = json!({ "total": { "type": "column", "column": "total" } });
+        let sql = entity_sql("order", json!({ "fields": fields }));
+        assert!(sql.sql.contains("begins_with(SK, ?)"), "{}", sql.sql);
+        assert_eq!(sql.params, [string("ORDER#")]);
+    }
+
+    #[test]
+    fn test_values_compared_with_prefixed_attributes_get_the_prefix() {
+        let sql = entity_sql(
+            "customer",
+            json!({
+                "fields": { "name": { "type": "column", "column": "name" } },
+                "predicate": compare("id", "_eq", json!("42")),
+            }),
+        );
+        assert!(sql.sql.contains("(PK = ?)"), "{}", sql.sql);
+        assert_eq!(sql.params, [string("customer"), string("CUSTOMER#42")]);
+
+        let sql = entity_sql(
+            "customer",
+            json!({
+                "fields": { "name": { "type": "column", "column": "name" } },
+                "predicate": compare("id", "_in", json!(["1", "2"])),
+            }),
+        );
+        assert!(sql.sql.contains("(PK IN [?, ?])"), "{}", sql.sql);
+        assert_eq!(
+            sql.params,
+            [
+                string("customer"),
+                string("CUSTOMER#1"),
+                string("CUSTOMER#2")
+            ]
+        );
+
+        // attributes without a prefix are compared as they are
+        let sql = entity_sql(
+            "customer",
+            json!({
+                "fields": { "name": { "type": "column", "column": "name" } },
+                "predicate": compare("name", "_eq", json!("Ada")),
+            }),
+        );
+        assert!(sql.sql.contains("(name = ?)"), "{}", sql.sql);
+        assert_eq!(sql.params, [string("customer"), string("Ada")]);
+    }
+
+    #[test]
+    fn test_variables_compared_with_prefixed_attributes_get_the_prefix_when_bound() {
+        let mut request = query_request(
+            "customer",
+            json!({
+                "fields": { "name": { "type": "column", "column": "name" } },
+                "predicate": {
+                    "type": "binary_comparison_operator",
+                    "column": { "type": "column", "name": "id", "path": [] },
+                    "operator": "_eq",
+                    "value": { "type": "variable", "name": "customer_id" },
+                },
+            }),
+        );
+        request["variables"] = json!([{ "customer_id": "42" }]);
+        let sql = translate(&entity_metadata(), request)
+            .unwrap()
+            .query
+            .query_sql();
+
+        assert!(sql.sql.contains("(PK = ?)"), "{}", sql.sql);
+        assert_eq!(
+            sql.params,
+            [
+                string("customer"),
+                Param::Variable {
+                    name: "customer_id".to_string(),
+                    attribute_type: AttributeType::String,
+                    key_prefix: Some("CUSTOMER#".to_string()),
+                    custom_scalar: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefixed_attributes_are_read_without_the_prefix() {
+        let plan = translate(
+            &entity_metadata(),
+            query_request(
+                "order",
+                json!({
+                    "fields": {
+                        "order_id": { "type": "column", "column": "order_id" },
+                        "total": { "type": "column", "column": "total" },
+                    },
+                }),
+            ),
+        )
+        .unwrap();
+        let fields = plan.query.rowset.fields.unwrap_or_default();
+
+        assert!(
+            fields.iter().any(|(alias, field)| alias == "order_id"
+                && matches!(
+                    field,
+                    Field::PrefixedColumn { attribute, key_prefix }
+                        if attribute == "SK" && key_prefix == "ORDER#"
+                )),
+            "{fields:?}"
+        );
+        assert!(
+            fields.iter().any(|(alias, field)| alias == "total"
+                && matches!(field, Field::Column { attribute, .. } if attribute == "total")),
+            "{fields:?}"
+        );
+    }
+}
@@ -8,36 +8,122 @@ use crate::translation::error::Error;
 use crate::translation::error::UnsupportedCapabilities;
 use crate::translation::helpers::FieldsInfo;
 use crate::translation::helpers::{Env, State, TableNameAndReference};
+use crate::translation::query::entities;
+use crate::translation::query::key_templates;
+use crate::translation::query::pagination;
+use crate::translation::query::relationships;
 use query_engine_metadata::metadata::{Type, TypeRepresentation};
 use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan;
 
-/// Translate the field-selection of a query to SQL.
+/// Translate the field-selection of a query to SQL, and the fields of the returned rows.
+/// Relationship fields read the attributes they are joined on.
 /// Because field selection may be nested this function is mutually recursive with
 /// 'translate_nested_field'.
 pub(crate) fn translate_fields(
     env: &Env,
-    _state: &mut State,
+    state: &mut State,
     fields: IndexMap<models::FieldName, models::Field>,
     current_table: &TableNameAndReference,
     from: sql::ast::From,
-) -> Result<sql::ast::Select, Error> {
+) -> Result<(sql::ast::Select, Vec<(String, execution_plan::Field)>), Error> {
     // find the table according to the metadata.
     let fields_info = env.lookup_fields_info(&current_table.name)?;
 
-    let columns: Vec<(sql::ast::ColumnAlias, sql::ast::Expression)> = fields
+    let (columns, row_fields): (
+        Vec<Vec<(sql::ast::ColumnAlias, sql::ast::Expression)>>,
+        Vec<_>,
+    ) = fields
         .into_iter()
         .map(|(alias, field)| match field {
             models::Field::Column {
                 column,
                 fields: None,
                 arguments,
-            } if arguments.is_empty() => unpack_and_wrap_fields(
-                env,
-                current_table,
-                &column,
-                sql::helpers::make_column_alias(alias.to_string()),
-                &fields_info,
-            ),
+            } if column.as_str() == pagination::CURSOR_FIELD
+                && arguments.is_empty()
+                && matches!(fields_info, FieldsInfo::Table { .. })
+                && fields_info.lookup_column(&column).is_err() =>
+            {
+                Ok((vec![], (alias.to_string(), execution_plan::Field::Cursor)))
+            }
+            models::Field::Column {
+                column,
+                fields: None,
+                arguments,
+            } if arguments.is_empty() => {
+                // virtual fields are split from the value of their attribute
+                if let Some((attribute, key_template, field)) =
+                    key_templates::lookup_virtual_field(&fields_info, &column)
+                {
+                    let select_column = sql::helpers::make_column(
+                        current_table.reference.clone(),
+                        sql::ast::ColumnName(attribute.clone()),
+                        sql::helpers::make_column_alias(alias.to_string()),
+                    );
+                    let field =
+                        key_templates::translate_field(attribute, key_template, &column, field);
+                    return Ok((vec![select_column], (alias.to_string(), field)));
+                }
+                let attribute = fields_info.lookup_column(&column)?.name.0;
+                let column = unpack_and_wrap_fields(
+                    env,
+                    current_table,
+                    &column,
+                    sql::helpers::make_column_alias(alias.to_string()),
+                    &fields_info,
+                )?;
+                let field = match entities::key_prefix(&fields_info, &attribute) {
+                    None => execution_plan::Field::Column {
+                        attribute,
+                        fields: None,
+                    },
+                    Some(key_prefix) => execution_plan::Field::PrefixedColumn {
+                        attribute,
+                        key_prefix,
+                    },
+                };
+                Ok((vec![column], (alias.to_string(), field)))
+            }
+            models::Field::Column {
+                column,
+                fields: Some(nested_field),
+                arguments,
+            } if arguments.is_empty() => {
+                let column_info = fields_info.lookup_column(&column)?;
+                let (nested_field, paths) =
+                    translate_nested_field(env, &column, &column_info.r#type, nested_field)?;
+                // read only the requested sub-attributes
+                let columns = paths
+                    .into_iter()
+                    .map(|path| {
+                        let (alias, expression) = sql::helpers::make_column(
+                            current_table.reference.clone(),
+                            column_info.name.clone(),
+                            sql::helpers::make_column_alias(alias.to_string()),
+                        );
+                        (
+                            alias,
+                            path.into_iter().fold(expression, |expression, field| {
+                                sql::ast::Expression::NestedFieldSelect {
+                                    expression: Box::new(expression),
+                                    nested_field: sql::ast::NestedField(field),
+                                }
+                            }),
+                        )
+                    })
+                    .collect();
+                Ok((
+                    columns,
+                    (
+                        alias.to_string(),
+                        execution_plan::Field::Column {
+                            attribute: column_info.name.0,
+                            fields: Some(nested_field),
+                        },
+                    ),
+                ))
+            }
             models::Field::Column {
                 column: _,
                 fields: _,
@@ -45,15 +131,212 @@ pub(crate) fn translate_fields(
             } => Err(Error::CapabilityNotSupported(
                 UnsupportedCapabilities::FieldArguments,
             )),
-            ndc_models::Field::Relationship { .. } => todo!(),
+            models::Field::Relationship {
+                query,
+                relationship,
+                arguments,
+            } => {
+                let (columns, field) = relationships::translate_relationship_field(
+                    env,
+                    state,
+                    current_table,
+                    &relationship,
+                    &arguments,
+                    &query,
+                )?;
+                Ok((columns, (alias.to_string(), field)))
+            }
         })
-        .collect::<Result<Vec<_>, Error>>()?;
+        .collect::<Result<Vec<_>, Error>>()?
+        .into_iter()
+        .unzip();
 
-    let mut select = sql::helpers::simple_select(columns);
+    // the cursor is not read from the items
+    let mut select = sql::helpers::simple_select(columns.into_iter().flatten().collect());
 
     select.from = Some(from);
 
-    Ok(select)
+    Ok((select, row_fields))
+}
+
+/// Translate the selection of nested fields of a value of the given type, and find the document
+/// paths, relative to the value, to read. An empty path reads the whole value.
+///
+/// Lists cannot be projected without indices, so the fields of their elements are selected from
+/// the whole list.
+fn translate_nested_field(
+    env: &Env,
+    field_name: &models::FieldName,
+    r#type: &Type,
+    nested_field: models::NestedField,
+) -> Result<(execution_plan::NestedField, Vec<Vec<String>>), Error> {
+    match nested_field {
+        models::NestedField::Object(models::NestedObject { fields }) => {
+            let Type::CompositeType(type_name) = r#type else {
+                return Err(Error::NestedFieldNotOfCompositeType {
+                    field_name: field_name.clone(),
+                    actual_type: r#type.clone(),
+                });
+            };
+            let composite_type = env.lookup_composite_type(type_name)?;
+            let mut paths = vec![];
+            let fields = fields
+                .into_iter()
+                .map(|(alias, field)| match field {
+                    models::Field::Column {
+                        column,
+                        fields,
+                        arguments,
+                    } if arguments.is_empty() => {
+                        let field_info = composite_type.fields.get(&column).ok_or_else(|| {
+                            Error::FieldNotFoundInCompositeType(column.clone(), type_name.clone())
+                        })?;
+                        let fields = match fields {
+                            None => {
+                                paths.push(vec![field_info.field_name.clone()]);
+                                None
+                            }
+                            Some(nested_field) => {
+                                let (nested_field, nested_paths) = translate_nested_field(
+                                    env,
+                                    &column,
+                                    &field_info.r#type,
+                                    nested_field,
+                                )?;
+                                paths.extend(nested_paths.into_iter().map(|mut path| {
+                                    path.insert(0, field_info.field_name.clone());
+                                    path
+                                }));
+                                Some(nested_field)
+                            }
+                        };
+                        Ok((
+                            alias.to_string(),
+                            execution_plan::Field::Column {
+                                attribute: field_info.field_name.clone(),
+                                fields,
+                            },
+                        ))
+                    }
+                    models::Field::Column { .. } => Err(Error::CapabilityNotSupported(
+                        UnsupportedCapabilities::FieldArguments,
+                    )),
+                    models::Field::Relationship { .. } => Err(Error::NotImplementedYet(
+                        "relationships in nested fields".to_string(),
+                    )),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            // an empty selection still returns an object for every map
+            if paths.is_empty() {
+                paths.push(vec![]);
+            }
+            Ok((execution_plan::NestedField::Object(fields), paths))
+        }
+        models::NestedField::Array(models::NestedArray { fields }) => {
+            let Type::ArrayType(element_type) = r#type else {
+                return Err(Error::NestedFieldNotOfArrayType {
+                    field_name: field_name.clone(),
+                    actual_type: r#type.clone(),
+                });
+            };
+            let (nested_field, _) = translate_nested_field(env, field_name, element_type, *fields)?;
+            Ok((
+                execution_plan::NestedField::Array(Box::new(nested_field)),
+                vec![vec![]],
+            ))
+        }
+    }
+}
+
+/// Remove the columns of a select list which read a document path another column reads as well,
+/// as DynamoDB rejects overlapping paths, and return the nested paths left.
+///
+/// Nested paths whose last step has the name of another column read are replaced by their
+/// top-level attribute, so that the items DynamoDB returns are not ambiguous.
+pub(crate) fn merge_document_paths(select: &mut sql::ast::Select) -> Vec<Vec<String>> {
+    let sql::ast::SelectList::SelectList(columns) = &mut select.select_list else {
+        return vec![];
+    };
+    loop {
+        let paths = columns
+            .iter()
+            .map(|(_, expression)| document_path(expression))
+            .collect::<Vec<_>>();
+        let is_covered = |index: usize| {
+            let Some(path) = &paths[index] else {
+                return false;
+            };
+            paths.iter().enumerate().any(|(other_index, other)| {
+                other.as_ref().is_some_and(|other| {
+                    path.starts_with(other) && (other.len() < path.len() || other_index < index)
+                })
+            })
+        };
+        let mut index = 0;
+        columns.retain(|_| {
+            index += 1;
+            !is_covered(index - 1)
+        });
+
+        let paths = columns
+            .iter()
+            .map(|(_, expression)| document_path(expression))
+            .collect::<Vec<_>>();
+        let names = paths.iter().flatten().filter_map(|path| path.last());
+        let ambiguous = paths.iter().position(|path| {
+            path.as_ref().is_some_and(|path| {
+                path.len() > 1
+                    && names
+                        .clone()
+                        .filter(|name| path.last() == Some(name))
+                        .count()
+                        > 1
+            })
+        });
+        match ambiguous {
+            None => {
+                return paths
+                    .into_iter()
+                    .flatten()
+                    .filter(|path| path.len() > 1)
+                    .collect()
+            }
+            Some(index) => {
+                let (alias, expression) = columns.remove(index);
+                columns.insert(index, (alias, top_level_column(expression)));
+            }
+        }
+    }
+}
+
+/// The document path a column reads, if it reads an attribute or a nested field of one.
+pub(crate) fn document_path(expression: &sql::ast::Expression) -> Option<Vec<String>> {
+    match expression {
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
+            name,
+            ..
+        }) => Some(vec![name.0.clone()]),
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::AliasedColumn {
+            column,
+            ..
+        }) => Some(vec![column.name.clone()]),
+        sql::ast::Expression::NestedFieldSelect {
+            expression,
+            nested_field,
+        } => document_path(expression).map(|mut path| {
+            path.push(nested_field.0.clone());
+            path
+        }),
+        _ => None,
+    }
+}
+
+/// The top-level attribute of a nested document path.
+fn top_level_column(expression: sql::ast::Expression) -> sql::ast::Expression {
+    match expression {
+        sql::ast::Expression::NestedFieldSelect { expression, .. } => top_level_column(*expression),
+        expression => expression,
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -86,10 +369,19 @@ fn unpack_and_wrap_fields(
                 wrap_in_type_representation(expression, column_type_representation),
             ))
         }
+        // Maps are returned as they are.
+        Type::CompositeType(_) => Ok(sql::helpers::make_column(
+            current_table.reference.clone(),
+            column_info.name.clone(),
+            alias,
+        )),
         Type::ArrayType(ref type_boxed) => match **type_boxed {
-            Type::ArrayType(_) => Err(Error::NestedArraysNotSupported {
-                field_name: column.clone(),
-            }),
+            // Lists of maps and lists are returned whole as they are.
+            Type::ArrayType(_) | Type::CompositeType(_) => Ok(sql::helpers::make_column(
+                current_table.reference.clone(),
+                column_info.name.clone(),
+                alias,
+            )),
             Type::ScalarType(ref scalar_type) => {
                 let inner_column_type_representation = env.lookup_type_representation(scalar_type);
                 let (alias, expression) = sql::helpers::make_column(
@@ -184,3 +476,92 @@ fn get_type_representation_cast_type(
         | TypeRepresentation::Enum(_) => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use query_engine_sql::sql;
+    use serde_json::json;
+
+    use super::merge_document_paths;
+    use crate::translation::test_helpers::{metadata, query_request, translate};
+
+    /// A select reading the given document paths.
+    fn select(paths: &[&[&str]]) -> sql::ast::Select {
+        let table = sql::ast::TableReference::DBTable {
+            table: sql::ast::TableName("users".to_string()),
+            gsi: None,
+        };
+        sql::helpers::simple_select(
+            paths
+                .iter()
+                .enumerate()
+                .map(|(index, path)| {
+                    let (alias, expression) = sql::helpers::make_column(
+                        table.clone(),
+                        sql::ast::ColumnName(path[0].to_string()),
+                        sql::helpers::make_column_alias(format!("column_{index}")),
+                    );
+                    let expression = path[1..].iter().fold(expression, |expression, field| {
+                        sql::ast::Expression::NestedFieldSelect {
+                            expression: Box::new(expression),
+                            nested_field: sql::ast::NestedField((*field).to_string()),
+                        }
+                    });
+                    (alias, expression)
+                })
+                .collect(),
+        )
+    }
+
+    fn read_paths(select: &sql::ast::Select) -> Vec<Vec<String>> {
+        match &select.select_list {
+            sql::ast::SelectList::SelectList(columns) => columns
+                .iter()
+                .filter_map(|(_, expression)| super::document_path(expression))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_paths_covered_by_their_attribute_are_not_read() {
+        let covered: [&[&[&str]]; 2] = [&[&["a"], &["a", "b"]], &[&["a", "b"], &["a"]]];
+        for paths in covered {
+            let mut select = select(paths);
+            let nested = merge_document_paths(&mut select);
+            assert_eq!(read_paths(&select), [["a"]]);
+            assert!(nested.is_empty(), "{nested:?}");
+        }
+    }
+
+    #[test]
+    fn test_sibling_paths_are_all_read() {
+        let mut select = select(&[&["a", "b"], &["a", "c"], &["a", "b"]]);
+        let nested = merge_document_paths(&mut select);
+        let expected = [["a", "b"], ["a", "c"]];
+        assert_eq!(read_paths(&select), expected);
+        assert_eq!(nested, expected);
+    }
+
+    #[test]
+    fn test_paths_ending_with_the_same_name_read_their_attribute() {
+        let mut select = select(&[&["a", "c"], &["b", "c"]]);
+        let nested = merge_document_paths(&mut select);
+        assert_eq!(read_paths(&select), [vec!["a"], vec!["b", "c"]]);
+        assert_eq!(nested, [["b", "c"]]);
+    }
+
+    #[test]
+    fn test_lists_of_lists_are_returned_whole() {
+        let request = query_request(
+            "users",
+            json!({ "fields": { "matrix": { "type": "column", "column": "matrix" } } }),
+        );
+        let plan = translate(&metadata(), request).unwrap();
+        assert!(
+            plan.query.query_sql().sql.contains("matrix"),
+            "{}",
+            plan.query.query_sql().sql
+        );
+    }
+}
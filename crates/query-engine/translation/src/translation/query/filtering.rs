@@ -4,16 +4,21 @@ use ndc_models as models;
 use query_engine_metadata::metadata;
 use query_engine_sql::sql::helpers::where_exists_select;
 
+use super::access_pattern;
+use super::entities;
+use super::key_templates;
+use super::operators;
 use super::root;
 use super::values;
 use crate::translation::error::Error;
-use crate::translation::helpers::wrap_in_field_path;
+use crate::translation::helpers::{translate_field_path, FieldPath};
 use crate::translation::helpers::{
-    ColumnInfo, Env, RootAndCurrentTables, State, TableNameAndReference,
+    CollectionInfo, ColumnInfo, Env, RootAndCurrentTables, State, TableNameAndReference,
 };
 use query_engine_metadata::metadata::database;
 use query_engine_sql::sql;
-use std::collections::VecDeque;
+use query_engine_sql::sql::execution_plan;
+use std::collections::{BTreeMap, VecDeque};
 use std::vec;
 
 /// Translate a boolean expression to a SQL expression.
@@ -54,23 +59,23 @@ pub fn translate_expression_with_joins(
 ) -> Result<(sql::ast::Expression, Vec<sql::ast::Join>), Error> {
     match predicate {
         models::Expression::And { expressions } => {
+            // equalities on the fields of key templates are rebuilt into conditions on the keys
+            let (key_conditions, expressions) =
+                key_templates::translate_key_conditions(env, root_and_current_tables, expressions)?;
             let mut acc_joins = vec![];
             let and_exprs = expressions
-                .iter()
+                .into_iter()
                 .map(|expr| {
                     translate_expression_with_joins(env, state, root_and_current_tables, expr)
                 })
-                .try_fold(
-                    sql::ast::Expression::Value(sql::ast::Value::Bool(true)),
-                    |acc, expr| {
-                        let (right, right_joins) = expr?;
-                        acc_joins.extend(right_joins);
-                        Ok(sql::ast::Expression::And {
-                            left: Box::new(acc),
-                            right: Box::new(right),
-                        })
-                    },
-                )?;
+                .try_fold(conjunction(key_conditions), |acc, expr| {
+                    let (right, right_joins) = expr?;
+                    acc_joins.extend(right_joins);
+                    Ok(sql::ast::Expression::And {
+                        left: Box::new(acc),
+                        right: Box::new(right),
+                    })
+                })?;
             Ok((and_exprs, acc_joins))
         }
         models::Expression::Or { expressions } => {
@@ -98,6 +103,20 @@ pub fn translate_expression_with_joins(
                 translate_expression_with_joins(env, state, root_and_current_tables, expression)?;
             Ok((sql::ast::Expression::Not(Box::new(expr)), joins))
         }
+        models::Expression::BinaryComparisonOperator { .. }
+            if key_templates::is_virtual_field_comparison(
+                env,
+                root_and_current_tables,
+                predicate,
+            )? =>
+        {
+            let (key_conditions, _) = key_templates::translate_key_conditions(
+                env,
+                root_and_current_tables,
+                std::slice::from_ref(predicate),
+            )?;
+            Ok((conjunction(key_conditions), vec![]))
+        }
         models::Expression::BinaryComparisonOperator {
             column,
             operator,
@@ -105,6 +124,25 @@ pub fn translate_expression_with_joins(
         } => {
             let left_typ = get_comparison_target_type(env, root_and_current_tables, column)?;
             let op = env.lookup_comparison_operator(&left_typ, operator)?;
+            // values compared with a prefixed attribute of an entity hold the prefix as well
+            let key_prefix = match op.operator_kind {
+                metadata::OperatorKind::Equal
+                | metadata::OperatorKind::In
+                | metadata::OperatorKind::Custom
+                | metadata::OperatorKind::BeginsWith
+                | metadata::OperatorKind::Between => {
+                    comparison_key_prefix(env, root_and_current_tables, column)?
+                }
+                metadata::OperatorKind::Contains
+                | metadata::OperatorKind::Size
+                | metadata::OperatorKind::AttributeType
+                | metadata::OperatorKind::IsMissing
+                | metadata::OperatorKind::Exists => None,
+            };
+            let with_key_prefix = |expression: sql::ast::Expression| match &key_prefix {
+                Some(key_prefix) => entities::add_key_prefix(key_prefix, expression),
+                None => expression,
+            };
             if op.operator_kind == metadata::OperatorKind::In {
                 let mut joins = vec![];
                 let (left, left_joins) =
@@ -151,7 +189,7 @@ pub fn translate_expression_with_joins(
                                             &database::Type::ScalarType(left_typ.clone()),
                                         )?;
                                         joins.extend(right_joins);
-                                        Ok(right)
+                                        Ok(with_key_prefix(right))
                                     })
                                     .collect::<Result<Vec<sql::ast::Expression>, Error>>()?;
 
@@ -167,30 +205,12 @@ pub fn translate_expression_with_joins(
                         }
                         _ => Err(Error::TypeMismatch(json_value.clone(), left_typ)),
                     },
-                    models::ComparisonValue::Variable { .. } => {
-                        let array_type = database::Type::ArrayType(Box::new(
-                            database::Type::ScalarType(left_typ),
-                        ));
-                        let (right, right_joins) = translate_comparison_value(
-                            env,
-                            state,
-                            root_and_current_tables,
-                            value,
-                            &array_type,
-                        )?;
-                        joins.extend(right_joins);
-
-                        let right = Box::new(make_unnest_subquery(state, right));
-
-                        Ok((
-                            sql::ast::Expression::BinaryOperation {
-                                left: Box::new(left),
-                                operator: sql::ast::BinaryOperator(op.operator_name.clone()),
-                                right,
-                            },
-                            joins,
-                        ))
-                    }
+                    // PartiQL takes the values of IN as a list of parameters, which a single
+                    // variable cannot be bound to
+                    models::ComparisonValue::Variable { .. } => Err(Error::NotImplementedYet(
+                        "'_in' with a variable, as PartiQL needs each value of the list bound on its own"
+                            .to_string(),
+                    )),
                 }
             } else {
                 let mut joins = vec![];
@@ -198,61 +218,84 @@ pub fn translate_expression_with_joins(
                     translate_comparison_target(env, state, root_and_current_tables, column)?;
                 joins.extend(left_joins);
 
-                let (right, right_joins) = translate_comparison_value(
-                    env,
-                    state,
-                    root_and_current_tables,
-                    value,
-                    &database::Type::ScalarType(op.argument_type.clone()),
-                )?;
-                joins.extend(right_joins);
-
-                if op.is_infix {
-                    Ok((
-                        sql::ast::Expression::BinaryOperation {
-                            left: Box::new(left),
-                            operator: sql::ast::BinaryOperator(op.operator_name.clone()),
-                            right: Box::new(right),
-                        },
-                        joins,
-                    ))
+                let argument_type = database::Type::ScalarType(op.argument_type.clone());
+                let right = if op.operator_kind == metadata::OperatorKind::Between {
+                    // the bounds are given as a two element array
+                    match value {
+                        models::ComparisonValue::Scalar {
+                            value: serde_json::Value::Array(bounds),
+                        } if bounds.len() == 2 => bounds
+                            .iter()
+                            .map(|bound| {
+                                values::translate_json_value(env, state, bound, &argument_type)
+                                    .map(with_key_prefix)
+                            })
+                            .collect::<Result<Vec<sql::ast::Expression>, Error>>()?,
+                        models::ComparisonValue::Scalar { value: json_value } => {
+                            return Err(Error::TypeMismatch(
+                                json_value.clone(),
+                                op.argument_type.clone(),
+                            ))
+                        }
+                        models::ComparisonValue::Column { .. }
+                        | models::ComparisonValue::Variable { .. } => {
+                            return Err(Error::NotImplementedYet(
+                                "BETWEEN bounds that are not literal values".to_string(),
+                            ))
+                        }
+                    }
                 } else {
-                    Ok((
-                        sql::ast::Expression::FunctionCall {
-                            function: sql::ast::Function::Unknown(op.operator_name.clone()),
-                            args: vec![left, right],
-                        },
-                        joins,
-                    ))
-                }
+                    let (right, right_joins) = translate_comparison_value(
+                        env,
+                        state,
+                        root_and_current_tables,
+                        value,
+                        &argument_type,
+                    )?;
+                    joins.extend(right_joins);
+                    vec![with_key_prefix(right)]
+                };
+
+                Ok((operators::translate_comparison(op, left, right)?, joins))
             }
         }
 
         models::Expression::Exists {
             in_collection,
             predicate,
-        } => match predicate {
-            None => Ok((sql::helpers::true_expr(), vec![])),
-            Some(predicate) => Ok((
+        } => {
+            // without a predicate, any item of the collection matches
+            let any = models::Expression::And {
+                expressions: vec![],
+            };
+            Ok((
                 translate_exists_in_collection(
                     env,
                     state,
                     root_and_current_tables,
                     in_collection.clone(),
-                    predicate,
+                    predicate.as_deref().unwrap_or(&any),
                 )?,
                 vec![],
-            )),
-        },
+            ))
+        }
         models::Expression::UnaryComparisonOperator { column, operator } => match operator {
+            // DynamoDB tells an attribute holding NULL apart from a missing one, both of which
+            // are null to the engine. `_is_missing` and `_exists` tell them apart.
             models::UnaryComparisonOperator::IsNull => {
                 let (value, joins) =
                     translate_comparison_target(env, state, root_and_current_tables, column)?;
 
                 Ok((
-                    sql::ast::Expression::UnaryOperation {
-                        expression: Box::new(value),
-                        operator: sql::ast::UnaryOperator::IsNull,
+                    sql::ast::Expression::Or {
+                        left: Box::new(sql::ast::Expression::UnaryOperation {
+                            expression: Box::new(value.clone()),
+                            operator: sql::ast::UnaryOperator::IsNull,
+                        }),
+                        right: Box::new(sql::ast::Expression::UnaryOperation {
+                            expression: Box::new(value),
+                            operator: sql::ast::UnaryOperator::IsMissing,
+                        }),
                     },
                     joins,
                 ))
@@ -421,6 +464,18 @@ pub fn translate_expression_with_joins(
 // }
 // }
 
+/// The conjunction of a list of conditions, true when there are none.
+fn conjunction(expressions: Vec<sql::ast::Expression>) -> sql::ast::Expression {
+    expressions
+        .into_iter()
+        .fold(sql::helpers::true_expr(), |left, right| {
+            sql::ast::Expression::And {
+                left: Box::new(left),
+                right: Box::new(right),
+            }
+        })
+}
+
 /// translate a comparison target.
 fn translate_comparison_target(
     env: &Env,
@@ -434,47 +489,75 @@ fn translate_comparison_target(
             path: _,
             field_path,
         } => {
-            let RootAndCurrentTables { root_table, .. } = root_and_current_tables;
-
-            // get the unrelated table information from the metadata.
-            let collection_info = env.lookup_collection(&root_table.name)?;
-            let ColumnInfo { name, .. } = collection_info.lookup_column(name)?;
-
-            Ok((
-                wrap_in_field_path(
-                    &field_path.into(),
-                    sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
-                        table: root_table.reference.clone(),
-                        name,
-                    }),
-                ),
-                vec![],
-            ))
+            // A column of the current collection, which inside an exists is the collection
+            // searched rather than the root one. Its type is looked up there too.
+            let RootAndCurrentTables { current_table, .. } = root_and_current_tables;
+            let (expression, _) =
+                translate_column_with_field_path(env, current_table, name, &field_path.into())?;
+            Ok((expression, vec![]))
         }
 
         // Compare a column from the root table.
         models::ComparisonTarget::RootCollectionColumn { name, field_path } => {
             let RootAndCurrentTables { root_table, .. } = root_and_current_tables;
-            // get the unrelated table information from the metadata.
-            let collection_info = env.lookup_collection(&root_table.name)?;
-
-            // find the requested column in the tables columns.
-            let ColumnInfo { name, .. } = collection_info.lookup_column(name)?;
+            let (expression, _) =
+                translate_column_with_field_path(env, root_table, name, &field_path.into())?;
+            Ok((expression, vec![]))
+        }
+    }
+}
 
-            Ok((
-                wrap_in_field_path(
-                    &field_path.into(),
-                    sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
-                        table: root_table.reference.clone(),
-                        name,
-                    }),
-                ),
-                vec![],
-            ))
+/// The key prefix of the values compared with a comparison target, if it reads a prefixed
+/// attribute of an entity whole.
+fn comparison_key_prefix(
+    env: &Env,
+    root_and_current_tables: &RootAndCurrentTables,
+    column: &models::ComparisonTarget,
+) -> Result<Option<String>, Error> {
+    match column {
+        models::ComparisonTarget::Column {
+            name, field_path, ..
+        } => entities::comparison_key_prefix(
+            env,
+            &root_and_current_tables.current_table,
+            name,
+            field_path.as_ref(),
+        ),
+        models::ComparisonTarget::RootCollectionColumn { name, field_path } => {
+            entities::comparison_key_prefix(
+                env,
+                &root_and_current_tables.root_table,
+                name,
+                field_path.as_ref(),
+            )
         }
     }
 }
 
+/// Translate a column of a table down its nested field path to a document path, and find the
+/// type at the end of the path.
+fn translate_column_with_field_path(
+    env: &Env,
+    table: &TableNameAndReference,
+    name: &models::FieldName,
+    field_path: &FieldPath,
+) -> Result<(sql::ast::Expression, database::Type), Error> {
+    // get the unrelated table information from the metadata.
+    let collection_info = env.lookup_collection(&table.name)?;
+    // find the requested column in the tables columns.
+    let ColumnInfo { name, r#type } = collection_info.lookup_column(name)?;
+
+    translate_field_path(
+        env,
+        field_path,
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
+            table: table.reference.clone(),
+            name,
+        }),
+        &r#type,
+    )
+}
+
 /// translate a comparison value.
 fn translate_comparison_value(
     env: &Env,
@@ -491,13 +574,17 @@ fn translate_comparison_value(
             values::translate_json_value(env, state, json_value, typ)?,
             vec![],
         )),
-        models::ComparisonValue::Variable { name: _ } => todo!("Variables are not supported"),
+        models::ComparisonValue::Variable { name } => {
+            Ok((values::translate_variable(env, name, typ)?, vec![]))
+        }
     }
 }
 
 /// Translate an EXISTS clause into a SQL subquery of the following form:
 ///
 /// > EXISTS (SELECT 1 as 'one' FROM <table> AS <alias> WHERE <predicate>)
+///
+/// or, across a relationship, into the placeholder of a semi-join on the target table.
 pub fn translate_exists_in_collection(
     env: &Env,
     state: &mut State,
@@ -508,11 +595,31 @@ pub fn translate_exists_in_collection(
     match in_collection {
         models::ExistsInCollection::Unrelated {
             collection,
-            arguments: _,
+            arguments,
         } => {
+            let arguments = arguments
+                .into_iter()
+                .map(|(name, argument)| match argument {
+                    models::RelationshipArgument::Variable { name: variable } => {
+                        Ok((name, models::Argument::Variable { name: variable }))
+                    }
+                    models::RelationshipArgument::Literal { value } => {
+                        Ok((name, models::Argument::Literal { value }))
+                    }
+                    models::RelationshipArgument::Column { .. } => Err(Error::NotImplementedYet(
+                        "column arguments of collections".to_string(),
+                    )),
+                })
+                .collect::<Result<BTreeMap<_, _>, Error>>()?;
             // create a from clause and get a reference of inner query.
-            let (table, from_clause) =
-                root::make_from_clause_and_reference(&collection, None, env, state, None)?;
+            let (table, from_clause) = root::make_from_clause_and_reference(
+                &collection,
+                None,
+                &arguments,
+                env,
+                state,
+                None,
+            )?;
 
             // CockroachDB doesn't like empty selects, so we do "SELECT 1 as 'one' ..."
             let column_alias = sql::helpers::make_column_alias("one".to_string());
@@ -540,7 +647,11 @@ pub fn translate_exists_in_collection(
                 &new_root_and_current_tables,
                 predicate,
             )?;
-            select.where_ = sql::ast::Where(expr);
+            select.where_ = sql::ast::Where(with_discriminator(
+                env,
+                &new_root_and_current_tables.current_table,
+                expr,
+            )?);
 
             // select.joins = expr_joins;
 
@@ -549,18 +660,139 @@ pub fn translate_exists_in_collection(
                 select: Box::new(select),
             })
         }
-        // We get a relationship name in exists, query the target table directly,
-        // and build a WHERE clause that contains the join conditions and the specified
-        // EXISTS condition.
+        // DynamoDB cannot run subqueries, so we query the target table for the join keys of
+        // the items matching the predicate, and the executor filters the current table on them
+        // in place of the semi-join placeholder.
         models::ExistsInCollection::Related {
-            relationship: _,
-            arguments: _,
-        } => todo!("Relationship is supported"),
-        models::ExistsInCollection::NestedCollection {
-            column_name: _,
-            arguments: _,
-            field_path: _,
-        } => todo!("Filter by nested collection is not implemented yet"),
+            relationship,
+            arguments,
+        } => {
+            let relationship = env.lookup_relationship(&relationship)?;
+            if !arguments.is_empty() || !relationship.arguments.is_empty() {
+                return Err(Error::NotImplementedYet(
+                    "arguments of relationships".to_string(),
+                ));
+            }
+            if relationship.column_mapping.is_empty() {
+                return Err(Error::NotImplementedYet(
+                    "exists across relationships without a column mapping".to_string(),
+                ));
+            }
+            // the statement on the target table cannot see the items of the root table
+            if references_root_collection(predicate) {
+                return Err(Error::NotImplementedYet(
+                    "comparisons with columns of the root collection in exists across relationships"
+                        .to_string(),
+                ));
+            }
+
+            let (target_name, gsi) = root::parse_collection_name(&relationship.target_collection)?;
+            let target_collection = env.lookup_collection(&target_name)?;
+            let CollectionInfo::Table { info, .. } = &target_collection else {
+                return Err(Error::NotImplementedYet(
+                    "exists across relationships to native queries".to_string(),
+                ));
+            };
+            let (table, from_clause) = root::make_from_clause_and_reference(
+                &target_name,
+                gsi.clone(),
+                &BTreeMap::new(),
+                env,
+                state,
+                None,
+            )?;
+            let source_info =
+                env.lookup_fields_info(&root_and_current_tables.current_table.name)?;
+
+            let mut select_cols = vec![];
+            let mut join = vec![];
+            for (source_column, target_column) in &relationship.column_mapping {
+                let source = source_info.lookup_column(source_column)?.name;
+                let target = target_collection.lookup_column(target_column)?.name;
+                let (alias, target_expression) = sql::helpers::make_column(
+                    table.reference.clone(),
+                    target.clone(),
+                    sql::helpers::make_column_alias(target.0.clone()),
+                );
+                select_cols.push((alias, target_expression.clone()));
+                join.push(execution_plan::JoinColumn {
+                    source: source.0.clone(),
+                    target: target.0,
+                    source_column: sql::helpers::make_column(
+                        root_and_current_tables.current_table.reference.clone(),
+                        source.clone(),
+                        sql::helpers::make_column_alias(source.0),
+                    )
+                    .1,
+                    target_column: target_expression,
+                });
+            }
+
+            let mut select = sql::helpers::simple_select(select_cols);
+            select.from = Some(from_clause);
+            let target_tables = RootAndCurrentTables {
+                root_table: root_and_current_tables.root_table.clone(),
+                current_table: table,
+            };
+            let condition = translate_expression(env, state, &target_tables, predicate)?;
+            select.where_ = sql::ast::Where(with_discriminator(
+                env,
+                &target_tables.current_table,
+                condition,
+            )?);
+            let select = sql::rewrites::constant_folding::normalize_select(select);
+
+            let access_pattern =
+                access_pattern::analyse(&target_name, info, gsi.as_ref(), &select.where_);
+
+            Ok(state.add_semi_join(execution_plan::SemiJoin {
+                query: select,
+                access_pattern,
+                join,
+            }))
+        }
+        models::ExistsInCollection::NestedCollection { .. } => Err(Error::NotImplementedYet(
+            "exists over nested collections".to_string(),
+        )),
+    }
+}
+
+/// Restrict a condition on a collection to the items of its entity, if it is one.
+pub fn with_discriminator(
+    env: &Env,
+    table: &TableNameAndReference,
+    condition: sql::ast::Expression,
+) -> Result<sql::ast::Expression, Error> {
+    Ok(match entities::discriminator_condition(env, table)? {
+        None => condition,
+        Some(discriminator) => sql::ast::Expression::And {
+            left: Box::new(discriminator),
+            right: Box::new(condition),
+        },
+    })
+}
+
+/// Whether an expression compares with columns of the root collection.
+fn references_root_collection(expression: &models::Expression) -> bool {
+    let is_root = |target: &models::ComparisonTarget| {
+        matches!(
+            target,
+            models::ComparisonTarget::RootCollectionColumn { .. }
+        )
+    };
+    match expression {
+        models::Expression::And { expressions } | models::Expression::Or { expressions } => {
+            expressions.iter().any(references_root_collection)
+        }
+        models::Expression::Not { expression } => references_root_collection(expression),
+        models::Expression::UnaryComparisonOperator { column, .. } => is_root(column),
+        models::Expression::BinaryComparisonOperator { column, value, .. } => {
+            is_root(column)
+                || matches!(value, models::ComparisonValue::Column { column } if is_root(column))
+        }
+        models::Expression::Exists { predicate, .. } => {
+            predicate.as_deref().is_some_and(references_root_collection)
+        }
     }
 }
 
@@ -570,62 +802,44 @@ fn get_comparison_target_type(
     root_and_current_tables: &RootAndCurrentTables,
     column: &models::ComparisonTarget,
 ) -> Result<models::ScalarTypeName, Error> {
-    match column {
+    let (_, r#type) = match column {
         models::ComparisonTarget::RootCollectionColumn { name, field_path } => {
-            let column = env
-                .lookup_collection(&root_and_current_tables.root_table.name)?
-                .lookup_column(name)?;
-
-            let mut field_path = match field_path {
-                None => VecDeque::new(),
-                Some(field_path) => field_path.iter().collect(),
-            };
-            get_column_scalar_type_name(&column.r#type, &mut field_path)
+            translate_column_with_field_path(
+                env,
+                &root_and_current_tables.root_table,
+                name,
+                &field_path.into(),
+            )?
         }
         models::ComparisonTarget::Column {
             name,
             path,
             field_path,
-        } => {
-            let mut field_path = match field_path {
-                None => VecDeque::new(),
-                Some(field_path) => field_path.iter().collect(),
-            };
-            match path.last() {
-                None => {
-                    let column = env
-                        .lookup_collection(&root_and_current_tables.current_table.name)?
-                        .lookup_column(name)?;
-
-                    get_column_scalar_type_name(&column.r#type, &mut field_path)
-                }
-                Some(_last) => {
-                    todo!("relationship is not supported")
-                }
+        } => match path.last() {
+            None => translate_column_with_field_path(
+                env,
+                &root_and_current_tables.current_table,
+                name,
+                &field_path.into(),
+            )?,
+            Some(_last) => {
+                return Err(Error::NotImplementedYet(
+                    "comparisons with columns of relationships".to_string(),
+                ))
             }
-        }
-    }
+        },
+    };
+    get_column_scalar_type_name(r#type)
 }
 
-/// Extract the scalar type name of a column down their nested field path.
-/// Will error if path do not lead to a scalar type.
-fn get_column_scalar_type_name(
-    typ: &database::Type,
-    field_path: &mut VecDeque<&models::FieldName>,
-) -> Result<models::ScalarTypeName, Error> {
-    let field = field_path.pop_front();
+/// Extract the scalar type name of a column at the end of its nested field path.
+/// Will error if the path does not lead to a scalar type.
+fn get_column_scalar_type_name(typ: database::Type) -> Result<models::ScalarTypeName, Error> {
     match typ {
-        database::Type::ScalarType(scalar_type) => match field {
-            None => Ok(scalar_type.clone()),
-            // todo: what about json?
-            Some(field) => Err(Error::ColumnNotFoundInCollection(
-                field.clone(),
-                scalar_type.as_str().into(),
-            )),
-        },
-        database::Type::ArrayType(_) => Err(Error::NonScalarTypeUsedInOperator {
-            r#type: typ.clone(),
-        }),
+        database::Type::ScalarType(scalar_type) => Ok(scalar_type),
+        database::Type::CompositeType(_) | database::Type::ArrayType(_) => {
+            Err(Error::NonScalarTypeUsedInOperator { r#type: typ })
+        }
     }
 }
 
@@ -649,3 +863,261 @@ fn make_unnest_subquery(
     subquery.from = Some(subquery_from);
     sql::ast::Expression::CorrelatedSubSelect(Box::new(subquery))
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use query_engine_sql::sql::execution_plan::AttributeType;
+    use query_engine_sql::sql::string::Param;
+
+    use crate::translation::error::Error;
+    use crate::translation::test_helpers::{metadata, query_request, query_sql, translate};
+
+    /// A comparison of a field nested in a column of the current collection with a literal value.
+    fn compare_nested(
+        column: &str,
+        field_path: &[&str],
+        operator: &str,
+        value: serde_json::Value,
+    ) -> serde_json::Value {
+        json!({
+            "type": "binary_comparison_operator",
+            "column": { "type": "column", "name": column, "path": [], "field_path": field_path },
+            "operator": operator,
+            "value": { "type": "scalar", "value": value },
+        })
+    }
+
+    fn filter_sql(predicate: serde_json::Value) -> Result<String, Error> {
+        query_sql(
+            "users",
+            json!({
+                "fields": { "id": { "type": "column", "column": "id" } },
+                "predicate": predicate,
+            }),
+        )
+        .map(|sql| sql.sql)
+    }
+
+    /// A comparison of a column of the current collection with any comparison value.
+    fn compare_with(column: &str, operator: &str, value: serde_json::Value) -> serde_json::Value {
+        json!({
+            "type": "binary_comparison_operator",
+            "column": { "type": "column", "name": column, "path": [] },
+            "operator": operator,
+            "value": value,
+        })
+    }
+
+    /// A query of the users with an order matching the predicate.
+    fn users_with_orders(predicate: serde_json::Value) -> Result<(), Error> {
+        let mut request = query_request(
+            "users",
+            json!({
+                "fields": { "id": { "type": "column", "column": "id" } },
+                "predicate": {
+                    "type": "exists",
+                    "in_collection": {
+                        "type": "related",
+                        "relationship": "orders",
+                        "arguments": {},
+                    },
+                    "predicate": predicate,
+                },
+            }),
+        );
+        request["collection_relationships"] = json!({
+            "orders": {
+                "column_mapping": { "id": "user_id" },
+                "relationship_type": "array",
+                "target_collection": "orders",
+                "arguments": {},
+            },
+        });
+        translate(&metadata(), request).map(|_| ())
+    }
+
+    #[test]
+    fn test_columns_inside_exists_are_columns_of_the_searched_collection() {
+        // only orders have a status
+        let result = users_with_orders(compare_with(
+            "status",
+            "_eq",
+            json!({ "type": "scalar", "value": "shipped" }),
+        ));
+        assert!(result.is_ok(), "{result:?}");
+
+        // only users have an email
+        let result = users_with_orders(compare_with(
+            "email",
+            "_eq",
+            json!({ "type": "scalar", "value": "ada@example.com" }),
+        ));
+        assert!(
+            matches!(
+                result,
+                Err(Error::ColumnNotFoundInCollection(ref column, ref collection))
+                    if column.as_str() == "email" && collection.as_str() == "orders"
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_in_renders_a_list_of_values() {
+        let sql = filter_sql(compare_with(
+            "age",
+            "_in",
+            json!({ "type": "scalar", "value": [18, 21] }),
+        ))
+        .unwrap();
+        assert!(sql.contains("(age IN [18, 21])"), "{sql}");
+
+        // nothing is in an empty list
+        let sql = filter_sql(compare_with(
+            "age",
+            "_in",
+            json!({ "type": "scalar", "value": [] }),
+        ))
+        .unwrap();
+        assert!(!sql.contains(" IN "), "{sql}");
+    }
+
+    #[test]
+    fn test_in_with_a_variable_is_rejected() {
+        let result = filter_sql(compare_with(
+            "age",
+            "_in",
+            json!({ "type": "variable", "name": "ages" }),
+        ));
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_variables_are_bound_as_typed_parameters() {
+        let mut request = query_request(
+            "users",
+            json!({
+                "fields": { "id": { "type": "column", "column": "id" } },
+                "predicate": compare_with(
+                    "age",
+                    "_gt",
+                    json!({ "type": "variable", "name": "min_age" }),
+                ),
+            }),
+        );
+        request["variables"] = json!([{ "min_age": 18 }, { "min_age": 21 }]);
+        let sql = translate(&metadata(), request).unwrap().query.query_sql();
+
+        assert!(sql.sql.contains("(age > ?)"), "{}", sql.sql);
+        assert_eq!(
+            sql.params,
+            [Param::Variable {
+                name: "min_age".to_string(),
+                attribute_type: AttributeType::Number,
+                key_prefix: None,
+                custom_scalar: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_between_literal_bounds() {
+        let sql = filter_sql(compare_with(
+            "age",
+            "_between",
+            json!({ "type": "scalar", "value": [18, 65] }),
+        ))
+        .unwrap();
+        assert!(sql.contains("(age BETWEEN 18 AND 65)"), "{sql}");
+    }
+
+    #[test]
+    fn test_between_bounds_must_be_a_pair_of_literals() {
+        for value in [json!(18), json!([18]), json!([18, 40, 65])] {
+            let result = filter_sql(compare_with(
+                "age",
+                "_between",
+                json!({ "type": "scalar", "value": value }),
+            ));
+            assert!(matches!(result, Err(Error::TypeMismatch(..))), "{result:?}");
+        }
+
+        let result = filter_sql(compare_with(
+            "age",
+            "_between",
+            json!({ "type": "variable", "name": "bounds" }),
+        ));
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+
+        let result = filter_sql(compare_with(
+            "age",
+            "_between",
+            json!({ "type": "column", "column": { "type": "column", "name": "created", "path": [] } }),
+        ));
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_size_operators_compare_the_size_of_the_attribute() {
+        let sql = filter_sql(compare_with(
+            "name",
+            "_size_gt",
+            json!({ "type": "scalar", "value": 3 }),
+        ))
+        .unwrap();
+        assert!(sql.contains("(size(name) > 3)"), "{sql}");
+    }
+
+    #[test]
+    fn test_filter_on_a_field_of_a_map() {
+        let sql = filter_sql(compare_nested("address", &["city"], "_eq", json!("Paris"))).unwrap();
+        assert!(sql.contains("(address.city = "), "{sql}");
+    }
+
+    #[test]
+    fn test_filter_on_an_element_of_a_list() {
+        let sql = filter_sql(compare_nested("tags", &["0"], "_eq", json!("admin"))).unwrap();
+        assert!(sql.contains("(tags[0] = "), "{sql}");
+    }
+
+    #[test]
+    fn test_filter_on_a_missing_field_of_a_map_is_rejected() {
+        let result = filter_sql(compare_nested("address", &["country"], "_eq", json!("FR")));
+        assert!(
+            matches!(
+                result,
+                Err(Error::FieldNotFoundInCompositeType(ref field, ref type_name))
+                    if field.as_str() == "country" && type_name.as_str() == "users_address"
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_filter_on_paths_not_matching_the_type_is_rejected() {
+        // lists are indexed by numbers
+        let result = filter_sql(compare_nested("tags", &["first"], "_eq", json!("admin")));
+        assert!(
+            matches!(result, Err(Error::NestedFieldNotOfCompositeType { .. })),
+            "{result:?}"
+        );
+
+        // scalars have no fields
+        let result = filter_sql(compare_nested("name", &["first"], "_eq", json!("Alice")));
+        assert!(
+            matches!(result, Err(Error::NestedFieldNotOfCompositeType { .. })),
+            "{result:?}"
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Key templates: attributes whose values encode several values, such as
+//! `ORDER#2024-01-05#1234`, whose parts are read and compared as virtual fields.
+//!
+//! DynamoDB cannot split attribute values, so the executor splits them when it builds the rows,
+//! and equalities on virtual fields are rebuilt into conditions on the whole attribute.
+
+use std::collections::BTreeMap;
+
+use ndc_models as models;
+use query_engine_metadata::metadata;
+use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan;
+
+use crate::translation::error::Error;
+use crate::translation::helpers::{Env, FieldsInfo, RootAndCurrentTables};
+
+/// The attribute, template and type of a virtual field of a collection, if the field is one.
+pub fn lookup_virtual_field<'a>(
+    fields_info: &FieldsInfo<'a>,
+    field_name: &models::FieldName,
+) -> Option<(
+    &'a String,
+    &'a metadata::KeyTemplate,
+    &'a metadata::KeyTemplateField,
+)> {
+    match fields_info {
+        FieldsInfo::Table { info, .. } if !info.columns.contains_key(field_name) => {
+            info.key_templates.lookup_field(field_name)
+        }
+        FieldsInfo::Table { .. } | FieldsInfo::NativeQuery { .. } => None,
+    }
+}
+
+/// The row field splitting a virtual field from the value of its attribute.
+pub fn translate_field(
+    attribute: &str,
+    key_template: &metadata::KeyTemplate,
+    field_name: &models::FieldName,
+    field: &metadata::KeyTemplateField,
+) -> execution_plan::Field {
+    execution_plan::Field::KeyTemplateField {
+        attribute: attribute.to_string(),
+        template: key_template
+            .parts()
+            .into_iter()
+            .map(|part| match part {
+                metadata::KeyTemplatePart::Text(text) => {
+                    execution_plan::KeyTemplatePart::Text(text)
+                }
+                metadata::KeyTemplatePart::Field(name) => {
+                    execution_plan::KeyTemplatePart::Field(name.to_string())
+                }
+            })
+            .collect(),
+        field: field_name.to_string(),
+        is_number: field.r#type.as_str() == "Number",
+    }
+}
+
+/// The text a value of a virtual field stands for in the attribute value.
+pub fn field_text(
+    field: &metadata::KeyTemplateField,
+    value: &serde_json::Value,
+) -> Result<String, Error> {
+    match (field.r#type.as_str(), value) {
+        ("String", serde_json::Value::String(text)) => Ok(text.clone()),
+        ("Number", serde_json::Value::Number(number)) => Ok(number.to_string()),
+        _ => Err(Error::TypeMismatch(value.clone(), field.r#type.clone())),
+    }
+}
+
+/// Fill a template with the values of its fields, up to the first field without a value.
+/// Return the text and whether every field had a value.
+pub fn fill_template(
+    key_template: &metadata::KeyTemplate,
+    values: &BTreeMap<models::FieldName, String>,
+) -> (String, bool) {
+    let mut text = String::new();
+    for part in key_template.parts() {
+        match part {
+            metadata::KeyTemplatePart::Text(part) => text.push_str(&part),
+            metadata::KeyTemplatePart::Field(name) => match values.get(&name) {
+                Some(value) => text.push_str(value),
+                None => return (text, false),
+            },
+        }
+    }
+    (text, true)
+}
+
+/// Whether an expression compares a virtual field of the current table.
+pub fn is_virtual_field_comparison(
+    env: &Env,
+    root_and_current_tables: &RootAndCurrentTables,
+    expression: &models::Expression,
+) -> Result<bool, Error> {
+    let models::Expression::BinaryComparisonOperator {
+        column: models::ComparisonTarget::Column { name, .. },
+        ..
+    } = expression
+    else {
+        return Ok(false);
+    };
+    let fields_info = env.lookup_fields_info(&root_and_current_tables.current_table.name)?;
+    Ok(lookup_virtual_field(&fields_info, name).is_some())
+}
+
+/// Rebuild the equalities on virtual fields among the conjuncts of a predicate into conditions
+/// on their attributes, and return the conditions along with the other conjuncts.
+///
+/// The fields of a template fixed from its start give the whole attribute value, or the
+/// beginning of it: `ORDER#{date}#{id}` with only `date` fixed is read with
+/// `begins_with(SK, 'ORDER#2024-01-05#')`.
+pub fn translate_key_conditions<'a>(
+    env: &Env,
+    root_and_current_tables: &RootAndCurrentTables,
+    expressions: &'a [models::Expression],
+) -> Result<(Vec<sql::ast::Expression>, Vec<&'a models::Expression>), Error> {
+    let current_table = &root_and_current_tables.current_table;
+    let fields_info = env.lookup_fields_info(&current_table.name)?;
+
+    let mut values: BTreeMap<&String, (&metadata::KeyTemplate, BTreeMap<_, _>)> = BTreeMap::new();
+    let mut conditions = vec![];
+    let mut rest = vec![];
+    for expression in expressions {
+        let models::Expression::BinaryComparisonOperator {
+            column:
+                models::ComparisonTarget::Column {
+                    name,
+                    path,
+                    field_path,
+                },
+            operator,
+            value,
+        } = expression
+        else {
+            rest.push(expression);
+            continue;
+        };
+        let Some((attribute, key_template, field)) = lookup_virtual_field(&fields_info, name)
+        else {
+            rest.push(expression);
+            continue;
+        };
+        if !path.is_empty() || field_path.as_ref().is_some_and(|path| !path.is_empty()) {
+            return Err(Error::NotImplementedYet(
+                "paths to key template fields".to_string(),
+            ));
+        }
+        let op = env.lookup_comparison_operator(&field.r#type, operator)?;
+        let models::ComparisonValue::Scalar { value } = value else {
+            return Err(Error::NotImplementedYet(
+                "comparisons of key template fields with columns or variables".to_string(),
+            ));
+        };
+        if op.operator_kind != metadata::OperatorKind::Equal {
+            return Err(Error::NotImplementedYet(format!(
+                "comparisons of key template fields with '{operator}'"
+            )));
+        }
+        let text = field_text(field, value)?;
+        let (_, field_values) = values
+            .entry(attribute)
+            .or_insert_with(|| (key_template, BTreeMap::new()));
+        match field_values.get(name) {
+            // a field cannot hold two values
+            Some(other) if other != &text => conditions.push(sql::helpers::false_expr()),
+            _ => {
+                field_values.insert(name.clone(), text);
+            }
+        }
+    }
+
+    for (attribute, (key_template, field_values)) in values {
+        let (text, is_complete) = fill_template(key_template, &field_values);
+        // fields after a field without a value cannot be compared within the attribute value
+        let fixed = key_template
+            .parts()
+            .into_iter()
+            .filter_map(|part| match part {
+                metadata::KeyTemplatePart::Field(name) => Some(name),
+                metadata::KeyTemplatePart::Text(_) => None,
+            })
+            .take_while(|name| field_values.contains_key(name))
+            .count();
+        if fixed < field_values.len() {
+            return Err(Error::NotImplementedYet(format!(
+                "comparisons of fields of the key template of '{attribute}' without the fields before them"
+            )));
+        }
+        let left = sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
+            table: current_table.reference.clone(),
+            name: sql::ast::ColumnName(attribute.clone()),
+        });
+        let right = sql::ast::Expression::Value(sql::ast::Value::String(text));
+        conditions.push(if is_complete {
+            sql::ast::Expression::BinaryOperation {
+                left: Box::new(left),
+                operator: sql::ast::BinaryOperator("=".to_string()),
+                right: Box::new(right),
+            }
+        } else {
+            sql::ast::Expression::FunctionCall {
+                function: sql::ast::Function::BeginsWith,
+                args: vec![left, right],
+            }
+        });
+    }
+    Ok((conditions, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use query_engine_metadata::metadata;
+    use query_engine_sql::sql::execution_plan::{Field, KeyTemplatePart};
+    use query_engine_sql::sql::string::{Param, SQL};
+
+    use crate::translation::error::Error;
+    use crate::translation::test_helpers::{compare, metadata, query_request, translate};
+
+    /// The metadata of the tests, with orders whose `order_id` sort key follows the template
+    /// `ORDER#{order_date}#{order_number}`.
+    fn key_template_metadata() -> metadata::Metadata {
+        let mut metadata = metadata();
+        metadata.tables.0.get_mut("orders").unwrap().key_templates =
+            serde_json::from_value(json!({
+                "order_id": {
+                    "template": "ORDER#{order_date}#{order_number}",
+                    "fields": {
+                        "order_date": { "type": "String" },
+                        "order_number": { "type": "Number" },
+                    },
+                },
+            }))
+            .unwrap();
+        metadata
+    }
+
+    fn orders_sql(predicate: serde_json::Value) -> Result<SQL, Error> {
+        let request = query_request(
+            "orders",
+            json!({
+                "fields": { "total": { "type": "column", "column": "total" } },
+                "predicate": predicate,
+            }),
+        );
+        Ok(translate(&key_template_metadata(), request)?
+            .query
+            .query_sql())
+    }
+
+    fn string(s: &str) -> Param {
+        Param::String(s.to_string())
+    }
+
+    #[test]
+    fn test_every_field_fixed_is_an_equality_on_the_attribute() {
+        let sql = orders_sql(json!({
+            "type": "and",
+            "expressions": [
+                compare("user_id", "_eq", json!("u1")),
+                compare("order_date", "_eq", json!("2024-01-05")),
+                compare("order_number", "_eq", json!(7)),
+            ],
+        }))
+        .unwrap();
+
+        assert!(sql.sql.contains("(order_id = ?)"), "{}", sql.sql);
+        assert!(sql.sql.contains("(user_id = ?)"), "{}", sql.sql);
+        assert!(
+            sql.params.contains(&string("ORDER#2024-01-05#7")),
+            "{:?}",
+            sql.params
+        );
+    }
+
+    #[test]
+    fn test_leading_fields_fixed_are_a_prefix_of_the_attribute() {
+        let sql = orders_sql(compare("order_date", "_eq", json!("2024-01-05"))).unwrap();
+
+        assert!(sql.sql.contains("begins_with(order_id, ?)"), "{}", sql.sql);
+        assert_eq!(sql.params, [string("ORDER#2024-01-05#")]);
+    }
+
+    #[test]
+    fn test_fields_without_the_fields_before_them_are_rejected() {
+        let result = orders_sql(compare("order_number", "_eq", json!(7)));
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_a_field_with_two_values_matches_nothing() {
+        let sql = orders_sql(json!({
+            "type": "and",
+            "expressions": [
+                compare("order_date", "_eq", json!("2024-01-05")),
+                compare("order_date", "_eq", json!("2024-01-06")),
+            ],
+        }))
+        .unwrap();
+        assert!(sql.sql.contains("false"), "{}", sql.sql);
+    }
+
+    #[test]
+    fn test_invalid_comparisons_of_fields() {
+        // the value must have the type of the field
+        let result = orders_sql(json!({
+            "type": "and",
+            "expressions": [
+                compare("order_date", "_eq", json!("2024-01-05")),
+                compare("order_number", "_eq", json!("7")),
+            ],
+        }));
+        assert!(matches!(result, Err(Error::TypeMismatch(..))), "{result:?}");
+
+        // only equalities rebuild into the attribute value
+        let result = orders_sql(compare("order_date", "_gt", json!("2024-01-05")));
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_virtual_fields_are_split_from_their_attribute() {
+        let request = query_request(
+            "orders",
+            json!({
+                "fields": { "number": { "type": "column", "column": "order_number" } },
+            }),
+        );
+        let plan = translate(&key_template_metadata(), request).unwrap();
+        let fields = plan.query.rowset.fields.unwrap_or_default();
+
+        assert!(
+            fields.iter().any(|(alias, field)| alias == "number"
+                && matches!(
+                    field,
+                    Field::KeyTemplateField { attribute, template, field, is_number: true }
+                        if attribute == "order_id"
+                            && field == "order_number"
+                            && template[0] == KeyTemplatePart::Text("ORDER#".to_string())
+                )),
+            "{fields:?}"
+        );
+    }
+}
@@ -1,7 +1,15 @@
 //! Translate an incoming `QueryRequest`.
 
+pub mod access_pattern;
+mod aggregates;
+pub mod entities;
 pub mod fields;
 pub mod filtering;
+pub mod key_templates;
+pub mod native_queries;
+pub mod operators;
+pub mod pagination;
+pub mod relationships;
 pub mod root;
 mod sorting;
 pub mod values;
@@ -9,7 +17,7 @@ pub mod values;
 use ndc_models::{self as models};
 
 use crate::translation::error::Error;
-use crate::translation::helpers::{Env, State};
+use crate::translation::helpers::{CollectionInfo, Env, State};
 use query_engine_metadata::metadata;
 use query_engine_sql::sql;
 
@@ -19,22 +27,22 @@ pub fn translate(
     query_request: models::QueryRequest,
 ) -> Result<sql::execution_plan::ExecutionPlan<sql::execution_plan::Query>, Error> {
     let mut state = State::new();
-    let env = Env::new(metadata);
-
-    let collection_string = query_request.collection.as_str();
-    let collection = if collection_string.contains(':') {
-        let split: Vec<&str> = collection_string.split(':').collect();
-        if split.len() != 2 {
-            return Err(Error::InvalidCollectionName(collection_string.to_string()));
-        };
-        let collection_name = models::CollectionName::new(split[0].into());
-        let gsi_name = split[1];
-        (collection_name, Some(sql::ast::Gsi(gsi_name.to_string())))
-    } else {
-        (models::CollectionName::new(collection_string.into()), None)
+    let env = Env::new(
+        metadata,
+        &query_request.collection_relationships,
+        query_request.variables.is_some(),
+    );
+
+    let collection = root::parse_collection_name(&query_request.collection)?;
+    let table = env.lookup_collection(&collection.0)?;
+
+    // the arguments of a native query are its parameters, and it has no cursor
+    let continuation_token = match table {
+        CollectionInfo::Table { .. } => pagination::translate_arguments(&query_request.arguments)?,
+        CollectionInfo::NativeQuery { .. } => None,
     };
 
-    let (query_limit, returns_field, select_set) = root::translate_query(
+    let (query_limit, returns_field, select_set, rowset) = root::translate_query(
         &env,
         &mut state,
         &root::MakeFrom::Collection {
@@ -51,12 +59,116 @@ pub fn translate(
     let json_select = sql::helpers::select_rowset(select_set, &returns_field);
 
     // normalize ast
-    let json_select = sql::rewrites::constant_folding::normalize_select(json_select);
+    let mut json_select = sql::rewrites::constant_folding::normalize_select(json_select);
+
+    let (access_pattern, order_by, document_paths) = match table {
+        CollectionInfo::Table { info, .. } => {
+            let access_pattern = access_pattern::analyse(
+                &collection.0,
+                info,
+                collection.1.as_ref(),
+                &json_select.where_,
+            );
+            let order_by =
+                sorting::plan_order_by(Some(info), collection.1.as_ref(), &mut json_select)?;
+            let document_paths = fields::merge_document_paths(&mut json_select);
+            (access_pattern, order_by, document_paths)
+        }
+        // the statement of a native query runs as written, and returns whole items
+        CollectionInfo::NativeQuery { name, .. } => {
+            let order_by = sorting::plan_order_by(None, None, &mut json_select)?;
+            (
+                sql::execution_plan::AccessPattern::NativeQuery {
+                    name: name.to_string(),
+                },
+                order_by,
+                vec![],
+            )
+        }
+    };
 
-    Ok(sql::execution_plan::simple_query_execution_plan(
+    let mut plan = sql::execution_plan::simple_query_execution_plan(
         query_request.variables,
         query_request.collection,
         json_select,
         query_limit,
-    ))
+        query_request.query.offset,
+        continuation_token,
+        access_pattern,
+        rowset,
+        document_paths,
+        order_by,
+    );
+    plan.query.semi_joins = state.take_semi_joins();
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::translation::test_helpers::{compare, metadata, query_request, translate};
+    use query_engine_sql::sql::execution_plan::AccessPattern;
+
+    fn access_pattern(collection: &str, predicate: serde_json::Value) -> AccessPattern {
+        let request = query_request(
+            collection,
+            serde_json::json!({
+                "fields": { "id": { "type": "column", "column": "id" } },
+                "predicate": predicate,
+            }),
+        );
+        translate(&metadata(), request)
+            .unwrap()
+            .query
+            .access_pattern
+    }
+
+    #[test]
+    fn test_full_primary_key_is_a_key_lookup() {
+        let predicate = serde_json::json!({
+            "type": "and",
+            "expressions": [
+                compare("id", "_eq", serde_json::json!("a")),
+                compare("created", "_eq", serde_json::json!(1)),
+            ],
+        });
+        assert_eq!(
+            access_pattern("users", predicate),
+            AccessPattern::KeyLookup {
+                table: "users".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_partition_key_is_a_query() {
+        assert_eq!(
+            access_pattern("users", compare("id", "_eq", serde_json::json!("a"))),
+            AccessPattern::Query {
+                table: "users".to_string(),
+                index: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_index_partition_key_is_a_query_on_the_index() {
+        assert_eq!(
+            access_pattern(
+                "users:by_email",
+                compare("email", "_eq", serde_json::json!("a@example.com"))
+            ),
+            AccessPattern::Query {
+                table: "users".to_string(),
+                index: Some("by_email".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_other_attributes_are_a_scan() {
+        assert!(matches!(
+            access_pattern("users", compare("name", "_eq", serde_json::json!("Alice"))),
+            AccessPattern::Scan { index: None, .. }
+        ));
+    }
 }
@@ -0,0 +1,68 @@
+//! Translate native queries: PartiQL statements written by the user, run with their arguments
+//! bound as parameters.
+
+use std::collections::BTreeMap;
+
+use ndc_models as models;
+use query_engine_metadata::metadata;
+
+use super::values;
+use crate::translation::error::Error;
+use crate::translation::helpers::Env;
+use crate::translation::mutation::translate::translate_attribute_value;
+use query_engine_sql::sql;
+
+/// Translate a native query to raw PartiQL, with a parameter for each of its placeholders.
+/// Every argument must be given, except nullable ones which default to null.
+pub fn translate(
+    env: &Env,
+    native_query: &metadata::NativeQueryInfo,
+    arguments: &BTreeMap<models::ArgumentName, models::Argument>,
+) -> Result<Vec<sql::ast::RawSql>, Error> {
+    if let Some(name) = arguments
+        .keys()
+        .find(|name| !native_query.arguments.contains_key(*name))
+    {
+        return Err(Error::ArgumentNotFound(name.clone()));
+    }
+
+    native_query
+        .parts()
+        .into_iter()
+        .map(|part| match part {
+            metadata::NativeQueryPart::Text(text) => Ok(sql::ast::RawSql::RawText(text)),
+            metadata::NativeQueryPart::Parameter(name) => {
+                let name = models::ArgumentName::new(name.into());
+                let argument_info = native_query
+                    .arguments
+                    .get(&name)
+                    .ok_or_else(|| Error::ArgumentNotFound(name.clone()))?;
+                let expression = match arguments.get(&name) {
+                    Some(models::Argument::Variable { name: variable }) => {
+                        values::translate_variable(env, variable, &argument_info.r#type)?
+                    }
+                    Some(models::Argument::Literal { value }) => {
+                        literal(&argument_info.r#type, value)?
+                    }
+                    None if argument_info.nullable == metadata::Nullable::Nullable => {
+                        literal(&argument_info.r#type, &serde_json::Value::Null)?
+                    }
+                    None => return Err(Error::ArgumentNotFound(name)),
+                };
+                Ok(sql::ast::RawSql::Expression(expression))
+            }
+        })
+        .collect()
+}
+
+/// A literal argument, checked against its type.
+fn literal(
+    r#type: &metadata::Type,
+    value: &serde_json::Value,
+) -> Result<sql::ast::Expression, Error> {
+    let typed_value = translate_attribute_value(r#type, value)?;
+    Ok(sql::ast::Expression::Value(sql::ast::Value::Typed {
+        value: typed_value.value,
+        attribute_type: typed_value.attribute_type,
+    }))
+}
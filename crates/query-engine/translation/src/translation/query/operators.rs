@@ -1,67 +1,260 @@
-use ndc_sdk::models;
+//! Render the comparison operators of the metadata as PartiQL conditions.
 
-use super::error::Error;
+use query_engine_metadata::metadata;
 use query_engine_sql::sql;
 
-pub fn translate_operator(
-    operator: &models::BinaryComparisonOperator,
-) -> Result<sql::ast::BinaryOperator, Error> {
-    match operator {
-        models::BinaryComparisonOperator::Equal => Ok(sql::ast::BinaryOperator::Equals),
-        models::BinaryComparisonOperator::Other { name } => {
-            lookup_by_name(name.as_str()).ok_or_else(|| Error::OperatorNotFound(name.clone()))
+use crate::translation::error::Error;
+
+/// Compare the attribute on the left with the values on the right using an operator of its
+/// scalar type. `IN` takes any number of values, `BETWEEN` its lower and upper bounds, and every
+/// other operator a single value.
+pub fn translate_comparison(
+    operator: &metadata::ComparisonOperator,
+    left: sql::ast::Expression,
+    right: Vec<sql::ast::Expression>,
+) -> Result<sql::ast::Expression, Error> {
+    match operator.operator_kind {
+        metadata::OperatorKind::In => Ok(sql::ast::Expression::BinaryArrayOperation {
+            left: Box::new(left),
+            operator: sql::ast::BinaryArrayOperator::In,
+            right,
+        }),
+        metadata::OperatorKind::Between => {
+            let [low, high] = <[sql::ast::Expression; 2]>::try_from(right).map_err(|_| {
+                Error::InternalError("BETWEEN expects a lower and an upper bound".to_string())
+            })?;
+            Ok(sql::ast::Expression::Between {
+                expression: Box::new(left),
+                low: Box::new(low),
+                high: Box::new(high),
+            })
+        }
+        metadata::OperatorKind::Equal | metadata::OperatorKind::Custom => {
+            let right = single_value(operator, right)?;
+            if operator.is_infix {
+                Ok(sql::ast::Expression::BinaryOperation {
+                    left: Box::new(left),
+                    operator: sql::ast::BinaryOperator(operator.operator_name.clone()),
+                    right: Box::new(right),
+                })
+            } else {
+                Ok(sql::ast::Expression::FunctionCall {
+                    function: sql::ast::Function::Unknown(operator.operator_name.clone()),
+                    args: vec![left, right],
+                })
+            }
+        }
+        metadata::OperatorKind::BeginsWith => Ok(sql::ast::Expression::FunctionCall {
+            function: sql::ast::Function::BeginsWith,
+            args: vec![left, single_value(operator, right)?],
+        }),
+        metadata::OperatorKind::Contains => Ok(sql::ast::Expression::FunctionCall {
+            function: sql::ast::Function::Contains,
+            args: vec![left, single_value(operator, right)?],
+        }),
+        metadata::OperatorKind::AttributeType => Ok(sql::ast::Expression::FunctionCall {
+            function: sql::ast::Function::AttributeType,
+            args: vec![left, single_value(operator, right)?],
+        }),
+        // size(attribute) <operator> value
+        metadata::OperatorKind::Size => Ok(sql::ast::Expression::BinaryOperation {
+            left: Box::new(sql::ast::Expression::FunctionCall {
+                function: sql::ast::Function::Size,
+                args: vec![left],
+            }),
+            operator: sql::ast::BinaryOperator(operator.operator_name.clone()),
+            right: Box::new(single_value(operator, right)?),
+        }),
+        // attribute IS [NOT] MISSING
+        metadata::OperatorKind::IsMissing | metadata::OperatorKind::Exists => {
+            let exists = operator.operator_kind == metadata::OperatorKind::Exists;
+            let is_missing = sql::ast::Expression::UnaryOperation {
+                expression: Box::new(left),
+                operator: sql::ast::UnaryOperator::IsMissing,
+            };
+            match single_value(operator, right)? {
+                sql::ast::Expression::Value(sql::ast::Value::Bool(value)) => {
+                    Ok(if value == exists {
+                        sql::ast::Expression::Not(Box::new(is_missing))
+                    } else {
+                        is_missing
+                    })
+                }
+                // PartiQL cannot compare the result of IS MISSING with a bound value
+                _ => Err(Error::NotImplementedYet(format!(
+                    "'{}' with a value that is not a literal boolean",
+                    operator.operator_name
+                ))),
+            }
         }
     }
 }
 
-fn lookup_by_name(name: &str) -> Option<sql::ast::BinaryOperator> {
-    match name {
-        "_eq" => Some(sql::ast::BinaryOperator::Equals),
-        "_neq" => Some(sql::ast::BinaryOperator::NotEquals),
-        "_lt" => Some(sql::ast::BinaryOperator::LessThan),
-        "_lte" => Some(sql::ast::BinaryOperator::LessThanOrEqualTo),
-        "_gt" => Some(sql::ast::BinaryOperator::GreaterThan),
-        "_gte" => Some(sql::ast::BinaryOperator::GreaterThanOrEqualTo),
-        "_like" => Some(sql::ast::BinaryOperator::Like),
-        "_nlike" => Some(sql::ast::BinaryOperator::NotLike),
-        "_ilike" => Some(sql::ast::BinaryOperator::CaseInsensitiveLike),
-        "_nilike" => Some(sql::ast::BinaryOperator::NotCaseInsensitiveLike),
-        "_similar" => Some(sql::ast::BinaryOperator::Similar),
-        "_nsimilar" => Some(sql::ast::BinaryOperator::NotSimilar),
-        "_regex" => Some(sql::ast::BinaryOperator::Regex),
-        "_nregex" => Some(sql::ast::BinaryOperator::NotRegex),
-        "_iregex" => Some(sql::ast::BinaryOperator::CaseInsensitiveRegex),
-        "_niregex" => Some(sql::ast::BinaryOperator::NotCaseInsensitiveRegex),
-        _ => None,
-    }
+fn single_value(
+    operator: &metadata::ComparisonOperator,
+    right: Vec<sql::ast::Expression>,
+) -> Result<sql::ast::Expression, Error> {
+    let [value] = <[sql::ast::Expression; 1]>::try_from(right).map_err(|_| {
+        Error::InternalError(format!(
+            "the operator '{}' expects a single value",
+            operator.operator_name
+        ))
+    })?;
+    Ok(value)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ndc_dynamodb_configuration::introspection::get_comparison_operators_for_type;
+    use query_engine_sql::sql::string::SQL;
 
-    // This test ties together the metadata and SQL comparison operators at test
-    // time, so we can avoid coupling them at compile time.
-    //
-    // It is not completely clear that this is necessary; this is more of an
-    // extra check. If it starts to become troublesome, we can remove it.
-    //
-    // This needs to live here because this module is private.
+    /// The words a DynamoDB PartiQL condition may be made of.
+    const PARTIQL_VOCABULARY: [&str; 16] = [
+        "=",
+        "<>",
+        "<",
+        "<=",
+        ">",
+        ">=",
+        "IN",
+        "BETWEEN",
+        "AND",
+        "NOT",
+        "IS",
+        "MISSING",
+        "begins_with",
+        "contains",
+        "size",
+        "attribute_type",
+    ];
+
+    // This test ties together the operators published by the configuration and the PartiQL
+    // they are rendered as, so that no operator DynamoDB does not understand sneaks back in.
     #[test]
-    fn test_metadata_binary_comparison_operators_are_in_sync_with_sql() {
-        for metadata_operator in
-            enum_iterator::all::<query_engine_metadata::metadata::database::ComparisonOperator>()
-        {
-            let name = metadata_operator.name();
-            let sql_operator = lookup_by_name(name)
-                .unwrap_or_else(|| panic!("{}", Error::OperatorNotFound(name.to_string())));
-            // For lack of a better way to ensure we get the correct operator,
-            // we can use their `Debug` representation. We may have to evolve
-            // this if things become more sophisticated.
-            assert_eq!(
-                format!("{:?}", metadata_operator),
-                format!("{:?}", sql_operator)
-            );
+    fn test_metadata_comparison_operators_are_in_sync_with_partiql() {
+        for type_name in [
+            "String",
+            "Number",
+            "Binary",
+            "Boolean",
+            "Any",
+            "Timestamp",
+            "EpochTimestamp",
+            "Date",
+            "UUID",
+        ] {
+            for (name, operator) in get_comparison_operators_for_type(
+                &ndc_models::ScalarTypeName::new(type_name.into()),
+            ) {
+                let right = match operator.operator_kind {
+                    metadata::OperatorKind::In | metadata::OperatorKind::Between => vec![
+                        sql::ast::Expression::Value(sql::ast::Value::Int8(1)),
+                        sql::ast::Expression::Value(sql::ast::Value::Int8(2)),
+                    ],
+                    metadata::OperatorKind::IsMissing | metadata::OperatorKind::Exists => {
+                        vec![sql::ast::Expression::Value(sql::ast::Value::Bool(true))]
+                    }
+                    _ => vec![sql::ast::Expression::Value(sql::ast::Value::Int8(1))],
+                };
+                let left = sql::ast::Expression::Value(sql::ast::Value::String("x".to_string()));
+                let expression = translate_comparison(&operator, left, right)
+                    .unwrap_or_else(|err| panic!("{type_name} {name}: {err}"));
+
+                let mut sql = SQL::new();
+                expression.to_sql(&mut sql);
+                let rendered = sql
+                    .sql
+                    .replace(['(', ')', '[', ']', ','], " ")
+                    .replace('?', " ");
+                for word in rendered.split_whitespace() {
+                    assert!(
+                        word == "1" || word == "2" || PARTIQL_VOCABULARY.contains(&word),
+                        "{type_name} {name} renders '{word}', which is not PartiQL: {}",
+                        sql.sql
+                    );
+                }
+            }
+        }
+    }
+
+    fn operator(kind: metadata::OperatorKind, name: &str) -> metadata::ComparisonOperator {
+        metadata::ComparisonOperator {
+            operator_name: name.to_string(),
+            operator_kind: kind,
+            argument_type: ndc_models::ScalarTypeName::new("Number".into()),
+            is_infix: true,
         }
     }
+
+    fn render(expression: &sql::ast::Expression) -> String {
+        let mut sql = SQL::new();
+        expression.to_sql(&mut sql);
+        sql.sql
+    }
+
+    fn column(name: &str) -> sql::ast::Expression {
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::TableColumn {
+            table: sql::ast::TableReference::DBTable {
+                table: sql::ast::TableName("users".to_string()),
+                gsi: None,
+            },
+            name: sql::ast::ColumnName(name.to_string()),
+        })
+    }
+
+    fn number(n: i32) -> sql::ast::Expression {
+        sql::ast::Expression::Value(sql::ast::Value::Int8(n))
+    }
+
+    #[test]
+    fn test_between_renders_its_bounds() {
+        let between = operator(metadata::OperatorKind::Between, "BETWEEN");
+        let expression =
+            translate_comparison(&between, column("age"), vec![number(18), number(65)]).unwrap();
+        assert_eq!(render(&expression), "(age BETWEEN 18 AND 65)");
+
+        assert!(translate_comparison(&between, column("age"), vec![number(18)]).is_err());
+    }
+
+    #[test]
+    fn test_size_compares_the_size_of_the_attribute() {
+        let size_gte = operator(metadata::OperatorKind::Size, ">=");
+        let expression = translate_comparison(&size_gte, column("name"), vec![number(3)]).unwrap();
+        assert_eq!(render(&expression), "(size(name) >= 3)");
+
+        assert!(translate_comparison(&size_gte, column("name"), vec![]).is_err());
+    }
+
+    #[test]
+    fn test_is_missing_and_exists_take_a_literal_boolean() {
+        let is_missing = operator(metadata::OperatorKind::IsMissing, "IS MISSING");
+        let exists = operator(metadata::OperatorKind::Exists, "IS NOT MISSING");
+        let boolean = |value| vec![sql::ast::Expression::Value(sql::ast::Value::Bool(value))];
+
+        let render_with = |operator, value| {
+            render(&translate_comparison(operator, column("email"), boolean(value)).unwrap())
+        };
+        assert_eq!(render_with(&is_missing, true), "(email IS MISSING )");
+        assert_eq!(render_with(&is_missing, false), "NOT (email IS MISSING )");
+        assert_eq!(render_with(&exists, true), "NOT (email IS MISSING )");
+        assert_eq!(render_with(&exists, false), "(email IS MISSING )");
+
+        let variable = sql::ast::Expression::Value(sql::ast::Value::Variable {
+            name: "present".to_string(),
+            attribute_type: sql::execution_plan::AttributeType::Boolean,
+            key_prefix: None,
+            custom_scalar: None,
+        });
+        let result = translate_comparison(&exists, column("email"), vec![variable]);
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+        let result = translate_comparison(&is_missing, column("email"), vec![column("name")]);
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+    }
 }
@@ -0,0 +1,129 @@
+//! Keyset pagination of collections.
+//!
+//! Every collection accepts an `after` argument holding an opaque cursor, and every row has a
+//! cursor field to continue the query after it. The cursor of the last row is the cursor of the
+//! next page.
+
+use std::collections::BTreeMap;
+
+use ndc_models as models;
+use query_engine_metadata::metadata;
+
+use crate::translation::error::Error;
+
+/// The name of the collection argument holding the cursor to continue from.
+pub const AFTER_ARGUMENT: &str = "after";
+
+/// The name of the field holding the cursor to continue the query after a row.
+pub const CURSOR_FIELD: &str = "_cursor";
+
+/// Whether a table gets a cursor field. An attribute of the same name takes precedence.
+pub fn has_cursor_field(table: &metadata::TableInfo) -> bool {
+    !table.columns.contains_key(CURSOR_FIELD)
+}
+
+/// Find the cursor to continue from in the arguments of a collection, and reject any other
+/// argument.
+pub(crate) fn translate_arguments(
+    arguments: &BTreeMap<models::ArgumentName, models::Argument>,
+) -> Result<Option<String>, Error> {
+    let mut after = None;
+    for (name, argument) in arguments {
+        if name.as_str() != AFTER_ARGUMENT {
+            return Err(Error::ArgumentNotFound(name.clone()));
+        }
+        after = match argument {
+            models::Argument::Literal {
+                value: serde_json::Value::Null,
+            } => None,
+            models::Argument::Literal {
+                value: serde_json::Value::String(cursor),
+            } => Some(cursor.clone()),
+            models::Argument::Literal { value } => {
+                return Err(Error::TypeMismatch(value.clone(), "String".into()))
+            }
+            models::Argument::Variable { .. } => return Err(Error::UnexpectedVariable),
+        };
+    }
+    Ok(after)
+}
+
+#[cfg(test)]
+mod tests {
+    use query_engine_sql::sql::execution_plan::Field;
+    use serde_json::json;
+
+    use crate::translation::error::Error;
+    use crate::translation::test_helpers::{metadata, query_request, translate};
+
+    fn request_with_arguments(arguments: serde_json::Value) -> serde_json::Value {
+        let mut request = query_request(
+            "users",
+            json!({
+                "fields": {
+                    "id": { "type": "column", "column": "id" },
+                    "cursor": { "type": "column", "column": "_cursor" },
+                },
+            }),
+        );
+        request["arguments"] = arguments;
+        request
+    }
+
+    #[test]
+    fn test_after_argument_sets_the_continuation_token() {
+        let plan = translate(
+            &metadata(),
+            request_with_arguments(json!({ "after": { "type": "literal", "value": "abc" } })),
+        )
+        .unwrap();
+        assert_eq!(plan.query.continuation_token.as_deref(), Some("abc"));
+
+        let plan = translate(
+            &metadata(),
+            request_with_arguments(json!({ "after": { "type": "literal", "value": null } })),
+        )
+        .unwrap();
+        assert_eq!(plan.query.continuation_token, None);
+    }
+
+    #[test]
+    fn test_cursor_field_is_not_read_from_items() {
+        let plan = translate(&metadata(), request_with_arguments(json!({}))).unwrap();
+
+        let fields = plan.query.rowset.fields.unwrap();
+        assert!(fields.contains(&("cursor".to_string(), Field::Cursor)));
+        assert!(
+            !plan.query.query_sql().sql.contains("_cursor"),
+            "{}",
+            plan.query.query_sql().sql
+        );
+    }
+
+    #[test]
+    fn test_invalid_after_arguments_are_rejected() {
+        let result = translate(
+            &metadata(),
+            request_with_arguments(json!({ "after": { "type": "literal", "value": 1 } })),
+        );
+        assert!(matches!(result, Err(Error::TypeMismatch(..))), "{result:?}");
+
+        let result = translate(
+            &metadata(),
+            request_with_arguments(json!({ "after": { "type": "variable", "name": "cursor" } })),
+        );
+        assert!(
+            matches!(result, Err(Error::UnexpectedVariable)),
+            "{result:?}"
+        );
+
+        let result = translate(
+            &metadata(),
+            request_with_arguments(json!({ "before": { "type": "literal", "value": "abc" } })),
+        );
+        assert!(
+            matches!(result, Err(Error::ArgumentNotFound(ref name)) if name.as_str() == "before"),
+            "{result:?}"
+        );
+    }
+}
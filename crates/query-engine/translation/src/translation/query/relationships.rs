@@ -0,0 +1,272 @@
+//! Translate relationship fields. DynamoDB cannot join tables, so the executor runs the query of
+//! a relationship for the rows of the source query, in batches of their join keys.
+//!
+//! Relationships must join on the partition key of their target table or index, so that each
+//! batch runs as a Query.
+
+use std::collections::BTreeMap;
+
+use ndc_models as models;
+
+use super::access_pattern;
+use super::fields;
+use super::root;
+use super::sorting;
+use crate::translation::error::Error;
+use crate::translation::helpers::{CollectionInfo, Env, State, TableNameAndReference};
+use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan;
+
+/// Translate a relationship field of the rows of the current table, and return the columns of
+/// the current table its join reads.
+pub fn translate_relationship_field(
+    env: &Env,
+    state: &mut State,
+    current_table: &TableNameAndReference,
+    relationship_name: &models::RelationshipName,
+    arguments: &BTreeMap<models::ArgumentName, models::RelationshipArgument>,
+    query: &models::Query,
+) -> Result<
+    (
+        Vec<(sql::ast::ColumnAlias, sql::ast::Expression)>,
+        execution_plan::Field,
+    ),
+    Error,
+> {
+    let relationship = env.lookup_relationship(relationship_name)?;
+    if !arguments.is_empty() || !relationship.arguments.is_empty() {
+        return Err(Error::NotImplementedYet(
+            "arguments of relationships".to_string(),
+        ));
+    }
+
+    let source_info = env.lookup_fields_info(&current_table.name)?;
+    let (target_name, gsi) = root::parse_collection_name(&relationship.target_collection)?;
+    let target_collection = env.lookup_collection(&target_name)?;
+    let CollectionInfo::Table { info, .. } = &target_collection else {
+        return Err(Error::NotImplementedYet(
+            "relationships to native queries".to_string(),
+        ));
+    };
+
+    let (limit, returns_field, select_set, rowset) = root::translate_query(
+        env,
+        state,
+        &root::MakeFrom::Collection {
+            name: target_name.clone(),
+            gsi: gsi.clone(),
+            arguments: BTreeMap::new(),
+        },
+        &None,
+        query,
+    )?;
+    let select = sql::helpers::select_rowset(select_set, &returns_field);
+    let mut select = sql::rewrites::constant_folding::normalize_select(select);
+
+    let target_reference = match &select.from {
+        Some(sql::ast::From::Table { alias, .. }) => {
+            sql::ast::TableReference::AliasedTable(alias.clone())
+        }
+        _ => {
+            return Err(Error::UnexpectedStructure(
+                "relationship query not reading a table".to_string(),
+            ))
+        }
+    };
+
+    let mut source_columns = vec![];
+    let mut join = vec![];
+    for (source_column, target_column) in &relationship.column_mapping {
+        let source = source_info.lookup_column(source_column)?.name;
+        let target = target_collection.lookup_column(target_column)?.name;
+        let source_column = sql::helpers::make_column(
+            current_table.reference.clone(),
+            source.clone(),
+            sql::helpers::make_column_alias(source.0.clone()),
+        );
+        let (alias, target_expression) = sql::helpers::make_column(
+            target_reference.clone(),
+            target.clone(),
+            sql::helpers::make_column_alias(target.0.clone()),
+        );
+        // the executor groups the target items by their join attributes
+        if let sql::ast::SelectList::SelectList(columns) = &mut select.select_list {
+            columns.push((alias, target_expression.clone()));
+        }
+        join.push(execution_plan::JoinColumn {
+            source: source.0,
+            target: target.0,
+            source_column: source_column.1.clone(),
+            target_column: target_expression,
+        });
+        source_columns.push(source_column);
+    }
+
+    // Each batch of join keys is read with a Query, which needs the partition key of the target
+    // to be one of the join attributes. Any other join would scan the whole target per batch.
+    if let Some(partition_key) = access_pattern::partition_key(info, gsi.as_ref()) {
+        if !join.iter().any(|column| column.target == partition_key) {
+            return Err(Error::RelationshipNotOnPartitionKey {
+                relationship: relationship_name.clone(),
+                target_collection: relationship.target_collection.clone(),
+                partition_key: partition_key.to_string(),
+            });
+        }
+    }
+
+    // DynamoDB runs the query with the join attributes fixed by the join keys of each batch.
+    let join_condition = join
+        .iter()
+        .fold(sql::helpers::true_expr(), |condition, column| {
+            sql::ast::Expression::And {
+                left: Box::new(condition),
+                right: Box::new(sql::ast::Expression::BinaryOperation {
+                    left: Box::new(column.target_column.clone()),
+                    operator: sql::ast::BinaryOperator("=".to_string()),
+                    right: Box::new(sql::ast::Expression::Value(sql::ast::Value::BatchKey(0))),
+                }),
+            }
+        });
+    let access_pattern = access_pattern::analyse(
+        &target_name,
+        info,
+        gsi.as_ref(),
+        &sql::ast::Where(sql::ast::Expression::And {
+            left: Box::new(select.where_.0.clone()),
+            right: Box::new(join_condition),
+        }),
+    );
+    let order_by = sorting::plan_order_by(Some(*info), gsi.as_ref(), &mut select)?;
+    let document_paths = fields::merge_document_paths(&mut select);
+
+    let execution_plan::ExecutionPlan {
+        query: target_query,
+        ..
+    } = execution_plan::simple_query_execution_plan(
+        None,
+        relationship.target_collection.clone(),
+        select,
+        limit,
+        query.offset,
+        None,
+        access_pattern,
+        rowset,
+        document_paths,
+        order_by,
+    );
+
+    Ok((
+        source_columns,
+        execution_plan::Field::Relationship(Box::new(execution_plan::RelationshipQuery {
+            query: target_query,
+            join,
+        })),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use query_engine_sql::sql::execution_plan::{AccessPattern, Field, RelationshipQuery};
+    use serde_json::json;
+
+    use crate::translation::error::Error;
+    use crate::translation::test_helpers::{metadata, query_request, translate};
+
+    /// Translate a query on `source` selecting a column of `target` across a relationship with
+    /// the given column mapping, and return the relationship query.
+    fn relationship_query(
+        source: &str,
+        target: &str,
+        column: &str,
+        column_mapping: serde_json::Value,
+    ) -> Result<RelationshipQuery, Error> {
+        let mut request = query_request(
+            source,
+            json!({
+                "fields": {
+                    "related": {
+                        "type": "relationship",
+                        "relationship": "related",
+                        "arguments": {},
+                        "query": {
+                            "fields": { column: { "type": "column", "column": column } },
+                        },
+                    },
+                },
+            }),
+        );
+        request["collection_relationships"] = json!({
+            "related": {
+                "column_mapping": column_mapping,
+                "relationship_type": "array",
+                "target_collection": target,
+                "arguments": {},
+            },
+        });
+        let plan = translate(&metadata(), request)?;
+        let fields = plan.query.rowset.fields.unwrap_or_default();
+        match fields.into_iter().find(|(alias, _)| alias == "related") {
+            Some((_, Field::Relationship(relationship))) => Ok(*relationship),
+            field => panic!("not a relationship field: {field:?}"),
+        }
+    }
+
+    #[test]
+    fn test_relationship_on_the_partition_key_is_a_query() {
+        let relationship =
+            relationship_query("users", "orders", "total", json!({ "id": "user_id" })).unwrap();
+
+        assert_eq!(relationship.join.len(), 1);
+        assert_eq!(relationship.join[0].source, "id");
+        assert_eq!(relationship.join[0].target, "user_id");
+        assert_eq!(
+            relationship.query.access_pattern,
+            AccessPattern::Query {
+                table: "orders".to_string(),
+                index: None,
+            }
+        );
+        // the join attribute is read to group the target items
+        let sql = relationship.query.query_sql().sql;
+        assert!(sql.contains("user_id"), "{sql}");
+    }
+
+    #[test]
+    fn test_relationship_on_the_partition_key_of_an_index() {
+        let relationship = relationship_query(
+            "orders",
+            "users:by_email",
+            "name",
+            json!({ "status": "email" }),
+        )
+        .unwrap();
+
+        assert_eq!(
+            relationship.query.access_pattern,
+            AccessPattern::Query {
+                table: "users".to_string(),
+                index: Some("by_email".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_relationship_not_on_the_partition_key_is_rejected() {
+        let result = relationship_query("users", "orders", "total", json!({ "name": "status" }));
+        assert!(
+            matches!(
+                result,
+                Err(Error::RelationshipNotOnPartitionKey { ref partition_key, .. })
+                    if partition_key == "user_id"
+            ),
+            "{result:?}"
+        );
+
+        // the sort key alone is not enough either
+        let result = relationship_query("users", "orders", "total", json!({ "id": "order_id" }));
+        assert!(
+            matches!(result, Err(Error::RelationshipNotOnPartitionKey { .. })),
+            "{result:?}"
+        );
+    }
+}
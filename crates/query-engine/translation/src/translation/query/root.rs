@@ -7,38 +7,46 @@ use indexmap::IndexMap;
 use ndc_models as models;
 use query_engine_sql::sql::ast::ReturnsFields;
 
+use super::aggregates;
 use super::fields;
 use super::filtering;
+use super::native_queries;
 use super::sorting;
 use crate::translation::error::Error;
 use crate::translation::helpers::{
     CollectionInfo, Env, RootAndCurrentTables, State, TableNameAndReference,
 };
 use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan;
 
 /// Translate a query to sql ast.
-/// We return a select set with a SQL query for the two components - the rows and the aggregates.
+/// We return a select set with a SQL query reading the items for both components - the rows and
+/// the aggregates - and a description of how to build them from the items.
 pub fn translate_query(
     env: &Env,
     state: &mut State,
     make_from: &MakeFrom,
     join_predicate: &Option<JoinPredicate<'_, '_>>,
     query_request: &models::Query,
-) -> Result<(Option<u32>, ReturnsFields, sql::helpers::SelectSet), Error> {
-    // translate rows selection.
-    let (returns_field, row_select) =
+) -> Result<
+    (
+        Option<u32>,
+        ReturnsFields,
+        sql::helpers::SelectSet,
+        execution_plan::Rowset,
+    ),
+    Error,
+> {
+    // translate rows selection, reading the attributes the aggregates need as well.
+    let (returns_field, row_select, rowset) =
         translate_rows_select(env, state, make_from, join_predicate, query_request)?;
 
-    // // translate aggregate selection.
-    // let aggregate_select =
-    //     translate_aggregate_select(env, state, make_from, join_predicate, query_request)?;
-
-    // Create a structure describing the selection set - only rows, only aggregates, or both.
-    let (_, rows) = (&returns_field, row_select);
+    // Create a structure describing the selection set.
     let select_set = (
         query_request.limit,
         returns_field,
-        sql::helpers::SelectSet::Rows(rows),
+        sql::helpers::SelectSet::Rows(row_select),
+        rowset,
     );
 
     Ok(select_set)
@@ -51,8 +59,14 @@ fn translate_rows_select(
     make_from: &MakeFrom,
     join_predicate: &Option<JoinPredicate<'_, '_>>,
     query: &models::Query,
-) -> Result<(ReturnsFields, sql::ast::Select), Error> {
+) -> Result<(ReturnsFields, sql::ast::Select, execution_plan::Rowset), Error> {
     let (current_table, from_clause) = make_reference_and_from_clause(env, state, make_from)?;
+    // PartiQL cannot filter the items of another statement
+    if matches!(from_clause, sql::ast::From::NativeQuery { .. }) && query.predicate.is_some() {
+        return Err(Error::NotImplementedYet(
+            "filters on native queries".to_string(),
+        ));
+    }
 
     // translate fields to select list
     let fields = query.fields.clone().unwrap_or_default();
@@ -66,20 +80,57 @@ fn translate_rows_select(
         ReturnsFields::FieldsWereRequested
     };
 
-    let mut fields_select = match returns_fields {
+    // translate aggregates, which the executor computes over the items read.
+    let aggregates = query
+        .aggregates
+        .as_ref()
+        .map(|aggregates| aggregates::translate_aggregates(env, &current_table, aggregates))
+        .transpose()?;
+    let aggregated_columns = aggregates
+        .as_deref()
+        .map(|aggregates| aggregates::aggregated_columns(&current_table, aggregates))
+        .unwrap_or_default();
+
+    let (mut fields_select, row_fields) = match returns_fields {
         ReturnsFields::FieldsWereRequested => {
-            fields::translate_fields(env, state, fields, &current_table, from_clause)?
+            let (mut select, row_fields) =
+                fields::translate_fields(env, state, fields, &current_table, from_clause)?;
+            if let sql::ast::SelectList::SelectList(columns) = &mut select.select_list {
+                columns.extend(aggregated_columns.into_iter().filter(|(alias, _)| {
+                    !row_fields.iter().any(|(_, field)| match field {
+                        execution_plan::Field::Column {
+                            attribute,
+                            fields: None,
+                        }
+                        | execution_plan::Field::PrefixedColumn { attribute, .. }
+                        | execution_plan::Field::KeyTemplateField { attribute, .. } => {
+                            attribute == &alias.name
+                        }
+                        execution_plan::Field::Column {
+                            fields: Some(_), ..
+                        }
+                        | execution_plan::Field::Cursor
+                        | execution_plan::Field::Relationship(_) => false,
+                    })
+                }));
+                // Only cursors were requested, but the items still have to be read.
+                if columns.is_empty() {
+                    select.select_list = sql::ast::SelectList::SelectStar;
+                }
+            }
+            (select, row_fields)
         }
         ReturnsFields::NoFieldsWereRequested => {
-            let select_1 = sql::ast::SelectList::Select1;
-
-            sql::ast::Select {
-                with: sql::helpers::empty_with(),
-                select_list: select_1,
-                from: Some(from_clause),
-                where_: sql::ast::Where(sql::helpers::empty_where()),
-                order_by: sql::helpers::empty_order_by(),
-            }
+            // DynamoDB cannot select a constant, so read the whole items when
+            // we only need to count them.
+            let select = if aggregated_columns.is_empty() {
+                sql::helpers::star_select(from_clause)
+            } else {
+                let mut select = sql::helpers::simple_select(aggregated_columns);
+                select.from = Some(from_clause);
+                select
+            };
+            (select, vec![])
         }
     };
 
@@ -93,7 +144,12 @@ fn translate_rows_select(
         &mut fields_select,
     )?;
 
-    Ok((returns_fields, fields_select))
+    let rowset = execution_plan::Rowset {
+        fields: query.fields.as_ref().map(|_| row_fields),
+        aggregates,
+    };
+
+    Ok((returns_fields, fields_select, rowset))
 }
 
 /// Translate the lion (or common) part of 'rows' or 'aggregates' part of a query.
@@ -129,17 +185,37 @@ pub fn translate_query_part(
         }
     }?;
 
-    select.where_ = sql::ast::Where(filter);
+    select.where_ = sql::ast::Where(filtering::with_discriminator(env, current_table, filter)?);
 
     select.order_by = order_by;
 
     Ok(())
 }
 
+/// Split a collection name into the table and, for collections of the form `table:index`, the
+/// global secondary index it reads.
+pub fn parse_collection_name(
+    collection: &models::CollectionName,
+) -> Result<(models::CollectionName, Option<sql::ast::Gsi>), Error> {
+    let collection_string = collection.as_str();
+    if collection_string.contains(':') {
+        let split: Vec<&str> = collection_string.split(':').collect();
+        if split.len() != 2 {
+            return Err(Error::InvalidCollectionName(collection_string.to_string()));
+        };
+        let collection_name = models::CollectionName::new(split[0].into());
+        let gsi_name = split[1];
+        Ok((collection_name, Some(sql::ast::Gsi(gsi_name.to_string()))))
+    } else {
+        Ok((models::CollectionName::new(collection_string.into()), None))
+    }
+}
+
 /// Create a from clause from a collection name and its reference.
 pub fn make_from_clause_and_reference(
     collection_name: &models::CollectionName,
     gsi_name: Option<sql::ast::Gsi>,
+    arguments: &BTreeMap<models::ArgumentName, models::Argument>,
     env: &Env,
     state: &mut State,
     collection_alias: Option<sql::ast::TableAlias>,
@@ -150,7 +226,14 @@ pub fn make_from_clause_and_reference(
     };
     // find the table according to the metadata.
     let collection_info = env.lookup_collection(collection_name)?;
-    let from_clause = make_from_clause(state, &collection_alias, &collection_info, gsi_name);
+    let from_clause = make_from_clause(
+        env,
+        state,
+        &collection_alias,
+        &collection_info,
+        gsi_name,
+        arguments,
+    )?;
 
     let collection_alias_name = sql::ast::TableReference::AliasedTable(collection_alias);
     let current_table = TableNameAndReference {
@@ -161,23 +244,35 @@ pub fn make_from_clause_and_reference(
 }
 
 /// Build a FROM clause from a collection info and an alias.
-/// Will add a Native Query to the 'State' if the collection is a native query.
+/// Native queries are inlined with their arguments.
 fn make_from_clause(
+    env: &Env,
     _state: &mut State,
     current_table_alias: &sql::ast::TableAlias,
     collection_info: &CollectionInfo,
     gsi_name: Option<sql::ast::Gsi>,
-) -> sql::ast::From {
+    arguments: &BTreeMap<models::ArgumentName, models::Argument>,
+) -> Result<sql::ast::From, Error> {
     match collection_info {
         CollectionInfo::Table { info, .. } => {
             let db_table = sql::ast::TableReference::DBTable {
                 table: sql::ast::TableName(info.table_name.clone()),
                 gsi: gsi_name,
             };
-            sql::ast::From::Table {
+            Ok(sql::ast::From::Table {
                 reference: db_table,
                 alias: current_table_alias.clone(),
+            })
+        }
+        CollectionInfo::NativeQuery { name, info } => {
+            // native queries have no indexes
+            if let Some(sql::ast::Gsi(gsi)) = gsi_name {
+                return Err(Error::InvalidCollectionName(format!("{name}:{gsi}")));
             }
+            Ok(sql::ast::From::NativeQuery {
+                sql: native_queries::translate(env, info, arguments)?,
+                alias: current_table_alias.clone(),
+            })
         }
     }
 }
@@ -219,8 +314,8 @@ fn make_reference_and_from_clause(
         MakeFrom::Collection {
             name,
             gsi,
-            arguments: _,
-        } => make_from_clause_and_reference(name, gsi.clone(), env, state, None),
+            arguments,
+        } => make_from_clause_and_reference(name, gsi.clone(), arguments, env, state, None),
         MakeFrom::TableReference { name, reference } => {
             let table_alias = state.make_table_alias(name.to_string());
             let from_clause = sql::ast::From::Table {
@@ -7,10 +7,15 @@ use ndc_models as models;
 
 use crate::translation::error::Error;
 use crate::translation::helpers::{
-    wrap_in_field_path, CollectionInfo, Env, FieldPath, RootAndCurrentTables, State,
+    translate_field_path, CollectionInfo, Env, FieldPath, RootAndCurrentTables, State,
     TableNameAndReference,
 };
+use query_engine_metadata::metadata;
 use query_engine_sql::sql;
+use query_engine_sql::sql::execution_plan;
+
+use super::access_pattern;
+use super::fields;
 
 // Top-level //
 
@@ -56,6 +61,67 @@ pub fn translate_order_by(
     }
 }
 
+/// Decide who orders the results of a select over a table, or over a native query when there is
+/// no table.
+///
+/// DynamoDB can only order by the sort key of the table or index, and only within a single
+/// partition. Such orderings stay in the statement and run as an ordered Query. Any other ordering
+/// is removed from the statement and returned, for the executor to sort the items in memory.
+pub fn plan_order_by(
+    table: Option<&metadata::TableInfo>,
+    gsi: Option<&sql::ast::Gsi>,
+    select: &mut sql::ast::Select,
+) -> Result<Vec<execution_plan::OrderByElement>, Error> {
+    let elements = select
+        .order_by
+        .elements
+        .iter()
+        .map(|element| match fields::document_path(&element.target) {
+            Some(mut field_path) => {
+                let attribute = field_path.remove(0);
+                Ok(execution_plan::OrderByElement {
+                    attribute,
+                    field_path,
+                    direction: element.direction.clone(),
+                })
+            }
+            None => Err(match &element.target {
+                sql::ast::Expression::NestedElementSelect { .. } => {
+                    Error::NotImplementedYet("ordering by elements of lists".to_string())
+                }
+                _ => Error::UnexpectedStructure("order by target that is not a column".to_string()),
+            }),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let is_native = match elements.as_slice() {
+        [] => true,
+        [element] => table
+            .and_then(|table| access_pattern::native_order_key(table, gsi, &select.where_))
+            .is_some_and(|sort_key| sort_key == element.attribute && element.field_path.is_empty()),
+        _ => false,
+    };
+
+    if is_native {
+        return Ok(vec![]);
+    }
+
+    // The executor needs the attributes it sorts by, whether they were requested or not.
+    // Those read already are merged with the requested ones afterwards.
+    let order_by = std::mem::replace(&mut select.order_by, sql::helpers::empty_order_by());
+    if let sql::ast::SelectList::SelectList(columns) = &mut select.select_list {
+        for element in order_by.elements {
+            let alias = sql::helpers::make_column_alias(
+                fields::document_path(&element.target)
+                    .unwrap_or_default()
+                    .join("."),
+            );
+            columns.push((alias, element.target));
+        }
+    }
+    Ok(elements)
+}
+
 // Types //
 
 /// Group columns or aggregates with the same path element.
@@ -241,14 +307,11 @@ fn translate_order_by_target_group(
         // The column is from the source table, we just need to query it directly.
         ColumnsOrSelect::Columns(columns) => Ok(columns
             .into_iter()
-            .map(|(i, direction, field_path, column_name)| {
+            .map(|(i, direction, target)| {
                 (
                     i,
                     sql::ast::OrderByElement {
-                        target: wrap_in_field_path(
-                            &field_path,
-                            sql::ast::Expression::ColumnReference(column_name),
-                        ),
+                        target,
                         direction: match direction {
                             models::OrderDirection::Asc => sql::ast::OrderByDirection::Asc,
                             models::OrderDirection::Desc => sql::ast::OrderByDirection::Desc,
@@ -267,10 +330,9 @@ enum ColumnsOrSelect {
     /// Columns represents target columns that are referenced from the current table.
     Columns(
         Vec<(
-            usize,                     // The global order by index for this column.
-            models::OrderDirection,    // The order direction.
-            FieldPath,                 // The nested field path.
-            sql::ast::ColumnReference, // A reference for this column.
+            usize,                  // The global order by index for this column.
+            models::OrderDirection, // The order direction.
+            sql::ast::Expression,   // A reference for this column, down its nested field path.
         )>,
     ),
 }
@@ -313,22 +375,13 @@ fn build_select_and_joins_for_order_by_group(
                 // If the path is empty, we don't need to build a query, just return the columns.
                 let table = env.lookup_collection(&root_and_current_tables.current_table.name)?;
                 let columns = translate_targets(
+                    env,
                     &table,
                     &root_and_current_tables.current_table,
                     element_group,
                 )?
                 .into_iter()
-                .map(|column| {
-                    (
-                        column.index,
-                        column.direction,
-                        column.field_path,
-                        sql::ast::ColumnReference::AliasedColumn {
-                            table: root_and_current_tables.current_table.reference.clone(),
-                            column: column.alias,
-                        },
-                    )
-                })
+                .map(|column| (column.index, column.direction, column.target))
                 .collect();
                 Ok(ColumnsOrSelect::Columns(columns))
             }
@@ -337,7 +390,9 @@ fn build_select_and_joins_for_order_by_group(
     // If we query a relationship, build a wrapping select query selecting the requested columns/aggregates
     // for the order by, and build a select of all the joins to select from.
     else {
-        todo!("relationship order by");
+        Err(Error::NotImplementedYet(
+            "ordering by columns or aggregates of relationships".to_string(),
+        ))
     }
 }
 
@@ -345,18 +400,24 @@ fn build_select_and_joins_for_order_by_group(
 struct OrderBySelectExpression {
     index: usize,
     direction: models::OrderDirection,
-    field_path: FieldPath,
-    alias: sql::ast::ColumnAlias,
+    target: sql::ast::Expression,
 }
 
 /// Take an element group and convert all of the elements we want to select
 /// to aliases and expressions, along with their order by direction and their index
 /// in the order by list.
 fn translate_targets(
+    env: &Env,
     target_collection: &CollectionInfo,
-    _table: &TableNameAndReference,
+    table: &TableNameAndReference,
     element_group: &OrderByElementGroup,
 ) -> Result<Vec<OrderBySelectExpression>, Error> {
+    let column_reference = |alias: sql::ast::ColumnAlias| {
+        sql::ast::Expression::ColumnReference(sql::ast::ColumnReference::AliasedColumn {
+            table: table.reference.clone(),
+            column: alias,
+        })
+    };
     match element_group {
         OrderByElementGroup::Columns { columns, path: _ } => {
             let columns = columns
@@ -369,12 +430,18 @@ fn translate_targets(
                     let selected_column_alias =
                         sql::helpers::make_column_alias(selected_column.name.0);
 
-                    // we use the real name of the column as an alias as well.
+                    // we use the real name of the column as an alias as well, and check the
+                    // nested field path against the nested types.
+                    let (target, _) = translate_field_path(
+                        env,
+                        field_path,
+                        column_reference(selected_column_alias),
+                        &selected_column.r#type,
+                    )?;
                     Ok::<OrderBySelectExpression, Error>(OrderBySelectExpression {
                         index: element.index,
                         direction: element.direction,
-                        alias: selected_column_alias.clone(),
-                        field_path: field_path.clone(),
+                        target,
                     })
                 })
                 .collect::<Result<Vec<_>, Error>>()?;
@@ -391,9 +458,7 @@ fn translate_targets(
                             Ok(OrderBySelectExpression {
                                 index: element.index,
                                 direction: element.direction,
-                                alias: column_alias,
-                                // Aggregates do not have a field path.
-                                field_path: (&None).into(),
+                                target: column_reference(column_alias),
                             })
                         }
                         Aggregate::SingleColumnAggregate { column } => {
@@ -406,9 +471,7 @@ fn translate_targets(
                             Ok(OrderBySelectExpression {
                                 index: element.index,
                                 direction: element.direction,
-                                alias: selected_column_alias.clone(),
-                                // Aggregates do not have a field path.
-                                field_path: (&None).into(),
+                                target: column_reference(selected_column_alias),
                             })
                         }
                     }
@@ -419,3 +482,100 @@ fn translate_targets(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use query_engine_sql::sql;
+    use query_engine_sql::sql::execution_plan::{ExecutionPlan, OrderByElement, Query};
+    use serde_json::json;
+
+    use crate::translation::error::Error;
+    use crate::translation::test_helpers::{compare, metadata, query_request, translate};
+
+    fn ordered_query(
+        fields: serde_json::Value,
+        predicate: Option<serde_json::Value>,
+        column: &str,
+        field_path: &[&str],
+        direction: &str,
+    ) -> Result<ExecutionPlan<Query>, Error> {
+        let mut query = json!({
+            "fields": fields,
+            "order_by": {
+                "elements": [{
+                    "order_direction": direction,
+                    "target": {
+                        "type": "column",
+                        "name": column,
+                        "field_path": field_path,
+                        "path": [],
+                    },
+                }],
+            },
+        });
+        if let Some(predicate) = predicate {
+            query["predicate"] = predicate;
+        }
+        translate(&metadata(), query_request("users", query))
+    }
+
+    fn id_field() -> serde_json::Value {
+        json!({ "id": { "type": "column", "column": "id" } })
+    }
+
+    #[test]
+    fn test_order_by_a_field_of_a_map_is_sorted_in_memory() {
+        let plan = ordered_query(id_field(), None, "address", &["city"], "desc").unwrap();
+
+        assert_eq!(
+            plan.query.order_by,
+            [OrderByElement {
+                attribute: "address".to_string(),
+                field_path: vec!["city".to_string()],
+                direction: sql::ast::OrderByDirection::Desc,
+            }]
+        );
+        // the statement reads the field to sort by, and leaves the ordering to the executor
+        let sql = plan.query.query_sql().sql;
+        assert!(sql.contains("address.city"), "{sql}");
+        assert!(!sql.contains("ORDER BY"), "{sql}");
+        assert_eq!(plan.query.document_paths, [["address", "city"]]);
+    }
+
+    #[test]
+    fn test_order_by_a_field_of_a_map_read_whole() {
+        let fields = json!({
+            "id": { "type": "column", "column": "id" },
+            "address": { "type": "column", "column": "address" },
+        });
+        let plan = ordered_query(fields, None, "address", &["city"], "asc").unwrap();
+
+        assert_eq!(plan.query.order_by.len(), 1);
+        assert!(plan.query.document_paths.is_empty());
+    }
+
+    #[test]
+    fn test_order_by_the_sort_key_of_a_partition_runs_in_dynamodb() {
+        let predicate = compare("id", "_eq", json!("a"));
+        let plan = ordered_query(id_field(), Some(predicate), "created", &[], "asc").unwrap();
+
+        assert!(plan.query.order_by.is_empty());
+        let sql = plan.query.query_sql().sql;
+        assert!(sql.contains("ORDER BY"), "{sql}");
+    }
+
+    #[test]
+    fn test_order_by_invalid_field_paths_is_rejected() {
+        let result = ordered_query(id_field(), None, "address", &["country"], "asc");
+        assert!(
+            matches!(result, Err(Error::FieldNotFoundInCompositeType(..))),
+            "{result:?}"
+        );
+
+        let result = ordered_query(id_field(), None, "tags", &["0"], "asc");
+        assert!(
+            matches!(result, Err(Error::NotImplementedYet(_))),
+            "{result:?}"
+        );
+    }
+}
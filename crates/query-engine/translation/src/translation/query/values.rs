@@ -1,8 +1,10 @@
 //! Handle the translation of literal values.
 
+use crate::translation::mutation::translate::attribute_type;
 use crate::translation::{error::Error, helpers::Env, helpers::State};
 use ndc_models as models;
 use query_engine_metadata::metadata::database;
+use query_engine_metadata::metadata::CustomScalarType;
 use query_engine_sql::sql;
 use query_engine_sql::sql::ast::{ColumnReference, Expression, Value};
 use query_engine_sql::sql::helpers::simple_select;
@@ -14,6 +16,7 @@ pub fn translate_json_value(
     value: &serde_json::Value,
     r#type: &database::Type,
 ) -> Result<sql::ast::Expression, Error> {
+    check_custom_scalar_value(r#type, value)?;
     match (value, r#type) {
         (serde_json::Value::Null, _) => Ok(Expression::Cast {
             expression: Box::new(Expression::Value(Value::Null)),
@@ -46,6 +49,25 @@ pub fn translate_json_value(
     }
 }
 
+/// Check that a value of a custom scalar type, such as an ISO-8601 `Timestamp` or a `UUID`, has
+/// the format of the type.
+pub fn check_custom_scalar_value(
+    r#type: &database::Type,
+    value: &serde_json::Value,
+) -> Result<(), Error> {
+    let database::Type::ScalarType(scalar_type_name) = r#type else {
+        return Ok(());
+    };
+    let Some(custom_scalar) = CustomScalarType::from_name(scalar_type_name.as_str()) else {
+        return Ok(());
+    };
+    if custom_scalar.is_valid_value(value) {
+        Ok(())
+    } else {
+        Err(Error::TypeMismatch(value.clone(), scalar_type_name.clone()))
+    }
+}
+
 /// Translate a NDC 'Type' to an SQL scalar type.
 fn type_to_ast_scalar_type(env: &Env, typ: &database::Type) -> Result<sql::ast::ScalarType, Error> {
     match typ {
@@ -75,33 +97,31 @@ fn type_to_ast_scalar_type_name(
                 scalar_type.type_name.to_string(),
             ))
         }
+        query_engine_metadata::metadata::Type::CompositeType(t) => {
+            Ok(sql::ast::ScalarTypeName::Unqualified(t.to_string()))
+        }
     }
 }
 
-/// Convert a variable into a SQL value.
+/// Convert a variable into a parameter of the statement, bound to its value in each variable
+/// set when the statement runs, as the attribute type of the values it is compared with.
 pub fn translate_variable(
     env: &Env,
-    state: &mut State,
-    variables_table: sql::ast::TableReference,
     variable: &models::VariableName,
     r#type: &database::Type,
 ) -> Result<sql::ast::Expression, Error> {
-    let variables_reference = Expression::ColumnReference(ColumnReference::AliasedColumn {
-        table: variables_table,
-        column: sql::helpers::make_column_alias(sql::helpers::VARIABLES_FIELD.to_string()),
-    });
-
-    // We use the binop '->' to project (as jsonb) the value of a variable from the data column of
-    // the variable table.
-    let projected_variable_exp = sql::ast::Expression::BinaryOperation {
-        left: Box::new(variables_reference),
-        operator: sql::ast::BinaryOperator("->".to_string()),
-        right: Box::new(sql::ast::Expression::Value(sql::ast::Value::String(
-            variable.to_string(),
-        ))),
-    };
-
-    translate_projected_variable(env, state, r#type, projected_variable_exp)
+    env.check_variables_available()?;
+    Ok(Expression::Value(Value::Variable {
+        name: variable.to_string(),
+        attribute_type: attribute_type(r#type),
+        key_prefix: None,
+        custom_scalar: match r#type {
+            database::Type::ScalarType(scalar_type_name) => {
+                CustomScalarType::from_name(scalar_type_name.as_str())
+            }
+            database::Type::ArrayType(_) | database::Type::CompositeType(_) => None,
+        },
+    }))
 }
 
 /// Produce a SQL expression that translates an expression of Postgres type 'jsonb' into a given
@@ -160,19 +180,21 @@ pub fn translate_projected_variable(
 
             sql::ast::Expression::CorrelatedSubSelect(Box::new(result_select))
         }
-        database::Type::ScalarType(_) => sql::ast::Expression::Cast {
-            expression: Box::new(sql::ast::Expression::BinaryOperation {
-                left: Box::new(exp),
-                operator: sql::ast::BinaryOperator("#>>".to_string()),
-                right: Box::new(sql::ast::Expression::Cast {
-                    expression: Box::new(sql::ast::Expression::Value(sql::ast::Value::Array(
-                        vec![],
-                    ))),
-                    r#type: sql::ast::ScalarType::ArrayType(sql::helpers::text_type_name()),
+        database::Type::ScalarType(_) | database::Type::CompositeType(_) => {
+            sql::ast::Expression::Cast {
+                expression: Box::new(sql::ast::Expression::BinaryOperation {
+                    left: Box::new(exp),
+                    operator: sql::ast::BinaryOperator("#>>".to_string()),
+                    right: Box::new(sql::ast::Expression::Cast {
+                        expression: Box::new(sql::ast::Expression::Value(sql::ast::Value::Array(
+                            vec![],
+                        ))),
+                        r#type: sql::ast::ScalarType::ArrayType(sql::helpers::text_type_name()),
+                    }),
                 }),
-            }),
-            r#type: type_to_ast_scalar_type(env, r#type)?,
-        },
+                r#type: type_to_ast_scalar_type(env, r#type)?,
+            }
+        }
     };
 
     Ok(result)
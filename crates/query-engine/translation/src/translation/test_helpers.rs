@@ -0,0 +1,149 @@
+//! Metadata and requests shared by the unit tests of the translation.
+
+use ndc_dynamodb_configuration::introspection::make_scalar_type;
+use query_engine_metadata::metadata;
+use query_engine_sql::sql;
+
+use crate::translation::error::Error;
+
+/// A `users` table keyed on `id` and `created`, with a map, lists and an index on `email`, and
+/// an `orders` table keyed on `user_id` and `order_id`.
+pub fn metadata() -> metadata::Metadata {
+    let mut metadata: metadata::Metadata = serde_json::from_value(serde_json::json!({
+        "tables": {
+            "users": {
+                "tableName": "users",
+                "columns": {
+                    "id": { "name": "id", "type": { "scalarType": "String" } },
+                    "created": { "name": "created", "type": { "scalarType": "Number" } },
+                    "name": { "name": "name", "type": { "scalarType": "String" } },
+                    "email": { "name": "email", "type": { "scalarType": "String" } },
+                    "age": { "name": "age", "type": { "scalarType": "Number" } },
+                    "active": { "name": "active", "type": { "scalarType": "Boolean" } },
+                    "address": { "name": "address", "type": { "compositeType": "users_address" } },
+                    "tags": { "name": "tags", "type": { "arrayType": { "scalarType": "String" } } },
+                    "matrix": {
+                        "name": "matrix",
+                        "type": { "arrayType": { "arrayType": { "scalarType": "Number" } } },
+                    },
+                },
+                "partitionKey": "id",
+                "sortKey": "created",
+                "gsi": {
+                    "by_email": {
+                        "partition_key": "email",
+                        "sort_key": null,
+                        "projection_type": { "projection_type": "ALL", "non_key_attributes": [] },
+                    },
+                },
+            },
+            "orders": {
+                "tableName": "orders",
+                "columns": {
+                    "user_id": { "name": "user_id", "type": { "scalarType": "String" } },
+                    "order_id": { "name": "order_id", "type": { "scalarType": "String" } },
+                    "total": { "name": "total", "type": { "scalarType": "Number" } },
+                    "status": { "name": "status", "type": { "scalarType": "String" } },
+                },
+                "partitionKey": "user_id",
+                "sortKey": "order_id",
+                "gsi": {},
+            },
+        },
+        "scalarTypes": {},
+        "compositeTypes": {
+            "users_address": {
+                "typeName": "users_address",
+                "fields": {
+                    "city": { "fieldName": "city", "type": { "scalarType": "String" } },
+                    "zip": { "fieldName": "zip", "type": { "scalarType": "String" } },
+                },
+            },
+        },
+    }))
+    .unwrap();
+    for scalar_type_name in ["String", "Number", "Binary", "Boolean", "Any"] {
+        metadata.scalar_types.0.insert(
+            scalar_type_name.into(),
+            make_scalar_type(&scalar_type_name.into()),
+        );
+    }
+    metadata
+}
+
+/// The metadata of [`metadata`] with a single-table design: an `app` table keyed on `PK` and
+/// `SK`, holding `customer` items told apart by their `type` attribute and `order` items told
+/// apart by the `ORDER#` prefix of their sort key.
+pub fn entity_metadata() -> metadata::Metadata {
+    let mut metadata = metadata();
+    let app: metadata::TableInfo = serde_json::from_value(serde_json::json!({
+        "tableName": "app",
+        "columns": {
+            "PK": { "name": "PK", "type": { "scalarType": "String" } },
+            "SK": { "name": "SK", "type": { "scalarType": "String" } },
+            "type": { "name": "type", "type": { "scalarType": "String" } },
+        },
+        "partitionKey": "PK",
+        "sortKey": "SK",
+        "gsi": {},
+    }))
+    .unwrap();
+    metadata.tables.0.insert("app".into(), app);
+    metadata.entities = serde_json::from_value(serde_json::json!({
+        "customer": {
+            "table": "app",
+            "discriminator": { "attributeValue": { "attribute": "type", "value": "customer" } },
+            "columns": {
+                "id": { "name": "PK", "type": { "scalarType": "String" } },
+                "name": { "name": "name", "type": { "scalarType": "String" } },
+            },
+            "keyPrefixes": { "PK": "CUSTOMER#" },
+        },
+        "order": {
+            "table": "app",
+            "discriminator": { "keyPrefix": { "attribute": "SK" } },
+            "columns": {
+                "customer_id": { "name": "PK", "type": { "scalarType": "String" } },
+                "order_id": { "name": "SK", "type": { "scalarType": "String" } },
+                "total": { "name": "total", "type": { "scalarType": "Number" } },
+            },
+            "keyPrefixes": { "PK": "CUSTOMER#", "SK": "ORDER#" },
+        },
+    }))
+    .unwrap();
+    ndc_dynamodb_configuration::convert_metadata(metadata)
+}
+
+/// Translate a query request given as json.
+pub fn translate(
+    metadata: &metadata::Metadata,
+    request: serde_json::Value,
+) -> Result<sql::execution_plan::ExecutionPlan<sql::execution_plan::Query>, Error> {
+    crate::translation::query::translate(metadata, serde_json::from_value(request).unwrap())
+}
+
+/// A query request on a collection, without arguments, relationships or variables.
+pub fn query_request(collection: &str, query: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "collection": collection,
+        "query": query,
+        "arguments": {},
+        "collection_relationships": {},
+    })
+}
+
+/// A comparison of a column of the current collection with a literal value.
+pub fn compare(column: &str, operator: &str, value: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "type": "binary_comparison_operator",
+        "column": { "type": "column", "name": column, "path": [] },
+        "operator": operator,
+        "value": { "type": "scalar", "value": value },
+    })
+}
+
+/// Translate a query on a collection of the test metadata, and render its statement.
+pub fn query_sql(collection: &str, query: serde_json::Value) -> Result<sql::string::SQL, Error> {
+    let plan = translate(&metadata(), query_request(collection, query))?;
+    Ok(plan.query.query_sql())
+}